@@ -0,0 +1,29 @@
+; Steps the backdrop colour once a frame from NMI, the picture after frame N is colour N - 3
+; NROM-128 with an empty CHR bank, assembled with src/asm into backdrop.nes
+        .org $8000
+reset:
+        sei
+        ldx #$FF
+        txs
+vblank1:
+        bit $2002
+        bpl vblank1
+vblank2:
+        bit $2002
+        bpl vblank2
+        lda #$80
+        sta $2000
+forever:
+        jmp forever
+nmi:
+        inc $00
+        lda #$3F
+        sta $2006
+        lda #$00
+        sta $2006
+        lda $00
+        and #$3F
+        sta $2007
+        rti
+        .org $BFFA
+        .word nmi, reset, reset
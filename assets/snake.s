@@ -0,0 +1,239 @@
+; Nick Morgan's snake from easy6502, steer with W A S D
+; The screen is 32 x 32 bytes at $0200-$05FF, $FE reads a random byte, $FF the last key
+        .org $0600
+
+appleL = $00            ; screen location of the apple
+appleH = $01
+snakeHeadL = $10        ; screen location of the head
+snakeHeadH = $11
+snakeBodyStart = $12    ; body segments follow the head in pairs
+snakeDirection = $02
+snakeLength = $03       ; in bytes, two per segment
+
+; One bit per direction
+movingUp = 1
+movingRight = 2
+movingDown = 4
+movingLeft = 8
+
+ASCII_w = $77
+ASCII_a = $61
+ASCII_s = $73
+ASCII_d = $64
+
+sysRandom = $FE
+sysLastKey = $FF
+
+        jsr init
+        jsr loop
+
+init:
+        jsr initSnake
+        jsr generateApplePosition
+        rts
+
+initSnake:
+        lda #movingRight
+        sta snakeDirection
+        lda #4                  ; two segments
+        sta snakeLength
+        lda #$11
+        sta snakeHeadL
+        lda #$10
+        sta snakeBodyStart
+        lda #$0F
+        sta $14                 ; body segment 1
+        lda #$04
+        sta snakeHeadH
+        sta $13                 ; body segment 1
+        sta $15                 ; body segment 2
+        rts
+
+; Low byte anywhere, high byte 2 to 5
+generateApplePosition:
+        lda sysRandom
+        sta appleL
+        lda sysRandom
+        and #$03
+        clc
+        adc #2
+        sta appleH
+        rts
+
+loop:
+        jsr readKeys
+        jsr checkCollision
+        jsr updateSnake
+        jsr drawApple
+        jsr drawSnake
+        jsr spinWheels
+        jmp loop
+
+readKeys:
+        lda sysLastKey
+        cmp #ASCII_w
+        beq upKey
+        cmp #ASCII_d
+        beq rightKey
+        cmp #ASCII_s
+        beq downKey
+        cmp #ASCII_a
+        beq leftKey
+        rts
+upKey:
+        lda #movingDown
+        bit snakeDirection
+        bne illegalMove
+        lda #movingUp
+        sta snakeDirection
+        rts
+rightKey:
+        lda #movingLeft
+        bit snakeDirection
+        bne illegalMove
+        lda #movingRight
+        sta snakeDirection
+        rts
+downKey:
+        lda #movingUp
+        bit snakeDirection
+        bne illegalMove
+        lda #movingDown
+        sta snakeDirection
+        rts
+leftKey:
+        lda #movingRight
+        bit snakeDirection
+        bne illegalMove
+        lda #movingLeft
+        sta snakeDirection
+        rts
+illegalMove:
+        rts
+
+checkCollision:
+        jsr checkAppleCollision
+        jsr checkSnakeCollision
+        rts
+
+checkAppleCollision:
+        lda appleL
+        cmp snakeHeadL
+        bne doneCheckingAppleCollision
+        lda appleH
+        cmp snakeHeadH
+        bne doneCheckingAppleCollision
+        ; Eat the apple and grow a segment
+        inc snakeLength
+        inc snakeLength
+        jsr generateApplePosition
+doneCheckingAppleCollision:
+        rts
+
+checkSnakeCollision:
+        ldx #2                  ; start with the second segment
+snakeCollisionLoop:
+        lda snakeHeadL,x
+        cmp snakeHeadL
+        bne continueCollisionLoop
+maybeCollided:
+        lda snakeHeadH,x
+        cmp snakeHeadH
+        beq didCollide
+continueCollisionLoop:
+        inx
+        inx
+        cpx snakeLength         ; reached the tail without a collision
+        beq didntCollide
+        jmp snakeCollisionLoop
+didCollide:
+        jmp gameOver
+didntCollide:
+        rts
+
+updateSnake:
+        ldx snakeLength
+        dex
+        txa
+updateloop:
+        lda snakeHeadL,x
+        sta snakeBodyStart,x
+        dex
+        bpl updateloop
+
+        lda snakeDirection
+        lsr
+        bcs up
+        lsr
+        bcs right
+        lsr
+        bcs down
+        lsr
+        bcs left
+up:
+        lda snakeHeadL
+        sec
+        sbc #$20
+        sta snakeHeadL
+        bcc upup
+        rts
+upup:
+        dec snakeHeadH
+        lda #$01
+        cmp snakeHeadH
+        beq collision
+        rts
+right:
+        inc snakeHeadL
+        lda #$1F
+        bit snakeHeadL
+        beq collision
+        rts
+down:
+        lda snakeHeadL
+        clc
+        adc #$20
+        sta snakeHeadL
+        bcs downdown
+        rts
+downdown:
+        inc snakeHeadH
+        lda #$06
+        cmp snakeHeadH
+        beq collision
+        rts
+left:
+        dec snakeHeadL
+        lda snakeHeadL
+        and #$1F
+        cmp #$1F
+        beq collision
+        rts
+collision:
+        jmp gameOver
+
+drawApple:
+        ldy #0
+        lda sysRandom
+        sta (appleL),y
+        rts
+
+drawSnake:
+        ldx snakeLength
+        lda #0
+        sta (snakeHeadL,x)      ; erase the end of the tail
+        ldx #0
+        lda #1
+        sta (snakeHeadL,x)      ; paint the head
+        rts
+
+spinWheels:
+        ldx #0
+spinloop:
+        nop
+        nop
+        dex
+        bne spinloop
+        rts
+
+gameOver:
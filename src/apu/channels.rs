@@ -0,0 +1,504 @@
+// Length counter load values, indexed by the top five bits of $4003 / $4007 / $400B / $400F
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1]
+];
+
+const TRIANGLE_TABLE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+];
+
+// NTSC periods in CPU cycles
+const NOISE_PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+const DMC_RATES: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+
+pub(crate) struct Envelope {
+    start: bool,
+    divider: u8,
+    decay: u8,
+    volume: u8,
+    constant: bool,
+    looping: bool,
+}
+
+impl Envelope {
+
+    fn new() -> Envelope {
+        Envelope {
+            start: false,
+            divider: 0,
+            decay: 0,
+            volume: 0,
+            constant: false,
+            looping: false
+        }
+    }
+
+    fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 > 0;
+        self.constant = data & 0x10 > 0;
+        self.volume = data & 0x0F;
+    }
+
+    // Quarter frame
+    fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.constant { self.volume } else { self.decay }
+    }
+}
+
+pub(crate) struct LengthCounter {
+    pub(crate) value: u8,
+    enabled: bool,
+    halt: bool,
+}
+
+impl LengthCounter {
+
+    fn new() -> LengthCounter {
+        LengthCounter {
+            value: 0,
+            enabled: false,
+            halt: false
+        }
+    }
+
+    fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    // Half frame
+    fn clock(&mut self) {
+        if !self.halt && self.value > 0 {
+            self.value -= 1;
+        }
+    }
+}
+
+/**
+ * Pulse channel, also used for the two MMC5 pulses which have no sweep unit
+ */
+pub(crate) struct Pulse {
+    // Pulse 1 negates with ones' complement, pulse 2 with twos' complement
+    ones_complement: bool,
+    has_sweep: bool,
+    duty: u8,
+    sequence: u8,
+    timer_period: u16,
+    timer: u16,
+    pub(crate) envelope: Envelope,
+    pub(crate) length: LengthCounter,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+
+    pub(crate) fn new(ones_complement: bool, has_sweep: bool) -> Pulse {
+        Pulse {
+            ones_complement,
+            has_sweep,
+            duty: 0,
+            sequence: 0,
+            timer_period: 0,
+            timer: 0,
+            envelope: Envelope::new(),
+            length: LengthCounter::new(),
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false
+        }
+    }
+
+    pub(crate) fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.duty = data >> 6;
+                self.length.halt = data & 0x20 > 0;
+                self.envelope.write(data);
+            }
+            1 => {
+                self.sweep_enabled = data & 0x80 > 0;
+                self.sweep_period = (data >> 4) & 0x07;
+                self.sweep_negate = data & 0x08 > 0;
+                self.sweep_shift = data & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((data & 0x07) as u16) << 8);
+                self.length.load(data >> 3);
+                self.sequence = 0;
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+        if self.sweep_negate {
+            let change = change + if self.ones_complement { 1 } else { 0 };
+            self.timer_period.saturating_sub(change)
+        } else {
+            self.timer_period + change
+        }
+    }
+
+    fn muted(&self) -> bool {
+        self.timer_period < 8 || (self.has_sweep && self.sweep_target() > 0x07FF)
+    }
+
+    // Every second CPU cycle
+    pub(crate) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence = (self.sequence + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(crate) fn clock_quarter(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub(crate) fn clock_half(&mut self) {
+        self.length.clock();
+        if !self.has_sweep {
+            return;
+        }
+
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.timer_period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    pub(crate) fn output(&self) -> u8 {
+        if self.length.value == 0 || self.muted() || DUTY_TABLE[self.duty as usize][self.sequence as usize] == 0 {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+pub(crate) struct Triangle {
+    timer_period: u16,
+    timer: u16,
+    sequence: u8,
+    pub(crate) length: LengthCounter,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    // Same bit as the length counter halt flag
+    control: bool,
+}
+
+impl Triangle {
+
+    pub(crate) fn new() -> Triangle {
+        Triangle {
+            timer_period: 0,
+            timer: 0,
+            sequence: 0,
+            length: LengthCounter::new(),
+            linear_reload_value: 0,
+            linear_counter: 0,
+            linear_reload: false,
+            control: false
+        }
+    }
+
+    pub(crate) fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.control = data & 0x80 > 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = data & 0x7F;
+            }
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((data & 0x07) as u16) << 8);
+                self.length.load(data >> 3);
+                self.linear_reload = true;
+            }
+            _ => {}
+        }
+    }
+
+    // Every CPU cycle
+    pub(crate) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            if self.length.value > 0 && self.linear_counter > 0 {
+                self.sequence = (self.sequence + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(crate) fn clock_quarter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub(crate) fn clock_half(&mut self) {
+        self.length.clock();
+    }
+
+    pub(crate) fn output(&self) -> u8 {
+        TRIANGLE_TABLE[self.sequence as usize]
+    }
+}
+
+pub(crate) struct Noise {
+    shift: u16,
+    short_mode: bool,
+    timer_period: u16,
+    timer: u16,
+    pub(crate) envelope: Envelope,
+    pub(crate) length: LengthCounter,
+}
+
+impl Noise {
+
+    pub(crate) fn new() -> Noise {
+        Noise {
+            shift: 1,
+            short_mode: false,
+            timer_period: NOISE_PERIODS[0],
+            timer: 0,
+            envelope: Envelope::new(),
+            length: LengthCounter::new()
+        }
+    }
+
+    pub(crate) fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.length.halt = data & 0x20 > 0;
+                self.envelope.write(data);
+            }
+            2 => {
+                self.short_mode = data & 0x80 > 0;
+                self.timer_period = NOISE_PERIODS[(data & 0x0F) as usize];
+            }
+            3 => {
+                self.length.load(data >> 3);
+                self.envelope.start = true;
+            }
+            _ => {}
+        }
+    }
+
+    // Every CPU cycle
+    pub(crate) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            let tap = if self.short_mode { 6 } else { 1 };
+            let feedback = (self.shift & 0x01) ^ ((self.shift >> tap) & 0x01);
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(crate) fn clock_quarter(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub(crate) fn clock_half(&mut self) {
+        self.length.clock();
+    }
+
+    pub(crate) fn output(&self) -> u8 {
+        if self.shift & 0x01 > 0 || self.length.value == 0 {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+/**
+ * Delta modulation channel
+ *
+ * The channel cannot read memory itself, the bus polls request() and hands the byte
+ * back through fill().
+ */
+pub(crate) struct Dmc {
+    irq_enabled: bool,
+    pub(crate) irq_flag: bool,
+    looping: bool,
+    rate: u16,
+    timer: u16,
+    output_level: u8,
+    sample_addr: u16,
+    sample_length: u16,
+    current_addr: u16,
+    pub(crate) bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+}
+
+impl Dmc {
+
+    pub(crate) fn new() -> Dmc {
+        Dmc {
+            irq_enabled: false,
+            irq_flag: false,
+            looping: false,
+            rate: DMC_RATES[0],
+            timer: 0,
+            output_level: 0,
+            sample_addr: 0xC000,
+            sample_length: 1,
+            current_addr: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silence: true
+        }
+    }
+
+    pub(crate) fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.irq_enabled = data & 0x80 > 0;
+                if !self.irq_enabled {
+                    self.irq_flag = false;
+                }
+                self.looping = data & 0x40 > 0;
+                self.rate = DMC_RATES[(data & 0x0F) as usize];
+            }
+            1 => self.output_level = data & 0x7F,
+            2 => self.sample_addr = 0xC000 + data as u16 * 64,
+            _ => self.sample_length = data as u16 * 16 + 1
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.irq_flag = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_addr = self.sample_addr;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    // Address of the next sample byte when the buffer is empty
+    pub(crate) fn request(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            return Some(self.current_addr);
+        }
+        None
+    }
+
+    pub(crate) fn fill(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        self.current_addr = if self.current_addr == 0xFFFF { 0x8000 } else { self.current_addr + 1 };
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_flag = true;
+            }
+        }
+    }
+
+    // Every CPU cycle
+    pub(crate) fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.rate - 1;
+
+        if !self.silence {
+            if self.shift & 0x01 > 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift = data;
+                }
+                None => self.silence = true
+            }
+        }
+    }
+
+    pub(crate) fn output(&self) -> u8 {
+        self.output_level
+    }
+}
@@ -0,0 +1,246 @@
+use crate::apu::pulse_level;
+
+// Modulation table entries, 4 resets the counter
+const MOD_STEPS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+
+// Master volume from $4089 as a fraction of full scale
+const MASTER_VOLUMES: [f32; 4] = [1.0, 2.0 / 3.0, 2.0 / 4.0, 2.0 / 5.0];
+
+struct FdsEnvelope {
+    disabled: bool,
+    increase: bool,
+    speed: u8,
+    gain: u8,
+    timer: u32,
+}
+
+impl FdsEnvelope {
+
+    fn new() -> FdsEnvelope {
+        FdsEnvelope {
+            disabled: true,
+            increase: false,
+            speed: 0,
+            gain: 0,
+            timer: 0
+        }
+    }
+
+    fn write(&mut self, data: u8) {
+        self.disabled = data & 0x80 > 0;
+        self.increase = data & 0x40 > 0;
+        self.speed = data & 0x3F;
+        if self.disabled {
+            self.gain = data & 0x3F;
+        }
+        self.timer = 0;
+    }
+
+    fn clock(&mut self, master_speed: u8) {
+        if self.disabled {
+            return;
+        }
+        let period = 8 * (self.speed as u32 + 1) * master_speed as u32;
+        self.timer += 1;
+        if self.timer < period {
+            return;
+        }
+        self.timer = 0;
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+    }
+}
+
+/**
+ * FDS expansion audio, a 64 step wavetable with a volume envelope and a frequency
+ * modulation unit
+ *
+ * $4040-$407F  wavetable, 6 bit samples, writable while $4089 bit 7 is set
+ * $4080        volume envelope
+ * $4082/$4083  wave frequency, $4083 bit 7 halts the wave and bit 6 the envelopes
+ * $4084        modulation envelope
+ * $4085        modulation counter
+ * $4086/$4087  modulation frequency, $4087 bit 7 halts it and opens the table
+ * $4088        modulation table input
+ * $4089        wavetable write enable and master volume
+ * $408A        envelope speed
+ * $4090/$4092  volume and modulation gain
+ */
+pub(crate) struct FdsAudio {
+    wave: [u8; 64],
+    wave_write: bool,
+    master_volume: u8,
+    wave_frequency: u16,
+    wave_halt: bool,
+    envelope_halt: bool,
+    wave_accumulator: u32,
+    wave_output: u8,
+
+    volume: FdsEnvelope,
+    modulation: FdsEnvelope,
+    envelope_speed: u8,
+
+    mod_table: [u8; 64],
+    mod_position: u8,
+    mod_counter: i8,
+    mod_frequency: u16,
+    mod_halt: bool,
+    mod_accumulator: u32,
+}
+
+impl FdsAudio {
+
+    pub(crate) fn new() -> FdsAudio {
+        FdsAudio {
+            wave: [0; 64],
+            wave_write: false,
+            master_volume: 0,
+            wave_frequency: 0,
+            wave_halt: true,
+            envelope_halt: true,
+            wave_accumulator: 0,
+            wave_output: 0,
+            volume: FdsEnvelope::new(),
+            modulation: FdsEnvelope::new(),
+            envelope_speed: 0xE8,
+            mod_table: [0; 64],
+            mod_position: 0,
+            mod_counter: 0,
+            mod_frequency: 0,
+            mod_halt: true,
+            mod_accumulator: 0
+        }
+    }
+
+    pub(crate) fn read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x4040..=0x407F => Some(if self.wave_write {
+                self.wave[(addr - 0x4040) as usize]
+            } else {
+                self.wave[self.wave_position()]
+            }),
+            0x4090 => Some(self.volume.gain),
+            0x4092 => Some(self.modulation.gain),
+            _ => None
+        }
+    }
+
+    pub(crate) fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4040..=0x407F => {
+                if self.wave_write {
+                    self.wave[(addr - 0x4040) as usize] = data & 0x3F;
+                }
+            }
+            0x4080 => self.volume.write(data),
+            0x4082 => self.wave_frequency = (self.wave_frequency & 0x0F00) | data as u16,
+            0x4083 => {
+                self.wave_frequency = (self.wave_frequency & 0x00FF) | (((data & 0x0F) as u16) << 8);
+                self.wave_halt = data & 0x80 > 0;
+                self.envelope_halt = data & 0x40 > 0;
+                if self.wave_halt {
+                    self.wave_accumulator = 0;
+                }
+            }
+            0x4084 => self.modulation.write(data),
+            0x4085 => self.mod_counter = ((data & 0x7F) << 1) as i8 >> 1,
+            0x4086 => self.mod_frequency = (self.mod_frequency & 0x0F00) | data as u16,
+            0x4087 => {
+                self.mod_frequency = (self.mod_frequency & 0x00FF) | (((data & 0x0F) as u16) << 8);
+                self.mod_halt = data & 0x80 > 0;
+                if self.mod_halt {
+                    self.mod_accumulator = 0;
+                }
+            }
+            0x4088 => {
+                // Each write fills two entries and moves the position along
+                if self.mod_halt {
+                    self.mod_table[self.mod_position as usize] = data & 0x07;
+                    self.mod_table[(self.mod_position + 1) as usize & 0x3F] = data & 0x07;
+                    self.mod_position = (self.mod_position + 2) & 0x3F;
+                }
+            }
+            0x4089 => {
+                self.wave_write = data & 0x80 > 0;
+                self.master_volume = data & 0x03;
+            }
+            0x408A => self.envelope_speed = data,
+            _ => {}
+        }
+    }
+
+    fn wave_position(&self) -> usize {
+        ((self.wave_accumulator >> 16) & 0x3F) as usize
+    }
+
+    // Wave frequency bent by the modulation unit, as described on the NESdev wiki
+    fn modulated_frequency(&self) -> i32 {
+        let pitch = self.wave_frequency as i32;
+        let mut temp = self.mod_counter as i32 * self.modulation.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if self.mod_counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= pitch;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        (pitch + temp).max(0)
+    }
+
+    fn clock_modulation(&mut self) {
+        if self.mod_halt || self.mod_frequency == 0 {
+            return;
+        }
+        self.mod_accumulator += self.mod_frequency as u32;
+        if self.mod_accumulator < 0x10000 {
+            return;
+        }
+        self.mod_accumulator &= 0xFFFF;
+
+        let entry = self.mod_table[self.mod_position as usize];
+        self.mod_counter = if entry == 4 {
+            0
+        } else {
+            // 7 bit signed wrap around
+            ((self.mod_counter.wrapping_add(MOD_STEPS[entry as usize]) as u8) << 1) as i8 >> 1
+        };
+        self.mod_position = (self.mod_position + 1) & 0x3F;
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if !self.envelope_halt && !self.wave_halt && self.envelope_speed != 0 {
+            self.volume.clock(self.envelope_speed);
+            self.modulation.clock(self.envelope_speed);
+        }
+
+        self.clock_modulation();
+
+        if !self.wave_halt {
+            self.wave_accumulator = (self.wave_accumulator + self.modulated_frequency() as u32) & 0x3F_FFFF;
+        }
+        // The output holds its last value while the table is being written
+        if !self.wave_write {
+            self.wave_output = self.wave[self.wave_position()];
+        }
+    }
+
+    // A full scale wave is mixed at about 2.4 times a full volume 2A03 pulse
+    pub(crate) fn output(&self) -> f32 {
+        let gain = self.volume.gain.min(32) as f32;
+        let level = self.wave_output as f32 / 63.0 * gain / 32.0 * MASTER_VOLUMES[self.master_volume as usize];
+        level * 2.4 * pulse_level(15.0)
+    }
+}
@@ -0,0 +1,85 @@
+use crate::apu::channels::Pulse;
+use crate::apu::pulse_level;
+
+// The MMC5 clocks its own envelopes and length counters at a fixed 240 Hz
+const FRAME_PERIOD: u32 = 7457;
+
+/**
+ * MMC5 expansion audio, two 2A03 style pulses without sweep and an 8 bit PCM channel
+ *
+ * $5000-$5003  pulse 1
+ * $5004-$5007  pulse 2
+ * $5010        PCM mode, bit 0 read mode, bit 7 IRQ enable
+ * $5011        PCM data in write mode
+ * $5015        pulse enable / length status
+ *
+ * PCM read mode, where the channel samples CPU reads from $8000-$BFFF, is not emulated.
+ * No released game is known to use it.
+ */
+pub(crate) struct Mmc5Audio {
+    pulses: [Pulse; 2],
+    pcm_read_mode: bool,
+    pcm: u8,
+    frame_cycle: u32,
+    odd_cycle: bool,
+}
+
+impl Mmc5Audio {
+
+    pub(crate) fn new() -> Mmc5Audio {
+        Mmc5Audio {
+            pulses: [Pulse::new(false, false), Pulse::new(false, false)],
+            pcm_read_mode: false,
+            pcm: 0,
+            frame_cycle: 0,
+            odd_cycle: false
+        }
+    }
+
+    pub(crate) fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5003 => self.pulses[0].write(addr, data),
+            0x5004..=0x5007 => self.pulses[1].write(addr, data),
+            0x5010 => self.pcm_read_mode = data & 0x01 > 0,
+            0x5011 => {
+                // Zero is ignored, in read mode it would raise the IRQ
+                if !self.pcm_read_mode && data != 0 {
+                    self.pcm = data;
+                }
+            }
+            0x5015 => {
+                self.pulses[0].length.set_enabled(data & 0x01 > 0);
+                self.pulses[1].length.set_enabled(data & 0x02 > 0);
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn read_status(&self) -> u8 {
+        (if self.pulses[0].length.value > 0 { 0x01 } else { 0 }) | (if self.pulses[1].length.value > 0 { 0x02 } else { 0 })
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if self.odd_cycle {
+            self.pulses[0].clock_timer();
+            self.pulses[1].clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.frame_cycle += 1;
+        if self.frame_cycle >= FRAME_PERIOD {
+            self.frame_cycle = 0;
+            for pulse in self.pulses.iter_mut() {
+                pulse.clock_quarter();
+                pulse.clock_half();
+            }
+        }
+    }
+
+    // Pulses match the 2A03 pulses, PCM at full scale is close to the DMC at full scale
+    pub(crate) fn output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pcm = self.pcm as f32 / 255.0 * (159.79 / (22638.0 / 127.0 + 100.0));
+        pulse_level(pulses) + pcm
+    }
+}
@@ -0,0 +1,230 @@
+pub(crate) mod channels;
+pub(crate) mod vrc6_audio;
+pub(crate) mod sunsoft5b_audio;
+pub(crate) mod n163_audio;
+pub(crate) mod mmc5_audio;
+pub(crate) mod vrc7_audio;
+pub(crate) mod fds_audio;
+#[cfg(test)]
+mod tests;
+
+use crate::apu::channels::{Dmc, Noise, Pulse, Triangle};
+
+pub const CPU_CLOCK_RATE: f64 = 1_789_773.0;
+pub const SAMPLE_RATE: f64 = 44_100.0;
+
+// Output level of one 2A03 pulse channel at a given volume, played on its own. Expansion
+// chips scale their output against this so they sit at the right level in the mix
+pub(crate) fn pulse_level(volume: f32) -> f32 {
+    if volume <= 0.0 {
+        return 0.0;
+    }
+    95.88 / (8128.0 / volume + 100.0)
+}
+
+/**
+ * 2A03 APU
+ *
+ * $4000-$4003  pulse 1
+ * $4004-$4007  pulse 2
+ * $4008-$400B  triangle
+ * $400C-$400F  noise
+ * $4010-$4013  DMC
+ * $4015        channel enable / status
+ * $4017        frame counter
+ *
+ * Clocked once per CPU cycle. Output is mixed with the non linear formulas from the 2A03
+ * resistor network, expansion audio from the cartridge is added on top and the result is
+ * averaged down to SAMPLE_RATE.
+ */
+pub struct Apu {
+    pub(crate) pulse1: Pulse,
+    pub(crate) pulse2: Pulse,
+    pub(crate) triangle: Triangle,
+    pub(crate) noise: Noise,
+    pub(crate) dmc: Dmc,
+
+    five_step: bool,
+    irq_inhibit: bool,
+    // Cleared by reading $4015
+    frame_irq: bool,
+    frame_cycle: u32,
+    odd_cycle: bool,
+
+    sample_clock: f64,
+    sample_sum: f32,
+    sample_count: u32,
+    samples: Vec<f32>,
+}
+
+impl Default for Apu {
+
+    fn default() -> Apu {
+        Apu::new()
+    }
+}
+
+impl Apu {
+
+    pub fn new() -> Apu {
+        Apu {
+            pulse1: Pulse::new(true, true),
+            pulse2: Pulse::new(false, true),
+            triangle: Triangle::new(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            frame_cycle: 0,
+            odd_cycle: false,
+            sample_clock: 0.0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            samples: vec![]
+        }
+    }
+
+    pub(crate) fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse1.write(addr, data),
+            0x4004..=0x4007 => self.pulse2.write(addr, data),
+            0x4008..=0x400B => self.triangle.write(addr, data),
+            0x400C..=0x400F => self.noise.write(addr, data),
+            0x4010..=0x4013 => self.dmc.write(addr, data),
+            0x4015 => {
+                self.pulse1.length.set_enabled(data & 0x01 > 0);
+                self.pulse2.length.set_enabled(data & 0x02 > 0);
+                self.triangle.length.set_enabled(data & 0x04 > 0);
+                self.noise.length.set_enabled(data & 0x08 > 0);
+                self.dmc.set_enabled(data & 0x10 > 0);
+            }
+            0x4017 => {
+                self.five_step = data & 0x80 > 0;
+                self.irq_inhibit = data & 0x40 > 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+                self.frame_cycle = 0;
+                if self.five_step {
+                    self.clock_quarter();
+                    self.clock_half();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Reading $4015 acknowledges the frame interrupt
+    pub(crate) fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
+        self.frame_irq = false;
+        return status;
+    }
+
+    pub(crate) fn peek_status(&self) -> u8 {
+        let mut status = 0x00;
+        if self.pulse1.length.value > 0 { status |= 0x01; }
+        if self.pulse2.length.value > 0 { status |= 0x02; }
+        if self.triangle.length.value > 0 { status |= 0x04; }
+        if self.noise.length.value > 0 { status |= 0x08; }
+        if self.dmc.bytes_remaining > 0 { status |= 0x10; }
+        if self.frame_irq { status |= 0x40; }
+        if self.dmc.irq_flag { status |= 0x80; }
+        return status;
+    }
+
+    pub(crate) fn irq_state(&self) -> bool {
+        self.frame_irq || self.dmc.irq_flag
+    }
+
+    fn clock_quarter(&mut self) {
+        self.pulse1.clock_quarter();
+        self.pulse2.clock_quarter();
+        self.triangle.clock_quarter();
+        self.noise.clock_quarter();
+    }
+
+    fn clock_half(&mut self) {
+        self.pulse1.clock_half();
+        self.pulse2.clock_half();
+        self.triangle.clock_half();
+        self.noise.clock_half();
+    }
+
+    // Frame sequencer steps, in CPU cycles
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+        match self.frame_cycle {
+            7457 | 22371 => self.clock_quarter(),
+            14913 => {
+                self.clock_quarter();
+                self.clock_half();
+            }
+            29829 if !self.five_step => {
+                self.clock_quarter();
+                self.clock_half();
+                if !self.irq_inhibit {
+                    self.frame_irq = true;
+                }
+            }
+            29830 if !self.five_step => self.frame_cycle = 0,
+            37281 => {
+                self.clock_quarter();
+                self.clock_half();
+            }
+            37282 => self.frame_cycle = 0,
+            _ => {}
+        }
+    }
+
+    /**
+     * Steps every channel by one CPU cycle. expansion is the cartridge's audio output for
+     * the same cycle, already scaled against the 2A03 channels.
+     */
+    pub(crate) fn clock(&mut self, expansion: f32) {
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+        self.clock_frame_counter();
+
+        self.sample_sum += self.output() + expansion;
+        self.sample_count += 1;
+        self.sample_clock += SAMPLE_RATE;
+        if self.sample_clock >= CPU_CLOCK_RATE {
+            self.sample_clock -= CPU_CLOCK_RATE;
+            self.samples.push(self.sample_sum / self.sample_count as f32);
+            self.sample_sum = 0.0;
+            self.sample_count = 0;
+        }
+    }
+
+    // Mixed 2A03 output, 0.0 to about 1.0
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulse1.output() + self.pulse2.output()) as f32;
+        let pulse_out = pulse_level(pulse);
+
+        let tnd = self.triangle.output() as f32 / 8227.0
+            + self.noise.output() as f32 / 12241.0
+            + self.dmc.output() as f32 / 22638.0;
+        let tnd_out = if tnd > 0.0 { 159.79 / (1.0 / tnd + 100.0) } else { 0.0 };
+
+        pulse_out + tnd_out
+    }
+
+    // Samples produced since the last call, at SAMPLE_RATE
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.cpu_write(0x4015, 0x00);
+        self.frame_irq = false;
+        self.frame_cycle = 0;
+    }
+}
@@ -0,0 +1,112 @@
+use crate::apu::pulse_level;
+
+/**
+ * Namco 163 expansion audio, up to eight wavetable channels in 128 bytes of internal RAM
+ *
+ * $F800 sets the RAM address (bit 7 auto increments) and $4800 reads or writes it. Channel
+ * n keeps its registers in the last 64 bytes at $40 + n * 8:
+ *
+ * +0 / +2 / +4  18 bit frequency, +4 also holds the wave length as 256 - (value & $FC)
+ * +1 / +3 / +5  24 bit phase
+ * +6            wave start, in 4 bit samples
+ * +7            volume, and at $7F the number of enabled channels minus one in bits 4-6
+ *
+ * The chip updates one channel every 15 CPU cycles and plays them one after another, the
+ * output here is the average over the enabled channels.
+ */
+pub(crate) struct N163Audio {
+    pub(crate) ram: [u8; 128],
+    address: u8,
+    auto_increment: bool,
+    cycle: u8,
+    current: u8,
+    outputs: [f32; 8],
+}
+
+impl N163Audio {
+
+    pub(crate) fn new() -> N163Audio {
+        N163Audio {
+            ram: [0; 128],
+            address: 0,
+            auto_increment: false,
+            cycle: 0,
+            current: 7,
+            outputs: [0.0; 8]
+        }
+    }
+
+    pub(crate) fn write_address(&mut self, data: u8) {
+        self.address = data & 0x7F;
+        self.auto_increment = data & 0x80 > 0;
+    }
+
+    pub(crate) fn write_data(&mut self, data: u8) {
+        self.ram[self.address as usize] = data;
+        self.step_address();
+    }
+
+    pub(crate) fn read_data(&mut self) -> u8 {
+        let data = self.peek_data();
+        self.step_address();
+        return data;
+    }
+
+    // The byte at the port address, which stays where it is
+    pub(crate) fn peek_data(&self) -> u8 {
+        return self.ram[self.address as usize];
+    }
+
+    fn step_address(&mut self) {
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+
+    fn enabled_channels(&self) -> u8 {
+        ((self.ram[0x7F] >> 4) & 0x07) + 1
+    }
+
+    fn update_channel(&mut self, channel: u8) {
+        let base = 0x40 + channel as usize * 8;
+        let frequency = self.ram[base] as u32
+            | (self.ram[base + 2] as u32) << 8
+            | ((self.ram[base + 4] & 0x03) as u32) << 16;
+        let length = (256 - (self.ram[base + 4] & 0xFC) as u32) << 16;
+        let mut phase = self.ram[base + 1] as u32
+            | (self.ram[base + 3] as u32) << 8
+            | (self.ram[base + 5] as u32) << 16;
+
+        phase = (phase + frequency) % length;
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+
+        let index = (((phase >> 16) + self.ram[base + 6] as u32) & 0xFF) as usize;
+        let sample = (self.ram[index >> 1] >> ((index & 0x01) * 4)) & 0x0F;
+        let volume = self.ram[base + 7] & 0x0F;
+        self.outputs[channel as usize] = (sample as f32 - 8.0) * volume as f32;
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.cycle += 1;
+        if self.cycle < 15 {
+            return;
+        }
+        self.cycle = 0;
+
+        let first = 8 - self.enabled_channels();
+        if self.current < first {
+            self.current = 7;
+        }
+        self.update_channel(self.current);
+        self.current = if self.current == first { 7 } else { self.current - 1 };
+    }
+
+    // Boards differ a lot in level, this puts a full scale channel at about a 2A03 pulse
+    pub(crate) fn output(&self) -> f32 {
+        let count = self.enabled_channels();
+        let sum: f32 = self.outputs[(8 - count) as usize..].iter().sum();
+        sum / count as f32 / 120.0 * pulse_level(15.0)
+    }
+}
@@ -0,0 +1,178 @@
+use crate::apu::pulse_level;
+
+/**
+ * Sunsoft 5B expansion audio, a YM2149F (AY-3-8910 family) with three square channels,
+ * a noise generator and a shared envelope
+ *
+ * $C000 selects the internal register, $E000 writes it:
+ *
+ * R0-R5   12 bit tone periods
+ * R6      noise period
+ * R7      mixer, bits 0-2 disable tone and bits 3-5 disable noise per channel
+ * R8-R10  channel volume, bit 4 follows the envelope instead
+ * R11-R12 envelope period
+ * R13     envelope shape, continue / attack / alternate / hold
+ *
+ * Tones toggle every 16 x period CPU cycles.
+ */
+pub(crate) struct Sunsoft5bAudio {
+    select: u8,
+    registers: [u8; 16],
+
+    prescaler: u8,
+    tone_counters: [u16; 3],
+    tone_outputs: [bool; 3],
+    noise_counter: u16,
+    noise_shift: u32,
+
+    envelope_counter: u32,
+    envelope_step: u8,
+    envelope_holding: bool,
+    envelope_attack: bool,
+}
+
+impl Sunsoft5bAudio {
+
+    pub(crate) fn new() -> Sunsoft5bAudio {
+        Sunsoft5bAudio {
+            select: 0,
+            registers: [0; 16],
+            prescaler: 0,
+            tone_counters: [0; 3],
+            tone_outputs: [false; 3],
+            noise_counter: 0,
+            noise_shift: 1,
+            envelope_counter: 0,
+            envelope_step: 0,
+            envelope_holding: false,
+            envelope_attack: false
+        }
+    }
+
+    pub(crate) fn write_select(&mut self, data: u8) {
+        self.select = data & 0x0F;
+    }
+
+    pub(crate) fn write_data(&mut self, data: u8) {
+        self.registers[self.select as usize] = data;
+        if self.select == 13 {
+            self.envelope_step = 0;
+            self.envelope_counter = 0;
+            self.envelope_holding = false;
+            self.envelope_attack = data & 0x04 > 0;
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let period = self.registers[channel * 2] as u16 | (((self.registers[channel * 2 + 1] & 0x0F) as u16) << 8);
+        period.max(1)
+    }
+
+    fn envelope_period(&self) -> u32 {
+        (self.registers[11] as u32 | ((self.registers[12] as u32) << 8)).max(1)
+    }
+
+    fn clock_envelope(&mut self) {
+        if self.envelope_holding {
+            return;
+        }
+        self.envelope_step += 1;
+        if self.envelope_step < 32 {
+            return;
+        }
+
+        let shape = self.registers[13];
+        let continues = shape & 0x08 > 0;
+        let alternate = shape & 0x02 > 0;
+        let hold = shape & 0x01 > 0;
+
+        if !continues {
+            self.envelope_holding = true;
+            self.envelope_attack = false;
+            self.envelope_step = 31;
+        } else if hold {
+            self.envelope_holding = true;
+            if alternate {
+                self.envelope_attack = !self.envelope_attack;
+            }
+            self.envelope_step = 31;
+        } else {
+            if alternate {
+                self.envelope_attack = !self.envelope_attack;
+            }
+            self.envelope_step = 0;
+        }
+    }
+
+    // 5 bit envelope level, 31 is loudest
+    fn envelope_level(&self) -> u8 {
+        if self.envelope_holding && self.registers[13] & 0x08 == 0 {
+            return 0;
+        }
+        if self.envelope_attack { self.envelope_step } else { 31 - self.envelope_step }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.prescaler += 1;
+        if self.prescaler < 16 {
+            return;
+        }
+        self.prescaler = 0;
+
+        for channel in 0..3 {
+            self.tone_counters[channel] += 1;
+            if self.tone_counters[channel] >= self.tone_period(channel) {
+                self.tone_counters[channel] = 0;
+                self.tone_outputs[channel] = !self.tone_outputs[channel];
+            }
+        }
+
+        // Noise runs at half the tone rate
+        self.noise_counter += 1;
+        if self.noise_counter >= ((self.registers[6] & 0x1F) as u16).max(1) * 2 {
+            self.noise_counter = 0;
+            let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+            self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+        }
+
+        self.envelope_counter += 1;
+        if self.envelope_counter >= self.envelope_period() {
+            self.envelope_counter = 0;
+            self.clock_envelope();
+        }
+    }
+
+    // Logarithmic, 1.5 dB per 5 bit level
+    fn amplitude(level: u8) -> f32 {
+        if level == 0 {
+            return 0.0;
+        }
+        10f32.powf(-1.5 * (31 - level) as f32 / 20.0)
+    }
+
+    // A channel at full volume is set about as loud as a full volume 2A03 pulse
+    pub(crate) fn output(&self) -> f32 {
+        let mixer = self.registers[7];
+        let noise = self.noise_shift & 0x01 > 0;
+        let mut sum = 0.0;
+
+        for channel in 0..3 {
+            let tone_on = self.tone_outputs[channel] || mixer & (0x01 << channel) > 0;
+            let noise_on = noise || mixer & (0x08 << channel) > 0;
+            if !(tone_on && noise_on) {
+                continue;
+            }
+
+            let volume = self.registers[8 + channel];
+            let level = if volume & 0x10 > 0 {
+                self.envelope_level()
+            } else if volume & 0x0F == 0 {
+                0
+            } else {
+                (volume & 0x0F) * 2 + 1
+            };
+            sum += Sunsoft5bAudio::amplitude(level);
+        }
+        sum * pulse_level(15.0)
+    }
+}
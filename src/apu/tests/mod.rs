@@ -0,0 +1,115 @@
+use crate::apu::{pulse_level, Apu, CPU_CLOCK_RATE, SAMPLE_RATE};
+
+fn run(apu: &mut Apu, cycles: u32) {
+    for _ in 0..cycles {
+        apu.clock(0.0);
+    }
+}
+
+#[test]
+fn test_length_counters_and_status() {
+    let mut apu = Apu::new();
+
+    // Loading a length while the channel is disabled does nothing
+    apu.cpu_write(0x4003, 0x08);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+
+    apu.cpu_write(0x4015, 0x0F);
+    apu.cpu_write(0x4003, 0x08);
+    apu.cpu_write(0x400B, 0x08);
+    assert_eq!(apu.read_status() & 0x0F, 0x05);
+
+    apu.cpu_write(0x4015, 0x04);
+    assert_eq!(apu.read_status() & 0x0F, 0x04);
+}
+
+#[test]
+fn test_length_counter_runs_out() {
+    let mut apu = Apu::new();
+    apu.cpu_write(0x4015, 0x01);
+
+    // Index 3 loads 2, two half frames of the 4 step sequence empty it
+    apu.cpu_write(0x4003, 0x18);
+    run(&mut apu, 14914);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+    run(&mut apu, 29830 - 14914);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+}
+
+#[test]
+fn test_frame_irq() {
+    let mut apu = Apu::new();
+
+    run(&mut apu, 29828);
+    assert!(!apu.irq_state());
+    run(&mut apu, 1);
+    assert!(apu.irq_state());
+
+    // Peeking leaves it, reading the status returns the flag once and clears it
+    assert_eq!(apu.peek_status() & 0x40, 0x40);
+    assert!(apu.irq_state());
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.irq_state());
+
+    // The 5 step sequence and the inhibit flag never raise it
+    apu.cpu_write(0x4017, 0x80);
+    run(&mut apu, 40000);
+    assert!(!apu.irq_state());
+    apu.cpu_write(0x4017, 0x40);
+    run(&mut apu, 40000);
+    assert!(!apu.irq_state());
+}
+
+#[test]
+fn test_pulse_output_level() {
+    let mut apu = Apu::new();
+    apu.cpu_write(0x4015, 0x01);
+    apu.cpu_write(0x4000, 0xBF);
+    apu.cpu_write(0x4002, 0x40);
+    apu.cpu_write(0x4003, 0x00);
+
+    // The triangle parks at step 0 and adds a constant 15 to the mix
+    let silent = Apu::new().output();
+    let mut highest: f32 = 0.0;
+    for _ in 0..2000 {
+        apu.clock(0.0);
+        highest = highest.max(apu.output() - silent);
+    }
+    assert!((highest - pulse_level(15.0)).abs() < 0.0001);
+}
+
+#[test]
+fn test_dmc_requests_sample_bytes() {
+    let mut apu = Apu::new();
+    apu.cpu_write(0x4012, 0x01);
+    apu.cpu_write(0x4013, 0x00);
+    assert_eq!(apu.dmc.request(), None);
+
+    apu.cpu_write(0x4015, 0x10);
+    assert_eq!(apu.dmc.request(), Some(0xC040));
+    assert_eq!(apu.read_status() & 0x10, 0x10);
+
+    apu.dmc.fill(0xFF);
+    assert_eq!(apu.dmc.request(), None);
+    assert_eq!(apu.read_status() & 0x10, 0x00);
+
+    // Every bit set walks the output level up
+    apu.cpu_write(0x4011, 0x40);
+    run(&mut apu, 428 * 17);
+    assert!(apu.dmc.output() > 0x40);
+}
+
+#[test]
+fn test_samples_include_expansion_audio() {
+    let mut apu = Apu::new();
+    let silent = apu.output();
+    let cycles = (CPU_CLOCK_RATE / 60.0) as u32;
+    for _ in 0..cycles {
+        apu.clock(0.25);
+    }
+
+    let samples = apu.take_samples();
+    assert!((samples.len() as f64 - SAMPLE_RATE / 60.0).abs() <= 1.0);
+    assert!(samples.iter().all(|s| (s - silent - 0.25).abs() < 0.0001));
+    assert!(apu.take_samples().is_empty());
+}
@@ -0,0 +1,179 @@
+use crate::apu::pulse_level;
+
+struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    // Ignore the duty and output the volume constantly
+    digitized: bool,
+    period: u16,
+    enabled: bool,
+    divider: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+
+    fn new() -> Vrc6Pulse {
+        Vrc6Pulse {
+            volume: 0,
+            duty: 0,
+            digitized: false,
+            period: 0,
+            enabled: false,
+            divider: 0,
+            step: 0
+        }
+    }
+
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.digitized = data & 0x80 > 0;
+                self.duty = (data >> 4) & 0x07;
+                self.volume = data & 0x0F;
+            }
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | (((data & 0x0F) as u16) << 8);
+                self.enabled = data & 0x80 > 0;
+                if !self.enabled {
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.divider == 0 {
+            self.divider = self.period >> shift;
+            self.step = (self.step + 1) & 0x0F;
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.digitized || self.step <= self.duty) {
+            return self.volume;
+        }
+        0
+    }
+}
+
+struct Vrc6Saw {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+    divider: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Saw {
+
+    fn new() -> Vrc6Saw {
+        Vrc6Saw {
+            rate: 0,
+            period: 0,
+            enabled: false,
+            divider: 0,
+            step: 0,
+            accumulator: 0
+        }
+    }
+
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | (((data & 0x0F) as u16) << 8);
+                self.enabled = data & 0x80 > 0;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    // The accumulator gains the rate on every second step and resets after seven additions
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+        self.divider = self.period >> shift;
+
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 0x01 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+/**
+ * VRC6 expansion audio, two pulses with 16 step duty and a sawtooth
+ *
+ * Registers are decoded by the mapper and arrive here as (channel, register):
+ * channel 0 is $9000-$9002, 1 is $A000-$A002, 2 is $B000-$B002 and $9003 is the
+ * frequency control (halt, and periods divided by 16 or 256).
+ */
+pub(crate) struct Vrc6Audio {
+    pulses: [Vrc6Pulse; 2],
+    saw: Vrc6Saw,
+    halt: bool,
+    shift: u8,
+}
+
+impl Vrc6Audio {
+
+    pub(crate) fn new() -> Vrc6Audio {
+        Vrc6Audio {
+            pulses: [Vrc6Pulse::new(), Vrc6Pulse::new()],
+            saw: Vrc6Saw::new(),
+            halt: false,
+            shift: 0
+        }
+    }
+
+    pub(crate) fn write(&mut self, channel: u16, register: u16, data: u8) {
+        match (channel, register) {
+            (0, 3) => {
+                self.halt = data & 0x01 > 0;
+                self.shift = if data & 0x04 > 0 { 8 } else if data & 0x02 > 0 { 4 } else { 0 };
+            }
+            (0, _) | (1, _) => self.pulses[channel as usize].write(register, data),
+            (2, _) => self.saw.write(register, data),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if self.halt {
+            return;
+        }
+        self.pulses[0].clock(self.shift);
+        self.pulses[1].clock(self.shift);
+        self.saw.clock(self.shift);
+    }
+
+    // The chip mixes linearly, a full volume pulse is about as loud as a full volume 2A03 pulse
+    pub(crate) fn output(&self) -> f32 {
+        let sum = self.pulses[0].output() + self.pulses[1].output() + self.saw.output();
+        sum as f32 * pulse_level(15.0) / 15.0
+    }
+}
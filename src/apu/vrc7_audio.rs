@@ -0,0 +1,297 @@
+use std::f64::consts::PI;
+use crate::apu::pulse_level;
+
+// The chip produces one sample every 72 cycles of its 3.58 MHz clock, 36 CPU cycles
+const FM_CYCLES: u8 = 36;
+const FM_RATE: f64 = 49_716.0;
+
+// Attenuation in dB at which an operator is treated as silent
+const SILENT: f64 = 48.0;
+
+// Built in instruments 1-15, instrument 0 is the custom patch in registers $00-$07
+const PATCHES: [[u8; 8]; 16] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06]
+];
+
+const MULTIPLIERS: [f64; 16] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0];
+
+#[derive(Clone, Copy, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Clone, Copy)]
+struct Operator {
+    phase: f64,
+    attenuation: f64,
+    state: EnvelopeState,
+}
+
+impl Operator {
+
+    fn new() -> Operator {
+        Operator {
+            phase: 0.0,
+            attenuation: SILENT,
+            state: EnvelopeState::Release
+        }
+    }
+
+    // dB per sample for a 0-63 envelope rate. The real chip steps through rate tables, this
+    // keeps the doubling every four rates and roughly its timing
+    fn step(rate: u8, seconds_at_zero: f64) -> f64 {
+        if rate < 4 {
+            return 0.0;
+        }
+        let seconds = seconds_at_zero / 2f64.powf(rate as f64 / 4.0);
+        SILENT / (seconds * FM_RATE)
+    }
+
+    fn clock_envelope(&mut self, rates: [u8; 4], sustain_level: f64, sustained: bool) {
+        match self.state {
+            EnvelopeState::Attack => {
+                if rates[0] >= 60 {
+                    self.attenuation = 0.0;
+                } else {
+                    self.attenuation -= Operator::step(rates[0], 1.7);
+                }
+                if self.attenuation <= 0.0 {
+                    self.attenuation = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.attenuation += Operator::step(rates[1], 12.0);
+                if self.attenuation >= sustain_level {
+                    self.attenuation = sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain => {
+                // Percussive tones keep decaying at the release rate while the key is held
+                if !sustained {
+                    self.attenuation += Operator::step(rates[2], 12.0);
+                }
+            }
+            EnvelopeState::Release => {
+                self.attenuation += Operator::step(rates[3], 12.0);
+            }
+        }
+        self.attenuation = self.attenuation.min(SILENT);
+    }
+
+    fn signal(&self, offset: f64, rectified: bool, extra_attenuation: f64) -> f64 {
+        let attenuation = self.attenuation + extra_attenuation;
+        if attenuation >= SILENT {
+            return 0.0;
+        }
+        let wave = (2.0 * PI * self.phase + offset).sin();
+        if rectified && wave < 0.0 {
+            return 0.0;
+        }
+        wave * 10f64.powf(-attenuation / 20.0)
+    }
+}
+
+struct FmChannel {
+    fnum: u16,
+    block: u8,
+    key_on: bool,
+    sustain_on: bool,
+    instrument: u8,
+    volume: u8,
+    operators: [Operator; 2],
+    feedback: [f64; 2],
+    output: f64,
+}
+
+impl FmChannel {
+
+    fn new() -> FmChannel {
+        FmChannel {
+            fnum: 0,
+            block: 0,
+            key_on: false,
+            sustain_on: false,
+            instrument: 0,
+            volume: 0,
+            operators: [Operator::new(), Operator::new()],
+            feedback: [0.0, 0.0],
+            output: 0.0
+        }
+    }
+
+    fn set_key(&mut self, key_on: bool) {
+        if key_on && !self.key_on {
+            for operator in self.operators.iter_mut() {
+                operator.phase = 0.0;
+                operator.state = EnvelopeState::Attack;
+            }
+        } else if !key_on && self.key_on {
+            for operator in self.operators.iter_mut() {
+                operator.state = EnvelopeState::Release;
+            }
+        }
+        self.key_on = key_on;
+    }
+}
+
+/**
+ * VRC7 expansion audio, a cut down YM2413 (OPLL) with six two operator FM channels
+ *
+ * $9010 selects the internal register and $9030 writes it:
+ *
+ * $00-$07  custom instrument
+ * $10-$15  frequency number, low 8 bits
+ * $20-$25  frequency number bit 8, octave, key on and sustain
+ * $30-$35  instrument and volume
+ *
+ * Phase and modulation follow the chip, the envelope generator and the LFOs are
+ * approximations of the real rate tables.
+ */
+pub(crate) struct Vrc7Audio {
+    select: u8,
+    custom: [u8; 8],
+    channels: [FmChannel; 6],
+    cycle: u8,
+    am_phase: f64,
+    vibrato_phase: f64,
+}
+
+impl Vrc7Audio {
+
+    pub(crate) fn new() -> Vrc7Audio {
+        Vrc7Audio {
+            select: 0,
+            custom: [0; 8],
+            channels: [FmChannel::new(), FmChannel::new(), FmChannel::new(), FmChannel::new(), FmChannel::new(), FmChannel::new()],
+            cycle: 0,
+            am_phase: 0.0,
+            vibrato_phase: 0.0
+        }
+    }
+
+    pub(crate) fn write_select(&mut self, data: u8) {
+        self.select = data;
+    }
+
+    pub(crate) fn write_data(&mut self, data: u8) {
+        let register = self.select;
+        let channel = (register & 0x0F) as usize;
+        match register {
+            0x00..=0x07 => self.custom[register as usize] = data,
+            0x10..=0x15 => self.channels[channel].fnum = (self.channels[channel].fnum & 0x100) | data as u16,
+            0x20..=0x25 => {
+                let ch = &mut self.channels[channel];
+                ch.fnum = (ch.fnum & 0xFF) | (((data & 0x01) as u16) << 8);
+                ch.block = (data >> 1) & 0x07;
+                ch.sustain_on = data & 0x20 > 0;
+                ch.set_key(data & 0x10 > 0);
+            }
+            0x30..=0x35 => {
+                self.channels[channel].instrument = data >> 4;
+                self.channels[channel].volume = data & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    fn patch(&self, instrument: u8) -> [u8; 8] {
+        if instrument == 0 { self.custom } else { PATCHES[instrument as usize] }
+    }
+
+    // Attack, decay, release while held and release after key off, as 0-63 rates
+    fn rates(patch: &[u8; 8], channel: &FmChannel, op: usize) -> [u8; 4] {
+        let ksr = patch[op] & 0x10 > 0;
+        let key_scale = ((channel.block << 1) | (channel.fnum >> 8) as u8) >> if ksr { 0 } else { 2 };
+        let rate = |r: u8| if r == 0 { 0 } else { (r * 4 + key_scale).min(63) };
+
+        let attack = patch[4 + op] >> 4;
+        let decay = patch[4 + op] & 0x0F;
+        let release = patch[6 + op] & 0x0F;
+        let sustained = patch[op] & 0x20 > 0;
+
+        let key_off = if channel.sustain_on { 5 } else if sustained { release } else { 7 };
+        [rate(attack), rate(decay), rate(release), rate(key_off)]
+    }
+
+    fn clock_sample(&mut self) {
+        self.am_phase = (self.am_phase + 3.7 / FM_RATE) % 1.0;
+        self.vibrato_phase = (self.vibrato_phase + 6.4 / FM_RATE) % 1.0;
+        let am = (1.0 - (2.0 * PI * self.am_phase).cos()) / 2.0 * 4.8;
+        let vibrato = 1.0 + 0.004 * (2.0 * PI * self.vibrato_phase).sin();
+
+        for index in 0..6 {
+            let patch = self.patch(self.channels[index].instrument);
+            let channel = &mut self.channels[index];
+            let base = channel.fnum as f64 * 2f64.powi(channel.block as i32) / 524_288.0;
+
+            for op in 0..2 {
+                let multiplier = MULTIPLIERS[(patch[op] & 0x0F) as usize];
+                let step = base * multiplier * if patch[op] & 0x40 > 0 { vibrato } else { 1.0 };
+                let sustain_level = ((patch[6 + op] >> 4) as f64 * 3.0).min(SILENT);
+                let rates = Vrc7Audio::rates(&patch, channel, op);
+                let operator = &mut channel.operators[op];
+                operator.phase = (operator.phase + step) % 1.0;
+                operator.clock_envelope(rates, sustain_level, patch[op] & 0x20 > 0);
+            }
+
+            let feedback_level = patch[3] & 0x07;
+            let feedback = if feedback_level == 0 {
+                0.0
+            } else {
+                (channel.feedback[0] + channel.feedback[1]) / 2.0 * PI / 16.0 * 2f64.powi(feedback_level as i32 - 1)
+            };
+            let mod_am = if patch[0] & 0x80 > 0 { am } else { 0.0 };
+            let total_level = (patch[2] & 0x3F) as f64 * 0.75;
+            let modulator = channel.operators[0].signal(feedback, patch[3] & 0x08 > 0, total_level + mod_am);
+            channel.feedback = [channel.feedback[1], modulator];
+
+            let car_am = if patch[1] & 0x80 > 0 { am } else { 0.0 };
+            let volume = channel.volume as f64 * 3.0;
+            channel.output = channel.operators[1].signal(modulator * 2.0 * PI, patch[3] & 0x10 > 0, volume + car_am);
+        }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.cycle += 1;
+        if self.cycle >= FM_CYCLES {
+            self.cycle = 0;
+            self.clock_sample();
+        }
+    }
+
+    pub(crate) fn silence(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.set_key(false);
+            channel.output = 0.0;
+            for operator in channel.operators.iter_mut() {
+                operator.attenuation = SILENT;
+            }
+        }
+    }
+
+    // A channel at full volume is about as loud as a full volume 2A03 pulse
+    pub(crate) fn output(&self) -> f32 {
+        let sum: f64 = self.channels.iter().map(|c| c.output).sum();
+        sum as f32 * pulse_level(15.0)
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cpu::instructions::make_instructions;
+use crate::cpu::AddressModes;
+
+#[cfg(test)]
+mod tests;
+
+/**
+ * A small two pass 6502 assembler, enough to write tests and demos as source instead of
+ * hex. One statement per line, anything after ; is a comment.
+ *
+ * loop:                    label, may share the line with a statement
+ * count = $10              constant
+ * .org $0600               continue at an address, gaps are filled with zeros
+ * .byte 1, $FF, "text"     bytes and strings
+ * .word loop, $1234        little endian words
+ *
+ * lda #$01    lda $10      lda $10,x     ldx $10,y     lda $1234     lda $1234,x
+ * lda ($10,x) lda ($10),y  jmp ($1234)   asl a         bne loop
+ *
+ * Numbers are $hex, %binary, decimal or 'c'. Operands can add and subtract, < and >
+ * take the low and high byte and * is the address of the current statement. Addresses
+ * that fit in a byte use zero page unless written with four hex digits or an a: prefix.
+ */
+pub struct Assembler {
+    unofficial: bool,
+    opcodes: HashMap<(String, AddressModes), u8>,
+    unofficial_opcodes: HashMap<(String, AddressModes), u8>,
+}
+
+// The assembled bytes, to be placed at origin
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+    pub labels: HashMap<String, u16>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// Assembles official opcodes only
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    Assembler::new().assemble(source)
+}
+
+enum Statement {
+    Org(u16),
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+    Instruction { opcode: u8, mode: AddressModes, operand: String },
+}
+
+impl Program {
+
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).cloned()
+    }
+}
+
+impl Default for Assembler {
+
+    fn default() -> Assembler {
+        Assembler::new()
+    }
+}
+
+impl Assembler {
+
+    pub fn new() -> Assembler {
+        let mut opcodes = HashMap::new();
+        let mut unofficial_opcodes = HashMap::new();
+        for (opcode, instruction) in make_instructions().iter().enumerate() {
+            let key = (instruction.name.trim_start_matches('*').to_uppercase(), instruction.addr);
+            // The first encoding wins, which picks the documented one of the duplicates
+            if instruction.name.starts_with('*') {
+                unofficial_opcodes.entry(key).or_insert(opcode as u8);
+            } else {
+                opcodes.entry(key).or_insert(opcode as u8);
+            }
+        }
+        // ca65 spells ISB this way
+        for ((name, mode), opcode) in unofficial_opcodes.clone() {
+            if name == "ISB" {
+                unofficial_opcodes.insert(("ISC".to_string(), mode), opcode);
+            }
+        }
+
+        Assembler { unofficial: false, opcodes, unofficial_opcodes }
+    }
+
+    // Accept the unofficial opcodes too, under the names the disassembler prints
+    pub fn set_unofficial(&mut self, enabled: bool) {
+        self.unofficial = enabled;
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Program, AsmError> {
+        let mut labels: HashMap<String, u16> = HashMap::new();
+        let mut statements: Vec<(usize, u16, Statement)> = vec![];
+        let mut origin = None;
+        let mut pc: u16 = 0;
+
+        // First pass, sizes and label addresses
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let error = |message: String| AsmError { line: number, message };
+            let mut text = strip_comment(line).trim();
+
+            while let Some(colon) = text.find(':') {
+                let name = text[..colon].trim();
+                if !is_identifier(name) {
+                    break;
+                }
+                define(&mut labels, name, pc).map_err(error)?;
+                text = text[colon + 1..].trim();
+            }
+            if text.is_empty() {
+                continue;
+            }
+
+            if let Some((name, value)) = text.split_once('=') {
+                let name = name.trim();
+                if is_identifier(name) {
+                    let value = evaluate(value, &labels, pc).map_err(error)?
+                        .ok_or_else(|| error(format!("{} must be defined before {}", value.trim(), name)))?;
+                    define(&mut labels, name, value).map_err(error)?;
+                    continue;
+                }
+            }
+
+            let (keyword, operand) = match text.find(char::is_whitespace) {
+                Some(space) => (&text[..space], text[space..].trim()),
+                None => (text, "")
+            };
+
+            let statement = match keyword.to_lowercase().as_str() {
+                ".org" => {
+                    let address = evaluate(operand, &labels, pc).map_err(error)?
+                        .ok_or_else(|| error(".org needs a known address".to_string()))?;
+                    if origin.is_none() {
+                        origin = Some(address);
+                    } else if address < pc {
+                        return Err(error(format!(".org ${:04X} is behind ${:04X}", address, pc)));
+                    }
+                    pc = address;
+                    Statement::Org(address)
+                }
+                ".byte" | ".db" => {
+                    let items = split_list(operand);
+                    for item in items.iter() {
+                        pc = pc.wrapping_add(string_literal(item).map_or(1, |s| s.len() as u16));
+                    }
+                    Statement::Bytes(items)
+                }
+                ".word" | ".dw" => {
+                    let items = split_list(operand);
+                    pc = pc.wrapping_add(items.len() as u16 * 2);
+                    Statement::Words(items)
+                }
+                _ => {
+                    let (opcode, mode, operand) = self.encode(keyword, operand, &labels, pc).map_err(error)?;
+                    pc = pc.wrapping_add(1 + operand_size(mode));
+                    Statement::Instruction { opcode, mode, operand }
+                }
+            };
+            origin.get_or_insert(0);
+            statements.push((number, pc, statement));
+        }
+
+        // Second pass, every label is known now
+        let origin = origin.unwrap_or(0);
+        let mut bytes = vec![];
+        for (number, end, statement) in statements {
+            let error = |message: String| AsmError { line: number, message };
+            let here = origin.wrapping_add(bytes.len() as u16);
+            let value = |expr: &str| -> Result<u16, AsmError> {
+                evaluate(expr, &labels, here).map_err(error)?
+                    .ok_or_else(|| error(format!("Unknown label in {}", expr.trim())))
+            };
+
+            match statement {
+                Statement::Org(address) => {
+                    bytes.resize(address.wrapping_sub(origin) as usize, 0);
+                }
+                Statement::Bytes(items) => {
+                    for item in items {
+                        match string_literal(&item) {
+                            Some(text) => bytes.extend_from_slice(text.as_bytes()),
+                            None => {
+                                let byte = value(&item)?;
+                                if byte > 0xFF {
+                                    return Err(error(format!("${:04X} does not fit in a byte", byte)));
+                                }
+                                bytes.push(byte as u8);
+                            }
+                        }
+                    }
+                }
+                Statement::Words(items) => {
+                    for item in items {
+                        bytes.extend_from_slice(&value(&item)?.to_le_bytes());
+                    }
+                }
+                Statement::Instruction { opcode, mode, operand } => {
+                    bytes.push(opcode);
+                    match mode {
+                        AddressModes::Imp => {}
+                        AddressModes::Rel => {
+                            let offset = value(&operand)? as i32 - end as i32;
+                            if !(-128..=127).contains(&offset) {
+                                return Err(error(format!("Branch to {} is {} bytes away", operand, offset)));
+                            }
+                            bytes.push(offset as i8 as u8);
+                        }
+                        _ if operand_size(mode) == 1 => {
+                            let byte = value(&operand)?;
+                            if byte > 0xFF {
+                                return Err(error(format!("${:04X} does not fit in a byte", byte)));
+                            }
+                            bytes.push(byte as u8);
+                        }
+                        _ => bytes.extend_from_slice(&value(&operand)?.to_le_bytes())
+                    }
+                }
+            }
+        }
+
+        Ok(Program { origin, bytes, labels })
+    }
+
+    // Works out the opcode and addressing mode, and returns the operand expression
+    fn encode(&self, mnemonic: &str, operand: &str, labels: &HashMap<String, u16>, pc: u16) -> Result<(u8, AddressModes, String), String> {
+        let mnemonic = mnemonic.to_uppercase();
+        let lower = operand.to_lowercase();
+
+        let (modes, expr): (Vec<AddressModes>, &str) = if operand.is_empty() || lower == "a" {
+            (vec![AddressModes::Imp], "")
+        } else if let Some(expr) = operand.strip_prefix('#') {
+            (vec![AddressModes::Imm], expr)
+        } else if operand.starts_with('(') && lower.ends_with(",x)") {
+            (vec![AddressModes::Izx], &operand[1..operand.len() - 3])
+        } else if operand.starts_with('(') && lower.ends_with("),y") {
+            (vec![AddressModes::Izy], &operand[1..operand.len() - 3])
+        } else if operand.starts_with('(') && operand.ends_with(')') {
+            (vec![AddressModes::Ind], &operand[1..operand.len() - 1])
+        } else if lower.ends_with(",x") || lower.ends_with(",y") {
+            let expr = &operand[..operand.len() - 2];
+            let modes = match (lower.ends_with(",x"), is_narrow(expr, labels, pc)) {
+                (true, true) => vec![AddressModes::Zpx, AddressModes::Abx],
+                (true, false) => vec![AddressModes::Abx],
+                (false, true) => vec![AddressModes::Zpy, AddressModes::Aby],
+                (false, false) => vec![AddressModes::Aby]
+            };
+            (modes, expr)
+        } else if is_narrow(operand, labels, pc) {
+            (vec![AddressModes::Rel, AddressModes::Zp0, AddressModes::Abs], operand)
+        } else {
+            (vec![AddressModes::Rel, AddressModes::Abs], operand)
+        };
+
+        let expr = expr.trim();
+        let expr = expr.strip_prefix("a:").or_else(|| expr.strip_prefix("A:")).unwrap_or(expr).trim();
+        for mode in modes {
+            let key = (mnemonic.clone(), mode);
+            let opcode = self.opcodes.get(&key).or_else(|| {
+                if self.unofficial { self.unofficial_opcodes.get(&key) } else { None }
+            });
+            if let Some(opcode) = opcode {
+                return Ok((*opcode, mode, expr.to_string()));
+            }
+        }
+        if !self.unofficial && self.unofficial_opcodes.keys().any(|(name, _)| *name == mnemonic) {
+            return Err(format!("{} is an unofficial opcode, enable them with set_unofficial", mnemonic));
+        }
+        Err(format!("No addressing mode of {} takes {}", mnemonic, operand))
+    }
+}
+
+fn operand_size(mode: AddressModes) -> u16 {
+    match mode {
+        AddressModes::Imp => 0,
+        AddressModes::Abs | AddressModes::Abx | AddressModes::Aby | AddressModes::Ind => 2,
+        _ => 1
+    }
+}
+
+fn define(labels: &mut HashMap<String, u16>, name: &str, value: u16) -> Result<(), String> {
+    if labels.insert(name.to_string(), value).is_some() {
+        return Err(format!("{} is defined twice", name));
+    }
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false
+    }
+}
+
+// Zero page is only used for values known to fit, forward references get the long form
+fn is_narrow(expr: &str, labels: &HashMap<String, u16>, pc: u16) -> bool {
+    let expr = expr.trim();
+    if expr.starts_with("a:") || expr.starts_with("A:") {
+        return false;
+    }
+    if let Some(hex) = expr.strip_prefix('$') {
+        if hex.len() > 2 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return false;
+        }
+    }
+    matches!(evaluate(expr, labels, pc), Ok(Some(value)) if value <= 0xFF)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = None;
+    for (i, c) in line.char_indices() {
+        match (quoted, c) {
+            (None, ';') => return &line[..i],
+            (None, '"') | (None, '\'') => quoted = Some(c),
+            (Some(q), c) if c == q => quoted = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+// Splits on commas that are not inside a string
+fn split_list(text: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut quoted = None;
+    for c in text.chars() {
+        match (quoted, c) {
+            (None, ',') => {
+                items.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            (None, '"') => quoted = Some(c),
+            (Some(q), c) if c == q => quoted = None,
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+    items
+}
+
+fn string_literal(item: &str) -> Option<&str> {
+    item.strip_prefix('"')?.strip_suffix('"')
+}
+
+/**
+ * Evaluates an operand. Ok(None) means a label that is not defined yet, which the first
+ * pass allows.
+ */
+fn evaluate(expr: &str, labels: &HashMap<String, u16>, pc: u16) -> Result<Option<u16>, String> {
+    let expr = expr.trim();
+    if let Some(rest) = expr.strip_prefix('<') {
+        return Ok(evaluate(rest, labels, pc)?.map(|value| value & 0x00FF));
+    }
+    if let Some(rest) = expr.strip_prefix('>') {
+        return Ok(evaluate(rest, labels, pc)?.map(|value| value >> 8));
+    }
+    if expr.is_empty() {
+        return Err("Missing operand".to_string());
+    }
+
+    let mut total: u16 = 0;
+    let mut known = true;
+    let mut sign = '+';
+    let mut term = String::new();
+    for c in expr.chars() {
+        // Inside a character literal + and - are just characters
+        let in_char = term.trim_start().starts_with('\'') && term.trim_start().len() < 3;
+        if (c == '+' || c == '-') && !term.trim().is_empty() && !in_char {
+            match term_value(term.trim(), labels, pc)? {
+                Some(value) => total = apply(total, sign, value),
+                None => known = false
+            }
+            sign = c;
+            term.clear();
+        } else {
+            term.push(c);
+        }
+    }
+    match term_value(term.trim(), labels, pc)? {
+        Some(value) => total = apply(total, sign, value),
+        None => known = false
+    }
+
+    Ok(if known { Some(total) } else { None })
+}
+
+fn apply(total: u16, sign: char, value: u16) -> u16 {
+    if sign == '-' { total.wrapping_sub(value) } else { total.wrapping_add(value) }
+}
+
+fn term_value(term: &str, labels: &HashMap<String, u16>, pc: u16) -> Result<Option<u16>, String> {
+    let parsed = if term == "*" {
+        Some(pc as u32)
+    } else if let Some(hex) = term.strip_prefix('$') {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = term.strip_prefix('%') {
+        u32::from_str_radix(binary, 2).ok()
+    } else if term.len() == 3 && term.starts_with('\'') && term.ends_with('\'') {
+        Some(term.as_bytes()[1] as u32)
+    } else if term.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+        term.parse::<u32>().ok()
+    } else if is_identifier(term) {
+        return Ok(labels.get(term).cloned());
+    } else {
+        None
+    };
+
+    match parsed {
+        Some(value) if value <= 0xFFFF => Ok(Some(value as u16)),
+        Some(_) => Err(format!("{} does not fit in 16 bits", term)),
+        None => Err(format!("Cannot read {}", term))
+    }
+}
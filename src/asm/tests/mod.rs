@@ -0,0 +1,151 @@
+use crate::asm::{assemble, Assembler};
+use crate::Nes;
+
+#[test]
+fn test_multiply_demo_matches_hand_assembly() {
+    // The program in bin/debug.rs, 10 * 3 by repeated addition
+    let program = assemble("
+        LDX #$0a
+        STX $0000
+        LDX #$03
+        STX $0001
+        LDY $0000
+        LDA #$00
+        CLC
+    loop:
+        ADC $0001
+        DEY
+        BNE loop
+        STA $0002
+        NOP
+        NOP
+        NOP
+    ").unwrap();
+
+    assert_eq!(program.origin, 0);
+    assert_eq!(program.bytes, vec![
+        0xA2, 0x0A, 0x8E, 0x00, 0x00, 0xA2, 0x03, 0x8E, 0x01, 0x00, 0xAC, 0x00, 0x00, 0xA9,
+        0x00, 0x18, 0x6D, 0x01, 0x00, 0x88, 0xD0, 0xFA, 0x8D, 0x02, 0x00, 0xEA, 0xEA, 0xEA
+    ]);
+    assert_eq!(program.label("loop"), Some(0x0010));
+}
+
+#[test]
+fn test_addressing_modes() {
+    let program = assemble("
+        .org $0600
+        ptr = $10
+        lda #<table
+        ldx #>table
+        lda ptr
+        lda ptr,x
+        ldx ptr,y
+        lda ptr+1,y     ; no zero page,Y form for LDA
+        lda a:ptr
+        lda ($20,x)
+        lda ($20),y
+        asl a
+        asl
+        jmp (vector)
+        jsr table
+    vector:
+        .word table, $1234
+    table:
+        .byte 1, %101, 'A', \"hi\"
+    ").unwrap();
+
+    assert_eq!(program.origin, 0x0600);
+    assert_eq!(program.label("table"), Some(0x0620));
+    assert_eq!(program.bytes, vec![
+        0xA9, 0x20, 0xA2, 0x06, 0xA5, 0x10, 0xB5, 0x10, 0xB6, 0x10, 0xB9, 0x11, 0x00,
+        0xAD, 0x10, 0x00, 0xA1, 0x20, 0xB1, 0x20, 0x0A, 0x0A, 0x6C, 0x1C, 0x06,
+        0x20, 0x20, 0x06, 0x20, 0x06, 0x34, 0x12, 0x01, 0x05, 0x41, 0x68, 0x69
+    ]);
+}
+
+#[test]
+fn test_org_pads_and_branches_backwards() {
+    let program = assemble("
+        .org $0200
+    start:
+        dex
+        bne start
+        .org $0206
+        jmp start
+    ").unwrap();
+    assert_eq!(program.bytes, vec![0xCA, 0xD0, 0xFD, 0x00, 0x00, 0x00, 0x4C, 0x00, 0x02]);
+}
+
+#[test]
+fn test_errors_name_the_line() {
+    let error = assemble("nop\nfoo #1").unwrap_err();
+    assert_eq!(error.line, 2);
+
+    let error = assemble("start:\n.org $0100\n.byte 0\n.org $0080").unwrap_err();
+    assert_eq!(error.line, 4);
+
+    let mut source = "loop:\n".to_string();
+    source.push_str(&"nop\n".repeat(200));
+    source.push_str("bne loop\n");
+    assert_eq!(assemble(&source).unwrap_err().line, 202);
+
+    assert!(assemble("lda missing").is_err());
+    assert!(assemble("a: nop\na: nop").is_err());
+}
+
+#[test]
+fn test_unofficial_opcodes_are_opt_in() {
+    assert!(assemble("lax $10").is_err());
+
+    let mut assembler = Assembler::new();
+    assembler.set_unofficial(true);
+    let program = assembler.assemble("lax $10\nisc $20\nisb $20\nnop #$01\nnop").unwrap();
+    assert_eq!(program.bytes, vec![0xA7, 0x10, 0xE7, 0x20, 0xE7, 0x20, 0x80, 0x01, 0xEA]);
+}
+
+#[test]
+fn test_disassembly_assembles_back_to_the_same_bytes() {
+    let mut assembler = Assembler::new();
+    assembler.set_unofficial(true);
+    let source = "
+        .org $0600
+        lda #$01
+        sta $02,x
+        stx $03,y
+        ora ($04,x)
+        eor ($05),y
+        inc a:$0006
+        rol $0700,x
+        sbc $0800,y
+        jmp ($0900)
+        bvs *-4
+        lsr a
+        lax ($10),y
+        slo $1234,x
+        brk
+    ";
+    let program = assembler.assemble(source).unwrap();
+
+    let mut nes = Nes::new();
+    nes.load(program.bytes.clone(), program.origin);
+    let cpu = &nes.cpu;
+    let end = program.origin + program.bytes.len() as u16 - 1;
+    let mut listing = String::from(".org $0600\n");
+    for instruction in cpu.disassemble_range(program.origin, end) {
+        listing.push_str(&instruction.to_ca65());
+        listing.push('\n');
+    }
+
+    assert_eq!(assembler.assemble(&listing).unwrap().bytes, program.bytes);
+}
+
+#[test]
+fn test_snake_source_assembles() {
+    // The source bin/snake_game.rs runs, it was hand assembled before
+    let program = assemble(include_str!("../../../assets/snake.s")).unwrap();
+    assert_eq!(program.origin, 0x0600);
+    assert_eq!(&program.bytes[..13], &[0x20, 0x06, 0x06, 0x20, 0x38, 0x06, 0x20, 0x0D, 0x06, 0x20, 0x2A, 0x06, 0x60]);
+    assert_eq!(program.label("loop"), Some(0x0638));
+    assert_eq!(program.label("gameOver"), Some(0x0735));
+    assert_eq!(program.bytes.len(), 0x0135);
+}
@@ -1,25 +1,39 @@
 
 
 use piston::{EventSettings};
-use nes_emulator::{create_system};
+use nes_emulator::{CpuVariant, Nes};
+use nes_emulator::asm::assemble;
 
 use nes_emulator::display::display::Game;
 use nes_emulator::display::display_debug::NesDebug;
 
 
 fn main() {
-    let game_code : Vec<u8> = vec![0xA2, 0x0A, 0x8E, 0x00, 0x00, 0xA2, 0x03, 0x8E, 0x01, 0x00, 0xAC,
-                                              0x00, 0x00, 0xA9, 0x00, 0x18, 0x6D, 0x01, 0x00, 0x88, 0xD0, 0xFA, 0x8D, 0x02, 0x00, 0xEA, 0xEA, 0xEA];
-
-    let (_bus_ref, cpu_ref, ppu_ref, state_ref) = create_system();
-
-    state_ref.as_ref().borrow_mut().load(game_code, 0);
-    cpu_ref.as_ref().borrow_mut().reset();
+    // 10 * 3 by repeated addition, the result ends up in $0002
+    let program = assemble("
+        LDX #$0a
+        STX $0000
+        LDX #$03
+        STX $0001
+        LDY $0000
+        LDA #$00
+        CLC
+    loop:
+        ADC $0001
+        DEY
+        BNE loop
+        STA $0002
+        NOP
+        NOP
+        NOP
+    ").expect("Invalid demo program");
+
+    let mut nes = Nes::new();
+    nes.cpu.set_variant(CpuVariant::Nmos6502);
+    nes.load(program.bytes, program.origin);
 
     let mut display= NesDebug::new(
-        state_ref.clone(),
-        cpu_ref.clone(),
-        ppu_ref.clone(),
+        nes,
         vec![0x0000, 0x8000],
         EventSettings {
             max_fps: 60,
@@ -0,0 +1,115 @@
+use std::io::{stdin, stdout, BufRead, Write};
+
+use nes_emulator::nsf::Nsf;
+use nes_emulator::nsf::player::{write_wav, NsfPlayer};
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn usage() -> ! {
+    eprintln!("usage: nsf_player <file.nsf|file.nsfe> [--track N] [--seconds S] [--wav out.wav] [--all]");
+    eprintln!();
+    eprintln!("  --track N    track to start on, counted from 1");
+    eprintln!("  --seconds S  length of each exported track, 120 by default");
+    eprintln!("  --wav FILE   export the track to FILE and exit");
+    eprintln!("  --all        with --wav, export every track as FILE_01.wav, FILE_02.wav, ...");
+    eprintln!();
+    eprintln!("Without --wav the player is interactive: n next, p previous, w export, q quit");
+    std::process::exit(1);
+}
+
+fn export(player: &mut NsfPlayer, path: &str, seconds: f64) {
+    let samples = player.render(seconds);
+    match write_wav(path, &samples, SAMPLE_RATE) {
+        Ok(_) => println!("Wrote {} ({} s) to {}", player.nsf().track_label(player.track()), seconds, path),
+        Err(e) => eprintln!("Cannot write {}: {}", path, e)
+    }
+}
+
+fn track_path(base: &str, track: u8) -> String {
+    let stem = base.strip_suffix(".wav").unwrap_or(base);
+    format!("{}_{:02}.wav", stem, track + 1)
+}
+
+fn print_track(player: &NsfPlayer) {
+    let nsf = player.nsf();
+    println!("[{}/{}] {}", player.track() + 1, nsf.song_count, nsf.track_label(player.track()));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut file = None;
+    let mut track = None;
+    let mut seconds = 120.0;
+    let mut wav = None;
+    let mut all = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--track" => {
+                i += 1;
+                track = args.get(i).and_then(|t| t.parse::<u8>().ok());
+                if track.is_none() { usage(); }
+            }
+            "--seconds" => {
+                i += 1;
+                seconds = args.get(i).and_then(|s| s.parse::<f64>().ok()).unwrap_or_else(|| usage());
+            }
+            "--wav" => {
+                i += 1;
+                wav = Some(args.get(i).cloned().unwrap_or_else(|| usage()));
+            }
+            "--all" => all = true,
+            arg if file.is_none() && !arg.starts_with("--") => file = Some(arg.to_string()),
+            _ => usage()
+        }
+        i += 1;
+    }
+    let file = file.unwrap_or_else(|| usage());
+
+    let nsf = Nsf::new(&file);
+    println!("{}", nsf.title);
+    println!("{}", nsf.artist);
+    println!("{}", nsf.copyright);
+    println!("{} tracks, {} us per frame", nsf.song_count, nsf.play_speed());
+
+    let mut player = NsfPlayer::new(nsf);
+    if let Some(track) = track {
+        player.start_track(track.max(1) - 1);
+    }
+
+    if let Some(wav) = wav {
+        if all {
+            for track in 0..player.nsf().song_count {
+                player.start_track(track);
+                export(&mut player, &track_path(&wav, track), seconds);
+            }
+        } else {
+            export(&mut player, &wav, seconds);
+        }
+        return;
+    }
+
+    let stem = file.rsplit_once('.').map(|(stem, _)| stem.to_string()).unwrap_or(file.clone());
+    print_track(&player);
+    loop {
+        print!("> ");
+        stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin().lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        match line.trim() {
+            "n" => { player.next_track(); print_track(&player); }
+            "p" => { player.previous_track(); print_track(&player); }
+            "w" => {
+                let track = player.track();
+                export(&mut player, &track_path(&stem, track), seconds);
+                // Exporting ran the track on, start it over
+                player.start_track(track);
+            }
+            "q" => break,
+            _ => println!("n next, p previous, w export, q quit")
+        }
+    }
+}
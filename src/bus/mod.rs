@@ -1,124 +1,195 @@
-use std::cell::{RefCell, RefMut};
-use std::rc::Rc;
-use graphics::math::add;
 use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::cpu_bus::CpuBus;
+use crate::cpu::StopReason;
 use crate::ppu::Ppu;
 use crate::state::State;
-use crate::mapper::Mapper;
+
+#[cfg(test)]
+mod tests;
 
 const RAM: u16 = 0x0000;
 const RAM_MIRRORS_END: u16 = 0x1FFF;
 const PPU_REGISTERS: u16 = 0x2000;
 const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
+const APU_STATUS: u16 = 0x4015;
 
-pub(crate) fn mem_read(state: &State, addr: u16, read_only: bool) -> u8 {
+pub(crate) fn mem_read(state: &mut State, addr: u16) -> u8 {
 
     let mut data: u8 = 0x00;
     if state.cartridge.is_none() {
-        if addr >= 0x0000 && addr <= 0xFFFF {
-            data = state.cpu_ram[addr as usize];
-        } else if addr >= 0x2000 && addr <= 0x3fff {
-            data = state.ppu_ram[addr as usize];
-        }
-        return data;
+        return state.cpu_ram[addr as usize];
     }
 
-    let mut mapped_addr = 0;
-    let mut mapper = state.get_mapper();
+    let claimed = state.get_cartridge_mut().cpu_read(addr, &mut data);
 
-    if mapper.cpu_map_read(state, addr, &mut mapped_addr) {
-        data = state.get_cartridge().v_prg_memory[mapped_addr as usize];
-    } else if addr >= 0x0000 && addr <= 0x1FFF {
+    if claimed {
+        // The cartridge sees every address first and can claim any of them
+    } else if addr <= 0x1FFF {
         let location = addr & 0x07ff;
         data = state.cpu_ram[location as usize];
     } else if addr >= 0x2000 && addr <= 0x3fff {
-        let location = addr & 0x0007;
-        data = state.ppu_ram[location as usize];
+        data = crate::ppu::cpu_read(state, addr & 0x0007);
+    } else if addr == APU_STATUS {
+        data = state.apu.read_status();
+    }
+    return data;
+}
+
+/**
+ * What mem_read would return without any of its side effects. Nothing is acknowledged,
+ * no port moves on and no mapper latch switches, so debug views can look anywhere.
+ */
+pub(crate) fn mem_peek(state: &State, addr: u16) -> u8 {
+
+    let mut data: u8 = 0x00;
+    if state.cartridge.is_none() {
+        return state.cpu_ram[addr as usize];
+    }
+
+    if state.get_cartridge().cpu_peek(addr, &mut data) {
+        // The cartridge sees every address first and can claim any of them
+    } else if addr <= 0x1FFF {
+        let location = addr & 0x07ff;
+        data = state.cpu_ram[location as usize];
+    } else if addr >= 0x2000 && addr <= 0x3fff {
+        data = crate::ppu::cpu_peek(state, addr & 0x0007);
+    } else if addr == APU_STATUS {
+        data = state.apu.peek_status();
     }
     return data;
 }
 
 pub(crate) fn mem_write(state: &mut State, addr: u16, data: u8) {
     if state.cartridge.is_none() {
-        if  addr >= 0x0000 && addr <= 0xFFFF {
-            state.cpu_ram[addr as usize] = data;
-        } else if addr >= 0x2000 && addr <= 0x3fff {
-            state.ppu_ram[addr as usize] = data;
-        }
+        state.cpu_ram[addr as usize] = data;
     } else {
-        let mut mapped_addr = 0;
-        let mut mapper = state.get_mapper();
+        let claimed = state.get_cartridge_mut().cpu_write(addr, data);
 
-        if mapper.cpu_map_write(state, addr, &mut mapped_addr) {
-            let mut cart = state.cartridge.as_ref().expect("Missing cart").as_ref().borrow_mut();
-            cart.v_prg_memory[mapped_addr as usize] = data;
-        } else if  addr >= 0x0000 && addr <= 0x1FFF {
+        if claimed {
+            // The cartridge sees every address first and can claim any of them
+        } else if addr <= 0x1FFF {
             let location = addr & 0x07ff;
             state.cpu_ram[location as usize] = data;
         } else if addr >= 0x2000 && addr <= 0x3fff {
             let location = addr & 0x0007;
             state.ppu_ram[location as usize] = data;
+            crate::ppu::cpu_write(state, location, data);
+        } else if (addr >= 0x4000 && addr <= 0x4013) || addr == APU_STATUS || addr == 0x4017 {
+            state.apu.cpu_write(addr, data);
         }
     }
 }
 
-pub(crate) fn reset(state: &mut State, ppu: &mut Ppu, cpu: &mut Cpu) {
+// The reset button, the CPU reads its vector through the state so that borrow ends first
+pub(crate) fn reset(ppu: &mut Ppu, cpu: &mut Cpu) {
+    {
+        let mut state = cpu.get_state_mut();
+        if let Some(cart) = state.cartridge.as_mut() {
+            cart.reset();
+        }
+        state.apu.reset();
+        state.n_system_clock_counter = 0;
+    }
     cpu.reset();
-    state.n_system_clock_counter = 0;
 }
 
-pub(crate) fn clock(ppu: &mut Ppu, cpu: &mut Cpu) -> Result<(), ()>{
-    ppu.clock();
+impl CpuBus for State {
+
+    fn read(&mut self, addr: u16) -> u8 {
+        return mem_read(self, addr);
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        mem_write(self, addr, data);
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        return mem_peek(self, addr);
+    }
+
+    // Steps the mapper and the APU, either of them can hold the IRQ line
+    fn tick(&mut self) -> bool {
+        let (cart_irq, expansion) = match self.cartridge.as_mut() {
+            Some(cart) => {
+                cart.mapper.cpu_clock();
+                (cart.mapper.irq_state(), cart.mapper.audio_sample())
+            }
+            None => (false, 0.0)
+        };
+
+        // The DMC fetches its next sample byte through the CPU bus
+        if let Some(addr) = self.apu.dmc.request() {
+            let data = mem_read(self, addr);
+            self.apu.dmc.fill(data);
+        }
+        self.apu.clock(expansion);
+        return cart_irq || self.apu.irq_state();
+    }
+}
+
+// Steps the rest of the system by one CPU cycle and drives the IRQ line from it
+pub(crate) fn clock_peripherals(cpu: &mut Cpu) {
+    let asserted = cpu.bus_mut().tick();
+    cpu.set_irq_line(asserted);
+}
+
+// One PPU dot, and a CPU cycle on every third one. The counter moves on even when the
+// CPU stops so the next call carries on with the following dot
+pub(crate) fn clock(ppu: &mut Ppu, cpu: &mut Cpu) -> Result<(), StopReason> {
+    if cpu.get_state().program_end() == Some(0) {
+        return Err(StopReason::InvalidState);
+    }
+    ppu.clock(cpu.get_state_mut());
+    let nmi = crate::ppu::nmi_output(cpu.get_state());
+    cpu.set_nmi_line(nmi);
+    let mut result = Ok(());
     if cpu.get_state_mut().n_system_clock_counter % 3 == 0 {
-        if let Err(_) = cpu.clock() {
-            return Err(());
+        result = cpu.run_cycle();
+        // Loaded code stops once its last instruction is done
+        if result.is_ok() && cpu.cycles == 0 && cpu.get_state().program_end() == Some(cpu.pc as usize) {
+            result = Err(StopReason::ProgramEnd);
         }
     }
     cpu.get_state_mut().n_system_clock_counter += 1;
-    return Ok(());
+    return result;
 }
 
-pub fn advance(ppu: &mut Ppu, cpu: &mut Cpu) -> Result<(), ()>{
+// Runs until the current instruction is done
+pub fn advance(ppu: &mut Ppu, cpu: &mut Cpu) -> StopReason {
     loop {
-        ppu.clock();
-        if cpu.get_state_mut().n_system_clock_counter % 3 == 0 {
-            if let Err(_) = cpu.clock() {
-                return Err(());
-            } else if cpu.cycles == 0 {
-                return Ok(())
-            }
+        let cpu_cycle = cpu.get_state().n_system_clock_counter % 3 == 0;
+        if let Err(reason) = clock(ppu, cpu) {
+            return reason;
+        }
+        if cpu_cycle && cpu.cycles == 0 {
+            return StopReason::InstructionComplete;
         }
-        cpu.get_state_mut().n_system_clock_counter += 1;
     }
 }
 
-pub fn system_clock(ppu: &mut Ppu, cpu: &mut Cpu) -> Result<(), ()>{
+// Runs until the PPU finishes a frame
+pub fn system_clock(ppu: &mut Ppu, cpu: &mut Cpu) -> StopReason {
     loop {
-        ppu.clock();
-        if cpu.get_state_mut().n_system_clock_counter % 3 == 0 {
-            let _ = cpu.clock();
+        if let Err(reason) = clock(ppu, cpu) {
+            return reason;
         }
-        cpu.get_state_mut().n_system_clock_counter += 1;
         if ppu.frame_complete {
             ppu.frame_complete = false;
-            return Ok(());
+            return StopReason::FrameComplete;
         }
     }
 }
 
-pub struct Bus {
-    pub(crate) cpu: Option<Rc<RefCell<Cpu>>>,
-    pub(crate) ppu: Option<Rc<RefCell<Ppu>>>,
-    pub(crate) state: Option<Rc<RefCell<State>>>,
-}
-
-impl Bus {
-
-    pub fn new() -> Bus {
-        Bus {
-            cpu: None,
-            ppu: None,
-            state: None
+// Runs for at most the given number of CPU cycles
+pub fn run(ppu: &mut Ppu, cpu: &mut Cpu, cpu_cycles: usize) -> StopReason {
+    let mut remaining = cpu_cycles;
+    while remaining > 0 {
+        if cpu.get_state().n_system_clock_counter % 3 == 0 {
+            remaining -= 1;
+        }
+        if let Err(reason) = clock(ppu, cpu) {
+            return reason;
         }
     }
-}
\ No newline at end of file
+    return StopReason::CycleBudgetExhausted;
+}
@@ -0,0 +1,220 @@
+use std::path::Path;
+
+use crate::asm::assemble;
+use crate::bus::mem_peek;
+use crate::cartridge::Cartridge;
+use crate::cpu::StopReason;
+use crate::mapper::create_mapper;
+use crate::state::State;
+use crate::Nes;
+
+// Directory of test ROMs that report through $6000, every .nes file in it is run
+const ROMS_DIR_VAR: &str = "BLARGG_ROMS";
+const ROMS_DIR: &str = "assets/test_roms";
+
+const CPU_CLOCK: usize = 1_789_773;
+// The longest of the blargg tests run for about 20 seconds
+const TIMEOUT_CYCLES: usize = 30 * CPU_CLOCK;
+// A test asking to be reset wants the button held for at least 100 ms
+const RESET_DELAY_CYCLES: usize = CPU_CLOCK / 10;
+
+const STATUS: u16 = 0x6000;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const RUNNING: u8 = 0x80;
+const NEEDS_RESET: u8 = 0x81;
+
+#[derive(Debug, PartialEq)]
+enum RomResult {
+    // Status 0 and the text the ROM printed
+    Passed(String),
+    // Any other final status, usually the number of the failing test
+    Failed(u8, String),
+    // Still running, or never wrote the signature, when time ran out
+    TimedOut(String),
+    // The CPU stopped on a JAM opcode at this address, with whatever text was there by then
+    Jammed(u16, String),
+}
+
+/**
+ * Runs a ROM that follows the blargg status protocol:
+ *
+ * $6000        $80 while running, $81 when the reset button should be pressed, then the result
+ * $6001-$6003  DE B0 61 once the bytes at $6000 are valid
+ * $6004        zero terminated text, what the ROM would print on screen
+ */
+fn run_rom(rom: &[u8], timeout_cycles: usize) -> RomResult {
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cartridge::from_bytes(rom));
+
+    let mut reset_at = None;
+    for cycle in 0..timeout_cycles {
+        for _ in 0..3 {
+            if let Err(StopReason::Jam(addr)) = nes.clock() {
+                return RomResult::Jammed(addr, read_message(nes.state()));
+            }
+        }
+        // Checking every few thousand cycles is plenty, the ROMs spin once they are done
+        if cycle % 4096 != 0 {
+            continue;
+        }
+
+        let status = {
+            let state = nes.state();
+            let signature: Vec<u8> = (1..4).map(|i| mem_peek(state, STATUS + i)).collect();
+            if signature != SIGNATURE {
+                continue;
+            }
+            mem_peek(state, STATUS)
+        };
+        match status {
+            RUNNING => {}
+            NEEDS_RESET => match reset_at {
+                None => reset_at = Some(cycle + RESET_DELAY_CYCLES),
+                Some(at) if cycle >= at => {
+                    reset_at = None;
+                    nes.reset();
+                }
+                _ => {}
+            },
+            0x00 => return RomResult::Passed(read_message(nes.state())),
+            _ => return RomResult::Failed(status, read_message(nes.state()))
+        }
+    }
+    return RomResult::TimedOut(read_message(nes.state()));
+}
+
+fn read_message(state: &State) -> String {
+    let mut message = String::new();
+    for addr in (STATUS + 4)..0x7000 {
+        let byte = mem_peek(state, addr);
+        if byte == 0x00 {
+            break;
+        }
+        message.push(byte as char);
+    }
+    return message.trim().to_string();
+}
+
+// An NROM image with a 16 KB PRG bank and CHR RAM, the program has to start at $C000
+fn nrom_image(prg: &[u8]) -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend_from_slice(prg);
+    rom.resize(16 + 0x4000, 0x00);
+    return rom;
+}
+
+// Mapper number from an iNES header, the test ROMs do not need NES 2.0
+fn header_mapper(rom: &[u8]) -> u16 {
+    return ((rom[7] & 0xF0) | (rom[6] >> 4)) as u16;
+}
+
+// A stand in for a test ROM, it reports the given status once it has been reset
+fn status_rom(status: u8) -> Vec<u8> {
+    let program = assemble(&format!("
+        .org $C000
+        reset:
+            lda #$80
+            sta $6000
+            lda #$DE
+            sta $6001
+            lda #$B0
+            sta $6002
+            lda #$61
+            sta $6003
+            lda $6004
+            bne report
+            lda #'!'
+            sta $6004
+            lda #$81
+            sta $6000
+        wait:
+            jmp wait
+        report:
+            ldx #0
+        copy:
+            lda message,x
+            sta $6005,x
+            beq done
+            inx
+            bne copy
+        done:
+            lda #${:02X}
+            sta $6000
+        forever:
+            jmp forever
+        message:
+            .byte \" after reset\", 0
+        .org $FFFA
+            .word reset, reset, reset
+    ", status)).unwrap();
+    assert_eq!(program.origin, 0xC000);
+    return nrom_image(&program.bytes);
+}
+
+#[test]
+fn test_rom_passes_after_requested_reset() {
+    assert_eq!(run_rom(&status_rom(0x00), CPU_CLOCK), RomResult::Passed("! after reset".to_string()));
+}
+
+#[test]
+fn test_rom_failure_reports_status() {
+    assert_eq!(run_rom(&status_rom(0x03), CPU_CLOCK), RomResult::Failed(0x03, "! after reset".to_string()));
+}
+
+#[test]
+fn test_rom_without_signature_times_out() {
+    let program = assemble("
+        .org $C000
+        reset:
+            jmp reset
+        .org $FFFA
+            .word reset, reset, reset
+    ").unwrap();
+    assert_eq!(run_rom(&nrom_image(&program.bytes), 20_000), RomResult::TimedOut(String::new()));
+}
+
+#[test]
+fn test_jam_stops_the_rom() {
+    let program = assemble("
+        .org $C000
+        reset:
+            lda #'x'
+            sta $6004
+            .byte $02
+        .org $FFFA
+            .word reset, reset, reset
+    ").unwrap();
+    assert_eq!(run_rom(&nrom_image(&program.bytes), 20_000), RomResult::Jammed(0xC005, "x".to_string()));
+}
+
+// The ROMs are not bundled, copy them into assets/test_roms (or point BLARGG_ROMS at them)
+// and run with --ignored
+#[test]
+#[ignore = "needs test ROMs in assets/test_roms"]
+fn test_rom_directory() {
+    let dir = std::env::var(ROMS_DIR_VAR).unwrap_or_else(|_| ROMS_DIR.to_string());
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("Could not read {}: {}, set {} to a directory of test ROMs", dir, err, ROMS_DIR_VAR))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "nes"))
+        .collect();
+    paths.sort();
+
+    let mut failures = vec![];
+    let mut ran = 0;
+    for path in paths {
+        let rom = std::fs::read(&path).expect("Unreadable ROM");
+        let name = Path::new(&path).file_name().unwrap().to_string_lossy().to_string();
+        // ROMs for boards without a mapper yet are left out rather than run on the wrong one
+        if rom.len() < 16 || create_mapper(header_mapper(&rom), 0, 1, 1).is_none() {
+            continue;
+        }
+        ran += 1;
+        match run_rom(&rom, TIMEOUT_CYCLES) {
+            RomResult::Passed(_) => {}
+            result => failures.push(format!("{}: {:?}", name, result))
+        }
+    }
+    assert!(ran > 0, "No ROMs with a supported mapper in {}", dir);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
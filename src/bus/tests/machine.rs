@@ -0,0 +1,160 @@
+use crate::asm::assemble;
+use crate::bus::mem_read;
+use crate::cartridge::Cartridge;
+use crate::{Nes, StopReason};
+use crate::ppu::{ppu_write, EMU_HEIGHT, EMU_WIDTH};
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn test_nes_is_send() {
+    assert_send::<Nes>();
+}
+
+#[test]
+fn test_nes_runs_on_another_thread() {
+    let program = assemble("
+        ldx #0
+    loop:
+        inx
+        stx $10
+        cpx #5
+        bne loop
+    ").unwrap();
+    let mut nes = Nes::new();
+    nes.load(program.bytes, program.origin);
+
+    let handle = std::thread::spawn(move || {
+        let reason = nes.run(1000);
+        (reason, nes)
+    });
+    let (reason, nes) = handle.join().unwrap();
+    assert_eq!(reason, StopReason::ProgramEnd);
+    assert_eq!(nes.state().cpu_ram[0x10], 5);
+}
+
+fn spinning_nes() -> Nes {
+    let program = assemble("
+    loop:
+        inc $10
+        jmp loop
+    ").unwrap();
+    let mut nes = Nes::new();
+    nes.load(program.bytes, program.origin);
+    return nes;
+}
+
+#[test]
+fn test_run_frame_returns_picture_audio_and_stats() {
+    let mut nes = spinning_nes();
+    let pallet = nes.ppu.pallet.clone();
+
+    let frame = nes.run_frame();
+    assert_eq!(frame.stats.stop, StopReason::FrameComplete);
+    assert_eq!(frame.stats.frame, 1);
+    assert_eq!(frame.indices.len(), (EMU_WIDTH * EMU_HEIGHT) as usize);
+    assert_eq!(frame.rgba.len(), frame.indices.len() * 4);
+    for (dot, &index) in frame.indices.iter().enumerate() {
+        assert_eq!(&frame.rgba[dot * 4..dot * 4 + 4], &pallet[index as usize].0);
+    }
+
+    // The APU hands over a sample every 1789773 / 44100 cycles or so
+    let expected = frame.stats.cpu_cycles as f64 * 44_100.0 / 1_789_773.0;
+    assert!((frame.audio.len() as f64 - expected).abs() <= 1.0, "{} samples in {} cycles", frame.audio.len(), frame.stats.cpu_cycles);
+
+    let second = nes.run_frame();
+    assert_eq!(second.stats.frame, 2);
+    assert!(second.stats.cpu_cycles > 0);
+}
+
+#[test]
+fn test_frames_follow_ntsc_timing() {
+    // 341 x 262 dots, a third of that in CPU cycles
+    let mut nes = spinning_nes();
+    nes.run_frame();
+    let cycles: u32 = (0..3).map(|_| nes.run_frame().stats.cpu_cycles).sum();
+    assert_eq!(cycles, 341 * 262);
+
+    // Rendering drops a dot on every odd frame, 29780.5 cycles a frame on average
+    nes.state_mut().ppu_ram[1] = 0x18;
+    nes.run_frame();
+    let (mut cycles, mut samples) = (0, 0);
+    for _ in 0..10 {
+        let frame = nes.run_frame();
+        cycles += frame.stats.cpu_cycles;
+        samples += frame.audio.len();
+    }
+    assert!((cycles as f64 / 10.0 - 29780.5).abs() < 0.2, "{} cycles in 10 frames", cycles);
+
+    // 44.1 kHz at 60.1 frames a second
+    let expected = cycles as f64 * 44_100.0 / 1_789_773.0;
+    assert!((samples as f64 - expected).abs() <= 1.0, "{} samples in {} cycles", samples, cycles);
+    assert!((samples as f64 / 10.0 - 733.8).abs() < 0.2, "{} samples in 10 frames", samples);
+}
+
+#[test]
+fn test_run_frame_stops_early_at_a_breakpoint() {
+    let mut nes = spinning_nes();
+    nes.cpu.add_breakpoint(0x0002);
+
+    let frame = nes.run_frame();
+    assert_eq!(frame.stats.stop, StopReason::Breakpoint(0x0002));
+    assert_eq!(frame.stats.frame, 0);
+}
+
+// An NROM cartridge with the program at $8000, which is also where it resets to
+fn nrom_nes(source: &str) -> Nes {
+    let program = assemble(source).unwrap();
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut prg = vec![0; 16384];
+    prg[..program.bytes.len()].copy_from_slice(&program.bytes);
+    prg[0x3FFC] = 0x00;
+    prg[0x3FFD] = 0x80;
+    rom.extend(prg);
+    rom.extend(vec![0; 8192]);
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cartridge::from_bytes(&rom));
+    return nes;
+}
+
+#[test]
+fn test_vertical_blank_raises_nmi() {
+    let mut nes = nrom_nes("
+        .org $8000
+        lda #$80
+        sta $2000
+    loop:
+        jmp loop
+    nmi:
+        inc $11
+        rti
+        .org $BFFA
+        .word nmi
+    ");
+    for _ in 0..4 {
+        nes.run_frame();
+    }
+    assert_eq!(nes.state().cpu_ram[0x11], 4);
+}
+
+#[test]
+fn test_peek_leaves_the_machine_alone() {
+    let mut nes = nrom_nes("
+        .org $8000
+    loop:
+        inc $10
+        jmp loop
+    ");
+
+    // Long enough for the APU frame counter to raise its IRQ, the CPU has I set
+    nes.run(30_000);
+    assert_eq!(nes.peek(0x8000), 0xE6);
+    assert_eq!(nes.peek(0x0810), nes.state().cpu_ram[0x10]);
+    assert_eq!(nes.peek(0x4015) & 0x40, 0x40);
+    assert_eq!(nes.peek(0x4015) & 0x40, 0x40);
+    assert_eq!(mem_read(nes.state_mut(), 0x4015) & 0x40, 0x40);
+    assert_eq!(nes.peek(0x4015) & 0x40, 0x00);
+
+    ppu_write(nes.state_mut(), 0x3F01, 0x2A);
+    assert_eq!(nes.peek_ppu(0x3F01), 0x2A);
+}
@@ -0,0 +1,2 @@
+mod blargg;
+mod machine;
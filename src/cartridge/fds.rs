@@ -0,0 +1,54 @@
+// One disk side in a .fds image, without gaps or CRCs
+pub(crate) const SIDE_SIZE: usize = 65500;
+
+// Gaps the drive sees before the first block and between blocks, in bytes
+const LEAD_IN: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+
+// The BIOS never gets a CRC error from the drive, so any value works here
+const CRC_PLACEHOLDER: [u8; 2] = [0x4D, 0x62];
+
+/**
+ * Splits a .fds image into disk sides, each laid out the way the drive streams it: a long
+ * lead in gap, then every block prefixed with the $80 gap end mark and followed by its
+ * CRC and a short gap. The optional 16 byte fwNES header is skipped.
+ */
+pub(crate) fn parse_fds(image: &[u8]) -> Vec<Vec<u8>> {
+    let data = if image.len() >= 16 && &image[0..4] == b"FDS\x1A" { &image[16..] } else { image };
+    data.chunks(SIDE_SIZE)
+        .filter(|side| side.len() == SIDE_SIZE)
+        .map(lay_out_side)
+        .collect()
+}
+
+fn lay_out_side(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEAD_IN];
+    let mut pos = 0;
+
+    let mut push_block = |raw: &mut Vec<u8>, pos: &mut usize, length: usize| -> bool {
+        if *pos + length > side.len() {
+            return false;
+        }
+        raw.push(0x80);
+        raw.extend_from_slice(&side[*pos..*pos + length]);
+        raw.extend_from_slice(&CRC_PLACEHOLDER);
+        raw.extend(vec![0; BLOCK_GAP]);
+        *pos += length;
+        return true;
+    };
+
+    // Disk info and file count blocks, then file header / file data pairs
+    if side[0] == 0x01 && push_block(&mut raw, &mut pos, 56) && side[pos] == 0x02 && push_block(&mut raw, &mut pos, 2) {
+        while pos + 16 < side.len() && side[pos] == 0x03 {
+            let file_size = side[pos + 13] as usize | (side[pos + 14] as usize) << 8;
+            if !push_block(&mut raw, &mut pos, 16) || side[pos] != 0x04 || !push_block(&mut raw, &mut pos, file_size + 1) {
+                break;
+            }
+        }
+    }
+
+    if raw.len() < LEAD_IN + SIDE_SIZE {
+        raw.resize(LEAD_IN + SIDE_SIZE, 0);
+    }
+    raw
+}
@@ -1,7 +1,27 @@
+mod fds;
+mod rom_db;
+mod unif;
+#[cfg(test)]
 mod tests;
 
 use std::fs::File;
 use std::io::Read;
+use crate::mapper::{create_mapper, Mapper, MAPPER_HANDLED};
+use crate::mapper::fds::Fds;
+use crate::mapper::nsf::NsfMapper;
+use crate::nsf::Nsf;
+use crate::cartridge::fds::parse_fds;
+use crate::cartridge::rom_db::{bundled_db, lookup, rom_crc32, rom_sha1, RomDbEntry};
+use crate::cartridge::unif::{board_mapper, parse_unif};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mirror {
+    Hardware,
+    Horizontal,
+    Vertical,
+    OneScreenLo,
+    OneScreenHi,
+}
 
 struct Header {
     name: Vec<char>,
@@ -15,22 +35,153 @@ struct Header {
     unused: Vec<u8>
 }
 
+pub use crate::cartridge::rom_db::Region;
+
+/**
+ * What is known about the loaded game. Starts out with what the header claims and is
+ * corrected from the ROM database when the PRG and CHR hashes are found in it.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CartridgeInfo {
+    // Only known for games in the database
+    pub title: Option<String>,
+    // Hashes of PRG and CHR ROM together, header excluded
+    pub crc32: u32,
+    pub sha1: String,
+    pub mapper_id: u16,
+    pub submapper: u8,
+    pub mirror: Mirror,
+    pub battery: bool,
+    // Volatile and battery backed PRG RAM together, in bytes
+    pub prg_ram_size: usize,
+    pub region: Region,
+    // Default expansion port device, numbered as in NES 2.0 header byte 15
+    pub expansion: u8,
+}
+
+impl CartridgeInfo {
+
+    fn new(mapper_id: u16, mirror: Mirror) -> CartridgeInfo {
+        CartridgeInfo {
+            title: None,
+            crc32: 0,
+            sha1: String::new(),
+            mapper_id,
+            submapper: 0,
+            mirror,
+            battery: false,
+            prg_ram_size: 0,
+            region: Region::Ntsc,
+            expansion: 0
+        }
+    }
+
+    fn hash(&mut self, prg: &[u8], chr: &[u8]) {
+        self.crc32 = rom_crc32(prg, chr);
+        self.sha1 = rom_sha1(prg, chr);
+    }
+
+    // The database wins over the header
+    fn correct(&mut self, entry: &RomDbEntry) {
+        self.title = Some(entry.title.clone());
+        self.mapper_id = entry.mapper;
+        self.submapper = entry.submapper;
+        if let Some(mirror) = entry.mirror {
+            self.mirror = mirror;
+        }
+        self.battery = entry.battery;
+        self.prg_ram_size = entry.prg_ram_size;
+        self.region = entry.region;
+        self.expansion = entry.expansion;
+    }
+}
+
 pub struct Cartridge {
     header: Header,
-    pub(crate) n_mapper_id: u8,
+    info: CartridgeInfo,
+    pub(crate) n_mapper_id: u16,
+    pub(crate) n_submapper: u8,
     pub(crate) n_prgbanks: u8,
     n_chrbanks: u8,
     pub(crate) v_prg_memory: Vec<u8>,
-    pub(crate) v_chr_memory: Vec<u8>
+    pub(crate) v_chr_memory: Vec<u8>,
+    pub(crate) mapper: Box<dyn Mapper>,
+    hw_mirror: Mirror,
 }
 
 
 impl Cartridge {
 
     pub fn new(file_path: &str) -> Cartridge {
-        let mut file = File::open(file_path).expect("File not found");
-        let mut buffer: [u8; 16] = [0; 16];
-        file.read(&mut buffer).expect("Cannot read from cartridge file");
+        Cartridge::from_bytes(&read_file(file_path))
+    }
+
+    // Famicom Disk System, the BIOS has to be supplied by the user
+    pub fn new_fds(bios_path: &str, disk_path: &str) -> Cartridge {
+        Cartridge::from_fds(&read_file(bios_path), &read_file(disk_path))
+    }
+
+    pub fn from_fds(bios: &[u8], disk: &[u8]) -> Cartridge {
+        assert!(bios.len() >= 8192, "FDS BIOS must be 8 KB");
+        let sides = parse_fds(disk);
+        assert!(!sides.is_empty(), "No disk sides in FDS image");
+
+        // iNES mapper 20 is reserved for the Disk System
+        let mut info = CartridgeInfo::new(20, Mirror::Horizontal);
+        info.hash(disk, &[]);
+        Cartridge::from_board(
+            ['F', 'D', 'S', 0x1A as char],
+            info,
+            bios[bios.len() - 8192..].to_vec(),
+            Box::new(Fds::new(sides))
+        )
+    }
+
+    // The player board for an NSF tune, it holds the tune data itself
+    pub fn from_nsf(nsf: &Nsf) -> Cartridge {
+        let mut info = CartridgeInfo::new(0, Mirror::Horizontal);
+        info.hash(&nsf.data, &[]);
+        info.title = Some(nsf.title.clone());
+        info.region = Region::from_code(nsf.region);
+        Cartridge::from_board(['N', 'E', 'S', 'M'], info, vec![], Box::new(NsfMapper::new(nsf)))
+    }
+
+    // Cartridges that do not come from an iNES image
+    fn from_board(name: [char; 4], info: CartridgeInfo, v_prg_memory: Vec<u8>, mapper: Box<dyn Mapper>) -> Cartridge {
+        Cartridge {
+            header: Header {
+                name: name.to_vec(),
+                prg_rom_chunks: 0,
+                chr_rom_chunks: 0,
+                mapper1: 0,
+                mapper2: 0,
+                prg_ram_size: 0,
+                tv_system1: 0,
+                tv_system2: 0,
+                unused: vec![0; 5]
+            },
+            n_mapper_id: info.mapper_id,
+            n_submapper: 0,
+            n_prgbanks: 0,
+            n_chrbanks: 0,
+            v_prg_memory,
+            v_chr_memory: vec![0; 8192],
+            mapper,
+            hw_mirror: info.mirror,
+            info
+        }
+    }
+
+    pub fn from_bytes(buffer: &[u8]) -> Cartridge {
+        Cartridge::from_bytes_with_db(buffer, bundled_db())
+    }
+
+    // Same as from_bytes with the header corrected from another database
+    pub(crate) fn from_bytes_with_db(buffer: &[u8], db: &[RomDbEntry]) -> Cartridge {
+        if buffer.len() >= 4 && &buffer[0..4] == b"UNIF" {
+            return Cartridge::from_unif(buffer, db);
+        }
+        assert!(buffer.len() >= 16, "Cartridge file is too short");
 
         let header = Header {
             name: vec![buffer[0] as char, buffer[1] as char, buffer[2] as char, buffer[3] as char],
@@ -44,30 +195,259 @@ impl Cartridge {
             unused: vec![buffer[11], buffer[12], buffer[13], buffer[14], buffer[15]]
         };
 
+        let mut offset = 16;
 
+        // Skip the trainer
         if (header.mapper1 & 0x04) > 0x00 {
-            let mut buffer_trash: [u8; 512] = [0; 512];
-            file.read(&mut buffer_trash).expect("Cannot read from cartridge file");
+            offset += 512;
         }
 
-        let n_mapper_id = ((header.mapper2 >> 4) << 4) | (header.mapper1 >> 4);
+        let mut n_mapper_id = (((header.mapper2 >> 4) << 4) | (header.mapper1 >> 4)) as u16;
+        let mut n_submapper = 0;
+
+        // NES 2.0 reuses the PRG RAM size byte for mapper bits 8-11 and the submapper
+        let is_nes2 = header.mapper2 & 0x0C == 0x08;
+        if is_nes2 {
+            n_mapper_id |= ((header.prg_ram_size & 0x0F) as u16) << 8;
+            n_submapper = header.prg_ram_size >> 4;
+        }
+
+        let hw_mirror = if header.mapper1 & 0x01 > 0 {
+            Mirror::Vertical
+        } else {
+            Mirror::Horizontal
+        };
+
+        let mut info = CartridgeInfo::new(n_mapper_id, hw_mirror);
+        info.submapper = n_submapper;
+        info.battery = header.mapper1 & 0x02 > 0;
+        if is_nes2 {
+            // Shift counts, 64 << n bytes each for volatile and battery backed RAM
+            let ram_size = |shift: u8| if shift == 0 { 0 } else { 64 << shift };
+            info.prg_ram_size = ram_size(header.tv_system2 & 0x0F) + ram_size(header.tv_system2 >> 4);
+            info.region = Region::from_code(header.unused[1]);
+            info.expansion = header.unused[4] & 0x3F;
+        } else {
+            // In 8 KB units, 0 still means 8 KB
+            info.prg_ram_size = header.prg_ram_size.max(1) as usize * 8192;
+            info.region = if header.tv_system1 & 0x01 > 0 { Region::Pal } else { Region::Ntsc };
+        }
 
         let n_prgbanks = header.prg_rom_chunks;
-        let mut v_prg_memory = vec![0; (n_prgbanks as usize * 16384) as usize];
-        file.read(&mut v_prg_memory).expect("Cannot read from cartridge file");
+        let prg_size = n_prgbanks as usize * 16384;
+        let v_prg_memory = read_chunk(buffer, offset, prg_size);
+        offset += prg_size;
 
         let n_chrbanks = header.chr_rom_chunks;
-        let mut v_chr_memory = vec![0; (n_chrbanks as usize * 8192) as usize];
-        file.read(&mut v_chr_memory).expect("Cannot read from cartridge file");
+        let v_chr_memory = if n_chrbanks == 0 {
+            // No CHR ROM means the board has 8 KB of CHR RAM instead
+            vec![0; 8192]
+        } else {
+            read_chunk(buffer, offset, n_chrbanks as usize * 8192)
+        };
+
+        Cartridge::from_rom(header, info, db, v_prg_memory, v_chr_memory, n_prgbanks, n_chrbanks)
+    }
 
+    // Looks the ROM up in the database, then builds the mapper the corrected info asks for
+    fn from_rom(header: Header, mut info: CartridgeInfo, db: &[RomDbEntry], v_prg_memory: Vec<u8>, v_chr_memory: Vec<u8>, n_prgbanks: u8, n_chrbanks: u8) -> Cartridge {
+        let chr_rom = if n_chrbanks == 0 { &[][..] } else { &v_chr_memory[..] };
+        info.hash(&v_prg_memory, chr_rom);
+        if let Some(entry) = lookup(db, info.crc32, &info.sha1) {
+            info.correct(entry);
+        }
+
+        let mapper = match create_mapper(info.mapper_id, info.submapper, n_prgbanks, n_chrbanks) {
+            Some(mapper) => mapper,
+            None => panic!("Unsupported mapper {}", info.mapper_id)
+        };
 
         Cartridge {
             header,
-            n_mapper_id,
+            n_mapper_id: info.mapper_id,
+            n_submapper: info.submapper,
             n_prgbanks,
             n_chrbanks,
             v_prg_memory,
-            v_chr_memory
+            v_chr_memory,
+            mapper,
+            hw_mirror: info.mirror,
+            info
         }
     }
-}
\ No newline at end of file
+
+    // UNIF images name the board instead of a mapper number
+    fn from_unif(buffer: &[u8], db: &[RomDbEntry]) -> Cartridge {
+        let image = parse_unif(buffer);
+        let n_mapper_id = match board_mapper(&image.board) {
+            Some(mapper_id) => mapper_id,
+            None => panic!("Unsupported UNIF board {}", image.board)
+        };
+
+        // Banks are counted in whole 16 KB / 8 KB units like an iNES header would
+        let n_prgbanks = image.prg.len().div_ceil(16384) as u8;
+        let mut v_prg_memory = image.prg;
+        v_prg_memory.resize(n_prgbanks as usize * 16384, 0);
+
+        let n_chrbanks = image.chr.len().div_ceil(8192) as u8;
+        let v_chr_memory = if n_chrbanks == 0 {
+            vec![0; 8192]
+        } else {
+            let mut chr = image.chr;
+            chr.resize(n_chrbanks as usize * 8192, 0);
+            chr
+        };
+
+        let mut mapper1 = ((n_mapper_id & 0x0F) << 4) as u8;
+        if image.mirror == Mirror::Vertical {
+            mapper1 |= 0x01;
+        }
+        if image.battery {
+            mapper1 |= 0x02;
+        }
+
+        let header = Header {
+            name: vec!['U', 'N', 'I', 'F'],
+            prg_rom_chunks: n_prgbanks,
+            chr_rom_chunks: n_chrbanks,
+            mapper1,
+            mapper2: (n_mapper_id & 0xF0) as u8,
+            prg_ram_size: 0,
+            tv_system1: 0,
+            tv_system2: 0,
+            unused: vec![0; 5]
+        };
+
+        let mut info = CartridgeInfo::new(n_mapper_id, image.mirror);
+        info.battery = image.battery;
+        Cartridge::from_rom(header, info, db, v_prg_memory, v_chr_memory, n_prgbanks, n_chrbanks)
+    }
+
+    pub fn info(&self) -> &CartridgeInfo {
+        &self.info
+    }
+
+    pub fn mirror(&self) -> Mirror {
+        let mirror = self.mapper.mirror();
+        if mirror == Mirror::Hardware {
+            self.hw_mirror
+        } else {
+            mirror
+        }
+    }
+
+    // Which of the two CIRAM pages backs a name table quadrant ($2000, $2400, $2800, $2C00)
+    pub(crate) fn ciram_page(&self, quadrant: usize) -> usize {
+        if let Some(page) = self.mapper.ciram_page(quadrant) {
+            return page;
+        }
+
+        match self.mirror() {
+            Mirror::Horizontal => (quadrant >> 1) & 0x01,
+            Mirror::OneScreenLo => 0,
+            Mirror::OneScreenHi => 1,
+            _ => quadrant & 0x01
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.mapper.reset();
+    }
+
+    pub fn disk_sides(&self) -> usize {
+        self.mapper.disk_sides()
+    }
+
+    // Flips to another disk side, false if there is no such side
+    pub fn insert_disk_side(&mut self, side: usize) -> bool {
+        self.mapper.insert_disk(Some(side))
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.mapper.insert_disk(None);
+    }
+
+    pub(crate) fn cpu_read(&mut self, addr: u16, data: &mut u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.cpu_map_read(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                *data = self.v_prg_memory[mapped_addr as usize];
+            }
+            return true;
+        }
+        return false;
+    }
+
+    // cpu_read without side effects, for debuggers
+    pub(crate) fn cpu_peek(&self, addr: u16, data: &mut u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.cpu_map_peek(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                *data = self.v_prg_memory[mapped_addr as usize];
+            }
+            return true;
+        }
+        return false;
+    }
+
+    pub(crate) fn cpu_write(&mut self, addr: u16, data: u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.cpu_map_write(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                self.v_prg_memory[mapped_addr as usize] = data;
+            }
+            return true;
+        }
+        return false;
+    }
+
+    pub(crate) fn ppu_read(&mut self, addr: u16, data: &mut u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.ppu_map_read(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                *data = self.v_chr_memory[mapped_addr as usize];
+            }
+            return true;
+        }
+        return false;
+    }
+
+    pub(crate) fn ppu_peek(&self, addr: u16, data: &mut u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.ppu_map_peek(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                *data = self.v_chr_memory[mapped_addr as usize];
+            }
+            return true;
+        }
+        return false;
+    }
+
+    pub(crate) fn ppu_write(&mut self, addr: u16, data: u8) -> bool {
+        let mut mapped_addr = 0;
+        if self.mapper.ppu_map_write(addr, &mut mapped_addr, data) {
+            if mapped_addr != MAPPER_HANDLED {
+                self.v_chr_memory[mapped_addr as usize] = data;
+            }
+            return true;
+        }
+        return false;
+    }
+}
+
+fn read_file(file_path: &str) -> Vec<u8> {
+    let mut file = File::open(file_path).expect("File not found");
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).expect("Cannot read from cartridge file");
+    buffer
+}
+
+// Copies a chunk out of the image, padding with zeros if the file was truncated
+fn read_chunk(buffer: &[u8], offset: usize, size: usize) -> Vec<u8> {
+    let mut chunk = vec![0; size];
+    if offset < buffer.len() {
+        let end = (offset + size).min(buffer.len());
+        chunk[..end - offset].copy_from_slice(&buffer[offset..end]);
+    }
+    chunk
+}
@@ -0,0 +1,158 @@
+use std::sync::OnceLock;
+
+use crate::cartridge::Mirror;
+
+/**
+ * The database shipped with the emulator, in the XML layout of the NES 2.0 header
+ * database. It only knows the bundled test ROM, the full nes20db.xml is not redistributed
+ * here. Point the NES20DB environment variable at a copy of it (or any file in the same
+ * layout) and its entries are searched before the bundled ones. Only the elements read
+ * below are used:
+ *
+ * <game>
+ *   <!-- Title -->
+ *   <rom crc32="..." sha1="..."/>          PRG and CHR ROM together, without the header
+ *   <pcb mapper="4" submapper="0" mirroring="V" battery="1"/>
+ *   <prgram size="8192"/>
+ *   <prgnvram size="8192"/>
+ *   <console type="0" region="0"/>         0 NTSC, 1 PAL, 2 both, 3 Dendy
+ *   <expansion type="1"/>                  default input device, NES 2.0 byte 15
+ * </game>
+ */
+const BUNDLED_DB: &str = include_str!("../../assets/nes20db.xml");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+    Multi,
+    Dendy,
+}
+
+impl Region {
+
+    pub(crate) fn from_code(code: u8) -> Region {
+        match code & 0x03 {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Multi,
+            _ => Region::Dendy
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RomDbEntry {
+    pub(crate) title: String,
+    pub(crate) crc32: u32,
+    pub(crate) sha1: String,
+    pub(crate) mapper: u16,
+    pub(crate) submapper: u8,
+    // None when not given or for four screen boards, the header's choice stands
+    pub(crate) mirror: Option<Mirror>,
+    pub(crate) battery: bool,
+    pub(crate) prg_ram_size: usize,
+    pub(crate) region: Region,
+    pub(crate) expansion: u8,
+}
+
+// The user's database from NES20DB followed by the bundled one, read once. A NES20DB
+// that can not be read is left out and the bundled entries are used on their own.
+pub(crate) fn bundled_db() -> &'static [RomDbEntry] {
+    static DB: OnceLock<Vec<RomDbEntry>> = OnceLock::new();
+    DB.get_or_init(|| {
+        let mut entries = std::env::var("NES20DB").ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|xml| parse_db(&xml))
+            .unwrap_or_default();
+        entries.extend(parse_db(BUNDLED_DB));
+        entries
+    })
+}
+
+pub(crate) fn rom_crc32(prg: &[u8], chr: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(prg);
+    hasher.update(chr);
+    hasher.finalize()
+}
+
+// Upper case hex, the way the database writes it
+pub(crate) fn rom_sha1(prg: &[u8], chr: &[u8]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(prg);
+    hasher.update(chr);
+    hasher.digest().to_string().to_uppercase()
+}
+
+// Both hashes have to agree, entries without a SHA-1 are matched on the CRC alone
+pub(crate) fn lookup<'a>(db: &'a [RomDbEntry], crc32: u32, sha1: &str) -> Option<&'a RomDbEntry> {
+    db.iter().find(|entry| entry.crc32 == crc32 && (entry.sha1.is_empty() || entry.sha1 == sha1))
+}
+
+pub(crate) fn parse_db(xml: &str) -> Vec<RomDbEntry> {
+    let mut entries = vec![];
+    for block in xml.split("<game>").skip(1) {
+        let block = block.split("</game>").next().unwrap_or("");
+
+        let rom = match find_tag(block, "rom") {
+            Some(rom) => rom,
+            None => continue
+        };
+        let crc32 = match attribute(rom, "crc32").and_then(|crc| u32::from_str_radix(crc, 16).ok()) {
+            Some(crc32) => crc32,
+            None => continue
+        };
+
+        let title = match (block.find("<!--"), block.find("-->")) {
+            (Some(start), Some(end)) if start < end => block[start + 4..end].trim().to_string(),
+            _ => String::new()
+        };
+
+        let pcb = find_tag(block, "pcb").unwrap_or("");
+        let mirror = match attribute(pcb, "mirroring") {
+            Some("H") => Some(Mirror::Horizontal),
+            Some("V") => Some(Mirror::Vertical),
+            _ => None
+        };
+        let ram_size = |tag: &str| {
+            find_tag(block, tag)
+                .and_then(|ram| number::<usize>(ram, "size"))
+                .unwrap_or(0)
+        };
+
+        entries.push(RomDbEntry {
+            title,
+            crc32,
+            sha1: attribute(rom, "sha1").unwrap_or("").to_uppercase(),
+            mapper: number(pcb, "mapper").unwrap_or(0),
+            submapper: number(pcb, "submapper").unwrap_or(0),
+            mirror,
+            battery: number::<u8>(pcb, "battery").unwrap_or(0) > 0,
+            prg_ram_size: ram_size("prgram") + ram_size("prgnvram"),
+            region: Region::from_code(find_tag(block, "console").and_then(|console| number(console, "region")).unwrap_or(0)),
+            expansion: find_tag(block, "expansion").and_then(|expansion| number(expansion, "type")).unwrap_or(0)
+        });
+    }
+    entries
+}
+
+// The attribute text of the first <name .../> element in a block
+fn find_tag<'a>(block: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{} ", name);
+    let start = block.find(&open)? + open.len();
+    let end = block[start..].find('>')? + start;
+    Some(&block[start..end])
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", name);
+    let padded_start = format!(" {}", tag).find(&key)?;
+    let start = padded_start + key.len() - 1;
+    let end = tag[start..].find('"')? + start;
+    Some(&tag[start..end])
+}
+
+fn number<T: std::str::FromStr>(tag: &str, name: &str) -> Option<T> {
+    attribute(tag, name)?.parse().ok()
+}
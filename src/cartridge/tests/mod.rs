@@ -1,8 +1,245 @@
-use crate::cartridge::Cartridge;
+use crate::cartridge::{Cartridge, Mirror, Region};
+use crate::cartridge::fds::{parse_fds, SIDE_SIZE};
+use crate::cartridge::rom_db::{lookup, parse_db, rom_crc32, rom_sha1};
+use crate::cartridge::unif::board_mapper;
 
 #[test]
 fn test_read_cartridge_header() {
     let cart = Cartridge::new("assets/nestest.nes");
 
     println!("{}", "hi");
-}
\ No newline at end of file
+}
+#[test]
+fn test_nes2_header_mapper_and_submapper() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 2, 1, 0x50, 0x18, 0x30, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(vec![0; 2 * 16384 + 8192]);
+    let cart = Cartridge::from_bytes(&rom);
+
+    assert_eq!(cart.n_mapper_id, 0x15);
+    assert_eq!(cart.n_submapper, 3);
+}
+
+// The low nibble of byte 8 holds mapper bits 8-11, nothing up there is implemented
+#[test]
+#[should_panic(expected = "Unsupported mapper 277")]
+fn test_nes2_header_extended_mapper() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 2, 1, 0x50, 0x18, 0x31, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(vec![0; 2 * 16384 + 8192]);
+    Cartridge::from_bytes(&rom);
+}
+
+#[test]
+fn test_ines_header_ignores_byte_8() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 2, 1, 0x50, 0x10, 0x31, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(vec![0; 2 * 16384 + 8192]);
+    let cart = Cartridge::from_bytes(&rom);
+
+    assert_eq!(cart.n_mapper_id, 0x15);
+    assert_eq!(cart.n_submapper, 0);
+}
+
+const TEST_DB: &str = r#"<nes20db>
+<game>
+	<!-- Test Game (USA) -->
+	<rom size="16384" crc32="CRC" sha1="SHA"/>
+	<pcb mapper="25" submapper="2" mirroring="V" battery="1"/>
+	<prgram size="2048"/>
+	<prgnvram size="8192"/>
+	<console type="0" region="1"/>
+	<expansion type="8"/>
+</game>
+</nes20db>"#;
+
+#[test]
+fn test_rom_db_lookup() {
+    let prg = vec![0x12; 16384];
+    let crc = rom_crc32(&prg, &[]);
+    let sha1 = rom_sha1(&prg, &[]);
+    let xml = TEST_DB.replace("CRC", &format!("{:08X}", crc)).replace("SHA", &sha1.to_lowercase());
+    let db = parse_db(&xml);
+
+    let entry = lookup(&db, crc, &sha1).expect("Entry not found");
+    assert_eq!(entry.title, "Test Game (USA)");
+    assert_eq!((entry.mapper, entry.submapper), (25, 2));
+    assert_eq!(entry.mirror, Some(Mirror::Vertical));
+    assert!(entry.battery);
+    assert_eq!(entry.prg_ram_size, 10240);
+    assert_eq!(entry.region, Region::Pal);
+    assert_eq!(entry.expansion, 8);
+
+    // Four screen or missing mirroring leaves the header alone
+    let four_screen = parse_db(&xml.replace("mirroring=\"V\"", "mirroring=\"4\""));
+    assert_eq!(four_screen[0].mirror, None);
+    let unspecified = parse_db(&xml.replace(" mirroring=\"V\"", ""));
+    assert_eq!(unspecified[0].mirror, None);
+
+    // Both hashes have to match
+    assert!(lookup(&db, crc, "0000").is_none());
+    assert!(lookup(&db, 0xDEAD_BEEF, &sha1).is_none());
+}
+
+#[test]
+fn test_database_supplies_ines_submapper() {
+    // VRC4 on mapper 25 with an iNES header, which cannot carry the submapper
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 8, 16, 0x90, 0x10, 0, 0, 0, 0, 0, 0, 0, 0];
+    let prg: Vec<u8> = (0..8 * 16384).map(|i| (i / 8192) as u8).collect();
+    let chr = vec![0; 16 * 8192];
+    rom.extend(&prg);
+    rom.extend(&chr);
+
+    let crc = rom_crc32(&prg, &chr);
+    let xml = TEST_DB.replace("CRC", &format!("{:08X}", crc)).replace("SHA", &rom_sha1(&prg, &chr));
+    let db = parse_db(&xml);
+
+    assert_eq!(Cartridge::from_bytes(&rom).n_submapper, 0);
+    let mut cart = Cartridge::from_bytes_with_db(&rom, &db);
+    assert_eq!(cart.n_submapper, 2);
+    assert_eq!(cart.info().title.as_deref(), Some("Test Game (USA)"));
+
+    // VRC4d puts the PRG swap register on A2, plain iNES would take A0 for it as well
+    let peek = |cart: &Cartridge, addr: u16| {
+        let mut data = 0;
+        cart.cpu_peek(addr, &mut data);
+        data
+    };
+    cart.cpu_write(0x8000, 5);
+    cart.cpu_write(0x9001, 0x02);
+    assert_eq!(peek(&cart, 0xC000), 14);
+    cart.cpu_write(0x9004, 0x02);
+    assert_eq!(peek(&cart, 0xC000), 5);
+}
+
+#[test]
+fn test_database_corrects_bad_header() {
+    let mut rom = std::fs::read("assets/nestest.nes").unwrap();
+    // Claim mapper 4 with vertical mirroring
+    rom[6] = 0x41;
+    let cart = Cartridge::from_bytes(&rom);
+
+    assert_eq!(cart.n_mapper_id, 0);
+    assert_eq!(cart.mirror(), Mirror::Horizontal);
+    let info = cart.info();
+    assert_eq!(info.title.as_deref(), Some("nestest"));
+    assert_eq!(info.crc32, 0x158B_0388);
+    assert_eq!(info.sha1, "4131307F0F69F2A5C54B7D438328C5B2A5ED0820");
+}
+
+#[test]
+fn test_unknown_rom_keeps_header_info() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x93, 0x00, 0, 0x01, 0, 0, 0, 0, 0, 0];
+    rom.extend(vec![0x55; 16384]);
+    let cart = Cartridge::from_bytes(&rom);
+
+    let info = cart.info();
+    assert_eq!(info.title, None);
+    assert_eq!(info.mapper_id, 9);
+    assert_eq!(info.mirror, Mirror::Vertical);
+    assert!(info.battery);
+    assert_eq!(info.prg_ram_size, 8192);
+    assert_eq!(info.region, Region::Pal);
+    assert_eq!(info.crc32, rom_crc32(&vec![0x55; 16384], &[]));
+}
+
+#[test]
+#[should_panic(expected = "Unsupported mapper 1")]
+fn test_unsupported_mapper_is_refused() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x10, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(vec![0x55; 16384]);
+    Cartridge::from_bytes(&rom);
+}
+
+#[test]
+fn test_parse_fds_sides() {
+    let mut side = vec![0x01];
+    side.resize(56, 0x20);
+    side.extend_from_slice(&[0x02, 0x00]);
+    side.resize(SIDE_SIZE, 0);
+
+    // Headerless image with two sides, the trailing partial side is dropped
+    let mut image = side.clone();
+    image.extend(side.clone());
+    image.extend(vec![0; 100]);
+    let sides = parse_fds(&image);
+    assert_eq!(sides.len(), 2);
+
+    // Lead in gap, gap end mark, the block, its CRC and the next gap
+    let raw = &sides[0];
+    let lead_in = 28300 / 8;
+    assert!(raw[..lead_in].iter().all(|b| *b == 0));
+    assert_eq!(raw[lead_in], 0x80);
+    assert_eq!(raw[lead_in + 1], 0x01);
+    let next = lead_in + 1 + 56 + 2 + 976 / 8;
+    assert!(raw[lead_in + 59..next].iter().all(|b| *b == 0));
+    assert_eq!(raw[next], 0x80);
+    assert_eq!(raw[next + 1], 0x02);
+
+    // fwNES header
+    let mut headed = b"FDS\x1A\x01".to_vec();
+    headed.resize(16, 0);
+    headed.extend(side);
+    assert_eq!(parse_fds(&headed).len(), 1);
+}
+
+fn unif_chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+fn make_unif(board: &str, chunks: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut image = b"UNIF".to_vec();
+    image.extend_from_slice(&7u32.to_le_bytes());
+    image.resize(32, 0);
+    let mut board = board.as_bytes().to_vec();
+    board.push(0);
+    image.extend(unif_chunk(b"MAPR", &board));
+    for chunk in chunks {
+        image.extend(chunk);
+    }
+    image
+}
+
+#[test]
+fn test_unif_board_and_rom_chunks() {
+    // PRG chunks are joined in numeric order, not file order
+    let image = make_unif("NES-PNROM", vec![
+        unif_chunk(b"PRG1", &vec![0x22; 16384]),
+        unif_chunk(b"PRG0", &vec![0x11; 16384]),
+        unif_chunk(b"CHR0", &vec![0x33; 8192]),
+        unif_chunk(b"MIRR", &[1]),
+        unif_chunk(b"BATR", &[1]),
+    ]);
+    let cart = Cartridge::from_bytes(&image);
+
+    assert_eq!(cart.n_mapper_id, 9);
+    assert_eq!(cart.n_prgbanks, 2);
+    assert_eq!(cart.n_chrbanks, 1);
+    assert_eq!(cart.v_prg_memory[0], 0x11);
+    assert_eq!(cart.v_prg_memory[16384], 0x22);
+    assert_eq!(cart.v_chr_memory[0], 0x33);
+    assert_eq!(cart.mirror(), Mirror::Vertical);
+    assert_eq!(cart.header.mapper1 & 0x03, 0x03);
+}
+
+#[test]
+fn test_unif_without_chr_gets_chr_ram() {
+    let image = make_unif("HVC-NROM-128", vec![unif_chunk(b"PRG0", &vec![0xEA; 16384])]);
+    let cart = Cartridge::from_bytes(&image);
+
+    assert_eq!(cart.n_mapper_id, 0);
+    assert_eq!(cart.n_prgbanks, 1);
+    assert_eq!(cart.n_chrbanks, 0);
+    assert_eq!(cart.v_chr_memory.len(), 8192);
+    assert_eq!(cart.mirror(), Mirror::Horizontal);
+}
+
+#[test]
+fn test_unif_board_names() {
+    assert_eq!(board_mapper("NES-EKROM"), Some(5));
+    assert_eq!(board_mapper("HVC-FKROM"), Some(10));
+    assert_eq!(board_mapper("NES-JLROM"), Some(69));
+    assert_eq!(board_mapper("UNL-SOMETHING"), None);
+    // MMC1 is not implemented, so its boards are not either
+    assert_eq!(board_mapper("NES-SLROM"), None);
+}
@@ -0,0 +1,112 @@
+use crate::cartridge::Mirror;
+
+// Magic plus revision, the rest of the 32 byte header is padding
+const HEADER_SIZE: usize = 32;
+
+/**
+ * Nintendo board names and the iNES mapper each one is wired as. UNIF names come with
+ * an "NES-" or "HVC-" prefix, which is stripped before the lookup. Only boards whose
+ * mapper is implemented are listed, the others are refused instead of running as NROM.
+ */
+const BOARDS: &[(&str, u16)] = &[
+    ("NROM", 0), ("NROM-128", 0), ("NROM-256", 0), ("HROM", 0), ("RROM", 0), ("RROM-128", 0),
+    ("SROM", 0), ("RTROM", 0), ("STROM", 0),
+    ("EKROM", 5), ("ELROM", 5), ("ETROM", 5), ("EWROM", 5),
+    ("PNROM", 9), ("PEEOROM", 9),
+    ("FJROM", 10), ("FKROM", 10),
+    ("BTR", 69), ("JLROM", 69), ("JSROM", 69),
+];
+
+pub(crate) struct UnifImage {
+    pub(crate) board: String,
+    pub(crate) prg: Vec<u8>,
+    pub(crate) chr: Vec<u8>,
+    pub(crate) mirror: Mirror,
+    pub(crate) battery: bool,
+}
+
+/**
+ * Reads a UNIF image. After the 32 byte header the file is a list of chunks, each a four
+ * character id and a little endian length:
+ *
+ * MAPR       board name
+ * PRG0-PRGF  PRG ROM, concatenated in order
+ * CHR0-CHRF  CHR ROM, concatenated in order
+ * MIRR       0 horizontal, 1 vertical, 2 / 3 one screen, 4 four screen, 5 mapper controlled
+ * BATR       battery backed PRG RAM
+ *
+ * Title, checksum, dumper and controller chunks are skipped.
+ */
+pub(crate) fn parse_unif(buffer: &[u8]) -> UnifImage {
+    assert!(buffer.len() >= HEADER_SIZE && &buffer[0..4] == b"UNIF", "Not a UNIF file");
+
+    let mut image = UnifImage {
+        board: String::new(),
+        prg: vec![],
+        chr: vec![],
+        mirror: Mirror::Horizontal,
+        battery: false
+    };
+    let mut prg_chunks: Vec<Option<&[u8]>> = vec![None; 16];
+    let mut chr_chunks: Vec<Option<&[u8]>> = vec![None; 16];
+
+    let mut offset = HEADER_SIZE;
+    while offset + 8 <= buffer.len() {
+        let id = &buffer[offset..offset + 4];
+        let length = u32::from_le_bytes([buffer[offset + 4], buffer[offset + 5], buffer[offset + 6], buffer[offset + 7]]) as usize;
+        offset += 8;
+        let chunk = &buffer[offset.min(buffer.len())..(offset + length).min(buffer.len())];
+        offset += length;
+
+        match id {
+            b"MAPR" => image.board = read_string(chunk),
+            b"MIRR" if !chunk.is_empty() => {
+                image.mirror = match chunk[0] {
+                    0 => Mirror::Horizontal,
+                    1 => Mirror::Vertical,
+                    2 => Mirror::OneScreenLo,
+                    3 => Mirror::OneScreenHi,
+                    // Four screen boards are not emulated, and for 5 the mapper overrides it anyway
+                    _ => Mirror::Vertical
+                };
+            }
+            b"BATR" => image.battery = chunk.first().map_or(true, |battery| *battery != 0),
+            [b'P', b'R', b'G', index] => {
+                if let Some(slot) = hex_digit(*index) {
+                    prg_chunks[slot] = Some(chunk);
+                }
+            }
+            [b'C', b'H', b'R', index] => {
+                if let Some(slot) = hex_digit(*index) {
+                    chr_chunks[slot] = Some(chunk);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for chunk in prg_chunks.into_iter().flatten() {
+        image.prg.extend_from_slice(chunk);
+    }
+    for chunk in chr_chunks.into_iter().flatten() {
+        image.chr.extend_from_slice(chunk);
+    }
+    image
+}
+
+// The iNES mapper for a UNIF board name
+pub(crate) fn board_mapper(board: &str) -> Option<u16> {
+    let name = board.strip_prefix("NES-").or_else(|| board.strip_prefix("HVC-")).unwrap_or(board);
+    BOARDS.iter()
+        .find(|(board_name, _)| *board_name == name)
+        .map(|(_, mapper_id)| *mapper_id)
+}
+
+fn hex_digit(digit: u8) -> Option<usize> {
+    (digit as char).to_digit(16).map(|value| value as usize)
+}
+
+fn read_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
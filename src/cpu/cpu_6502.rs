@@ -1,17 +1,21 @@
-use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use std::rc::Rc;
 
-use crate::bus::{mem_read, mem_write};
 use crate::cpu::Flags::{U, I, B, C, Z, V, N, D};
-use crate::cpu::{Flags, Opcodes, AddressModes};
+use crate::cpu::{CpuVariant, Flags, Opcodes, AddressModes, StopReason};
+use crate::cpu::cpu_bus::CpuBus;
 
+use crate::cpu::disassembler::{decode, DecodedInstruction};
 use crate::cpu::instructions::make_instructions;
 use crate::state::State;
 
-pub struct Cpu {
-    pub(crate) state: Option<Rc<RefCell<State>>>,
+/**
+ * The 6502 core, generic over what it is wired to. Cpu on its own is the NES CPU on the
+ * NES bus (State), Cpu<FlatBus> or a bus of your own runs the same core anywhere else.
+ */
+pub struct Cpu<B: CpuBus = State> {
+    // Everything the CPU reaches, on the NES that is RAM, the APU and the cartridge
+    pub(crate) bus: B,
     // All used memory addresses end up in here
     addr_abs: u16,
     // Represents absolute address following a branch
@@ -36,9 +40,69 @@ pub struct Cpu {
     pub(crate) status: u8,
     // Represents the working input value to the ALU
     fetched: u8,
+    // Set by the JAM opcodes, only a reset gets the CPU going again
+    halted: bool,
+    // What the current cycles are spent on
+    sequence: Sequence,
+    // Cycle of the current sequence, the opcode fetch is cycle 1
+    step: u8,
+    // Cycle on which the effective address became known, 0 while still addressing
+    addressed: u8,
+    // Zero page or indirect pointer being followed
+    ptr: u16,
+    // An indexed address carried into the next page
+    page_crossed: bool,
+    // IRQ input, held low by the mapper or the APU
+    irq_line: bool,
+    // NMI input, only a change from high to low counts
+    nmi_line: bool,
+    // The inputs as the CPU sees them, a cycle after they change
+    irq_signal: bool,
+    nmi_signal: bool,
+    // An NMI edge seen this cycle, raises nmi_signal from the next one
+    nmi_edge: bool,
+    // What the last poll found, acted on when the instruction ends
+    irq_pending: bool,
+    nmi_pending: bool,
+    // Decides whether the D flag switches ADC and SBC to BCD
+    variant: CpuVariant,
+    // Stop before the instruction at one of these addresses runs
+    breakpoints: HashSet<u16>,
+    // Stop after a cycle that reads or writes one of these addresses
+    watchpoints: HashSet<u16>,
+    watch_hit: Option<u16>,
+    // Every bus access as (address, data, write) while the test harnesses record them
+    pub(crate) bus_log: Option<Vec<(u16, u8, bool)>>,
     lookup: Vec<Instruction>
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Sequence {
+    Instruction,
+    // IRQ or NMI, with the vector to load unless an NMI takes it over
+    Interrupt(u16),
+    // The cycles after a reset, nothing is accessed
+    Reset,
+}
+
+// How an instruction uses the bus once its opcode is fetched
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Access {
+    Implied,
+    Read,
+    Write,
+    ReadModifyWrite,
+    Push,
+    Pull,
+    Jump,
+    Branch,
+    Brk,
+    Jsr,
+    Rti,
+    Rts,
+    Jam,
+}
+
 #[allow(dead_code)]
 pub(crate) struct Instruction {
     pub(crate) name: String,
@@ -47,12 +111,41 @@ pub(crate) struct Instruction {
     pub(crate) cycles: usize
 }
 
-#[allow(arithmetic_overflow, dead_code)]
 impl Cpu {
 
     pub fn new() -> Cpu {
+        return Cpu::with_bus(State::new());
+    }
+
+    pub(crate) fn get_state_mut(&mut self) -> &mut State {
+        &mut self.bus
+    }
+
+    pub(crate) fn get_state(&self) -> &State {
+        &self.bus
+    }
+
+    /**
+     * The next instruction and the registers as a nestest.log line, with the scanline and
+     * dot the PPU has reached on the bus:
+     *
+     * C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+     */
+    pub fn trace(&self) -> String {
+        return format!(
+            "{:<48}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.decode(self.pc).to_nestest(), self.a, self.x, self.y, self.status, self.stkp,
+            self.bus.ppu_scan_line, self.bus.ppu_cycle, self.clock_count
+        );
+    }
+}
+
+#[allow(arithmetic_overflow, dead_code)]
+impl<B: CpuBus> Cpu<B> {
+
+    pub fn with_bus(bus: B) -> Cpu<B> {
         Cpu {
-            state: None,
+            bus,
             addr_abs: 0x0000,
             addr_rel: 0x00,
             opcode: 0x00,
@@ -65,362 +158,646 @@ impl Cpu {
             stkp: 0x00,
             status: 0x00,
             fetched: 0x00,
+            halted: false,
+            sequence: Sequence::Instruction,
+            step: 0,
+            addressed: 0,
+            ptr: 0x0000,
+            page_crossed: false,
+            irq_line: false,
+            nmi_line: false,
+            irq_signal: false,
+            nmi_signal: false,
+            nmi_edge: false,
+            irq_pending: false,
+            nmi_pending: false,
+            variant: CpuVariant::Ricoh2A03,
+            breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            watch_hit: None,
+            bus_log: None,
             lookup: make_instructions()
         }
     }
 
-    pub(crate) fn clock(&mut self) -> Result<(), ()> {
+    /**
+     * Runs one CPU cycle. Every cycle makes the one bus access the 6502 makes on it,
+     * including the dummy reads while an indexed address is fixed up and the write of the
+     * unmodified value in read-modify-write instructions, so register side effects land on
+     * the right cycle. Nothing else on the bus moves, see run_cycle.
+     */
+    pub fn clock(&mut self) -> Result<(), StopReason> {
+        if self.halted {
+            self.clock_count += 1;
+            return Err(StopReason::Jam(self.pc));
+        }
         if self.cycles == 0 {
-            self.opcode = self.read(self.pc);
-            self.set_flag(U, true);
-            self.pc += 1;
-            self.cycles = self.lookup[self.opcode as usize].cycles as u8;
-
-            let additional_cycle1 = match self.lookup[self.opcode as usize].addr {
-                AddressModes::Imp => self.imp(),
-                AddressModes::Imm => self.imm(),
-                AddressModes::Rel => self.rel(),
-                AddressModes::Abs => self.abs(),
-                AddressModes::Izx => self.izx(),
-                AddressModes::Izy => self.izy(),
-                AddressModes::Zp0 => self.zp0(),
-                AddressModes::Zpx => self.zpx(),
-                AddressModes::Zpy => self.zpy(),
-                AddressModes::Aby => self.aby(),
-                AddressModes::Ind => self.ind(),
-                AddressModes::Abx => self.abx()
-            };
-
-            let additional_cycle2 = match self.lookup[self.opcode as usize].operate {
-                Opcodes::Brk => self.brk(),
-                Opcodes::Bpl => self.bpl(),
-                Opcodes::Jsr => self.jsr(),
-                Opcodes::Bmi => self.bmi(),
-                Opcodes::Rti => self.rti(),
-                Opcodes::Bvc => self.bvc(),
-                Opcodes::Rts => self.rts(),
-                Opcodes::Bvs => self.bvs(),
-                Opcodes::Nop => self.nop(),
-                Opcodes::Bcc => self.bcc(),
-                Opcodes::Ldy => self.ldy(),
-                Opcodes::Bcs => self.bcs(),
-                Opcodes::Cpy => self.cpy(),
-                Opcodes::Bne => self.bne(),
-                Opcodes::Cpx => self.cpx(),
-                Opcodes::Beq => self.beq(),
-                Opcodes::Ora => self.ora(),
-                Opcodes::And => self.and(),
-                Opcodes::Eor => self.eor(),
-                Opcodes::Adc => self.adc(),
-                Opcodes::Sta => self.sta(),
-                Opcodes::Lda => self.lda(),
-                Opcodes::Cmp => self.cmp(),
-                Opcodes::Sbc => self.sbc(),
-                Opcodes::Xxx => self.xxx(),
-                Opcodes::Ldx => self.ldx(),
-                Opcodes::Bit => self.bit(),
-                Opcodes::Sty => self.sty(),
-                Opcodes::Asl => self.asl(),
-                Opcodes::Rol => self.rol(),
-                Opcodes::Lsr => self.lsr(),
-                Opcodes::Ror => self.ror(),
-                Opcodes::Stx => self.stx(),
-                Opcodes::Dec => self.dec(),
-                Opcodes::Inc => self.inc(),
-                Opcodes::Php => self.php(),
-                Opcodes::Clc => self.clc(),
-                Opcodes::Plp => self.plp(),
-                Opcodes::Sec => self.sec(),
-                Opcodes::Pha => self.pha(),
-                Opcodes::Cli => self.cli(),
-                Opcodes::Pla => self.pla(),
-                Opcodes::Sei => self.sei(),
-                Opcodes::Dey => self.dey(),
-                Opcodes::Tya => self.tya(),
-                Opcodes::Tay => self.tay(),
-                Opcodes::Clv => self.clv(),
-                Opcodes::Iny => self.iny(),
-                Opcodes::Cld => self.cld(),
-                Opcodes::Inx => self.inx(),
-                Opcodes::Sed => self.sed(),
-                Opcodes::Txa => self.txa(),
-                Opcodes::Txs => self.txs(),
-                Opcodes::Tax => self.tax(),
-                Opcodes::Tsx => self.tsx(),
-                Opcodes::Dex => self.dex(),
-                Opcodes::Jmp => self.jmp(),
-                Opcodes::Lax => self.lax(),
-                Opcodes::Sax => self.sax(),
-                Opcodes::Dcp => self.dcp(),
-                Opcodes::Isb => self.isb(),
-                Opcodes::Slo => self.slo(),
-                Opcodes::Rla => self.rla(),
-                Opcodes::Sre => self.sre(),
-                Opcodes::Rra => self.rra(),
-            };
-
-            self.cycles += additional_cycle1;
-            if additional_cycle2 {
-                self.cycles += 1;
-            }
+            self.start_sequence();
+        }
+        self.step += 1;
+
+        let done = match self.sequence {
+            Sequence::Instruction => self.instruction_cycle(),
+            Sequence::Interrupt(vector) => self.interrupt_cycle(vector),
+            Sequence::Reset => self.cycles == 1
+        };
+
+        // The poll on the last cycle but one decides what follows the instruction
+        if !done && self.polls() {
+            self.nmi_pending = self.nmi_signal;
+            self.irq_pending = self.irq_signal && !self.get_flag(I);
+        }
+        // The inputs are sampled late in the cycle and act from the next one
+        self.irq_signal = self.irq_line;
+        if self.nmi_edge {
+            self.nmi_edge = false;
+            self.nmi_signal = true;
+        }
 
+        self.clock_count += 1;
+        // The lookup table only gives the usual length, the sequence decides when it ends
+        if done {
+            self.cycles = 0;
             self.set_flag(U, true);
+        } else {
+            self.cycles = self.cycles.saturating_sub(1).max(1);
         }
-        self.clock_count += 1;
-        self.cycles -= 1;
 
-        let mut state = self.state.as_ref().expect("Missing state").as_ref().borrow_mut();
-        if self.pc as usize == state.code_end && self.cycles == 0 {
-            return Err(());
-        }
-        return Ok(());
+        return self.stop_reason();
     }
 
-    pub(crate) fn get_state_mut(&mut self) -> RefMut<'_, State> {
-        self.state.as_ref().expect("Missing Stete").as_ref().borrow_mut()
+    // One cycle for the whole system, the bus ticks and drives IRQ, then the CPU clocks
+    pub fn run_cycle(&mut self) -> Result<(), StopReason> {
+        let asserted = self.bus.tick();
+        self.set_irq_line(asserted);
+        return self.clock();
     }
 
-    pub(crate) fn get_state(&self) -> Ref<'_, State> {
-        self.state.as_ref().expect("Missing Stete").as_ref().borrow()
+    // Runs cycles until the current instruction, or interrupt sequence, is done
+    pub fn step(&mut self) -> StopReason {
+        loop {
+            if let Err(reason) = self.run_cycle() {
+                return reason;
+            }
+            if self.cycles == 0 {
+                return StopReason::InstructionComplete;
+            }
+        }
     }
 
-    fn complete(&mut self) -> bool {
-        return self.cycles == 0;
+    // Checked after every cycle, breakpoints only between instructions
+    fn stop_reason(&mut self) -> Result<(), StopReason> {
+        if self.halted {
+            return Err(StopReason::Jam(self.pc));
+        }
+        if let Some(addr) = self.watch_hit.take() {
+            return Err(StopReason::Watchpoint(addr));
+        }
+        if self.cycles > 0 {
+            return Ok(());
+        }
+        if self.breakpoints.contains(&self.pc) {
+            return Err(StopReason::Breakpoint(self.pc));
+        }
+        return Ok(());
     }
 
-    fn get_flag(&mut self, f: Flags) -> bool {
-        return if (self.status & f as u8) > 0 {
-            true
+    // Picks what runs once the last instruction is done, an NMI wins over an IRQ
+    fn start_sequence(&mut self) {
+        self.sequence = if self.nmi_pending {
+            self.nmi_signal = false;
+            Sequence::Interrupt(0xFFFA)
+        } else if self.irq_pending {
+            Sequence::Interrupt(0xFFFE)
         } else {
-            false
+            Sequence::Instruction
+        };
+        if self.sequence != Sequence::Instruction {
+            self.cycles = 7;
         }
+        self.nmi_pending = false;
+        self.irq_pending = false;
+        self.step = 0;
     }
 
-    pub(crate) fn set_flag(&mut self, f: Flags, v: bool) {
-        if v {
-            self.status |= f as u8;
-        } else {
-            self.status &= !(f as u8);
+    /**
+     * Whether interrupts are polled at the end of this cycle. BRK and the interrupt
+     * sequence never poll, so one instruction always runs before the next interrupt. A
+     * taken branch that stays on its page skips the poll on its last cycle but one.
+     */
+    fn polls(&self) -> bool {
+        if self.sequence != Sequence::Instruction {
+            return false;
         }
+        return match self.access() {
+            Access::Brk => false,
+            Access::Branch => !(self.step == 2 && !self.page_crossed),
+            _ => true
+        };
     }
 
-    fn read(&mut self, a: u16) -> u8 {
-        let mut state = self.state.as_ref().expect("Missing state").as_ref().borrow_mut();
-        mem_read(&mut state, a, false)
-    }
+    // One cycle of the current instruction, true on its last cycle
+    fn instruction_cycle(&mut self) -> bool {
+        if self.step == 1 {
+            self.opcode = self.read(self.pc);
+            self.set_flag(U, true);
+            self.pc = self.pc.wrapping_add(1);
+            self.cycles = self.lookup[self.opcode as usize].cycles as u8;
+            self.addressed = 0;
+            return false;
+        }
 
-    pub fn write(&mut self, a: u16, d: u8) {
-        let mut state = self.state.as_ref().expect("Missing state").as_ref().borrow_mut();
-        mem_write(&mut state, a, d)
+        let mode = self.lookup[self.opcode as usize].addr;
+        let access = self.access();
+        match access {
+            Access::Implied => {
+                self.read(self.pc);
+                self.fetched = self.a;
+                self.operate();
+                return true;
+            }
+            Access::Read | Access::Write | Access::ReadModifyWrite => {
+                if mode == AddressModes::Imm {
+                    self.fetched = self.read(self.pc);
+                    self.pc = self.pc.wrapping_add(1);
+                    self.operate();
+                    return true;
+                }
+                if self.addressed == 0 {
+                    if self.address_cycle(mode, access == Access::Read) {
+                        self.addressed = self.step;
+                    }
+                    return false;
+                }
+                return match (access, self.step - self.addressed) {
+                    (Access::Read, _) => {
+                        self.fetched = self.read(self.addr_abs);
+                        self.operate();
+                        true
+                    }
+                    // The store itself is the operation
+                    (Access::Write, _) => {
+                        self.operate();
+                        true
+                    }
+                    (_, 1) => {
+                        self.fetched = self.read(self.addr_abs);
+                        false
+                    }
+                    // The old value goes back out while the new one is worked out
+                    (_, 2) => {
+                        self.write(self.addr_abs, self.fetched);
+                        self.operate();
+                        false
+                    }
+                    _ => {
+                        self.write(self.addr_abs, self.fetched);
+                        true
+                    }
+                };
+            }
+            Access::Push => {
+                if self.step == 2 {
+                    self.read(self.pc);
+                    return false;
+                }
+                self.operate();
+                return true;
+            }
+            Access::Pull => {
+                match self.step {
+                    2 => { self.read(self.pc); }
+                    3 => { self.read(0x0100 + self.stkp as u16); }
+                    _ => {
+                        self.operate();
+                        return true;
+                    }
+                }
+                return false;
+            }
+            Access::Jump => {
+                if self.address_cycle(mode, false) {
+                    self.jmp();
+                    return true;
+                }
+                return false;
+            }
+            Access::Branch => return self.branch(),
+            Access::Brk => return self.brk(),
+            Access::Jsr => return self.jsr(),
+            Access::Rti => return self.rti(),
+            Access::Rts => return self.rts(),
+            Access::Jam => {
+                self.read(self.pc);
+                self.jam();
+                return true;
+            }
+        }
     }
 
-    pub fn reset(&mut self) {
-        self.addr_abs = 0xFFFC;
-        let lo = self.read(self.addr_abs + 0) as u16;
-        let hi = self.read(self.addr_abs + 1) as u16;
-
-        self.pc = (hi << 8) | lo;
-
-        // Reset internal registers
-        self.a = 0;
-        self.x = 0;
-        self.y = 0;
-        self.stkp = 0xFD;
-        self.status = 0x00 | U as u8;
+    fn access(&self) -> Access {
+        let instruction = &self.lookup[self.opcode as usize];
+        return match instruction.operate {
+            Opcodes::Brk => Access::Brk,
+            Opcodes::Jsr => Access::Jsr,
+            Opcodes::Rti => Access::Rti,
+            Opcodes::Rts => Access::Rts,
+            Opcodes::Pha | Opcodes::Php => Access::Push,
+            Opcodes::Pla | Opcodes::Plp => Access::Pull,
+            Opcodes::Jmp => Access::Jump,
+            Opcodes::Bpl | Opcodes::Bmi | Opcodes::Bvc | Opcodes::Bvs |
+            Opcodes::Bcc | Opcodes::Bcs | Opcodes::Bne | Opcodes::Beq => Access::Branch,
+            Opcodes::Jam => Access::Jam,
+            _ if instruction.addr == AddressModes::Imp => Access::Implied,
+            Opcodes::Sta | Opcodes::Stx | Opcodes::Sty | Opcodes::Sax |
+            Opcodes::Sha | Opcodes::Tas | Opcodes::Shy | Opcodes::Shx => Access::Write,
+            Opcodes::Asl | Opcodes::Lsr | Opcodes::Rol | Opcodes::Ror | Opcodes::Inc | Opcodes::Dec |
+            Opcodes::Slo | Opcodes::Rla | Opcodes::Sre | Opcodes::Rra | Opcodes::Dcp | Opcodes::Isb => Access::ReadModifyWrite,
+            _ => Access::Read
+        };
+    }
 
-        // Clear internal helper variables
-        self.addr_rel = 0x0000;
-        self.addr_abs = 0x0000;
-        self.fetched = 0x00;
+    /**
+     * One cycle of working out the effective address, true once addr_abs holds it. Reads
+     * that index within a page are ready a cycle early, everything else spends a cycle
+     * reading from the address before the carry into the high byte.
+     */
+    fn address_cycle(&mut self, mode: AddressModes, read: bool) -> bool {
+        return match (mode, self.step) {
+            (AddressModes::Zp0, _) => {
+                self.addr_abs = self.read(self.pc) as u16;
+                self.pc = self.pc.wrapping_add(1);
+                true
+            }
+            (AddressModes::Zpx, 2) | (AddressModes::Zpy, 2) => {
+                self.addr_abs = self.read(self.pc) as u16;
+                self.pc = self.pc.wrapping_add(1);
+                false
+            }
+            (AddressModes::Zpx, _) => {
+                self.read(self.addr_abs);
+                self.addr_abs = (self.addr_abs + self.x as u16) & 0x00FF;
+                true
+            }
+            (AddressModes::Zpy, _) => {
+                self.read(self.addr_abs);
+                self.addr_abs = (self.addr_abs + self.y as u16) & 0x00FF;
+                true
+            }
+            (AddressModes::Abs, 2) | (AddressModes::Abx, 2) | (AddressModes::Aby, 2) | (AddressModes::Ind, 2) => {
+                self.addr_abs = self.read(self.pc) as u16;
+                self.pc = self.pc.wrapping_add(1);
+                false
+            }
+            (AddressModes::Abs, _) => {
+                self.addr_abs |= (self.read(self.pc) as u16) << 8;
+                self.pc = self.pc.wrapping_add(1);
+                true
+            }
+            (AddressModes::Abx, 3) | (AddressModes::Aby, 3) => {
+                let base = ((self.read(self.pc) as u16) << 8) | self.addr_abs;
+                self.pc = self.pc.wrapping_add(1);
+                let index = if mode == AddressModes::Abx { self.x } else { self.y };
+                self.index(base, index, read)
+            }
+            (AddressModes::Abx, _) | (AddressModes::Aby, _) => self.fix_page(read),
+            (AddressModes::Ind, 3) => {
+                self.ptr = ((self.read(self.pc) as u16) << 8) | self.addr_abs;
+                self.pc = self.pc.wrapping_add(1);
+                false
+            }
+            (AddressModes::Ind, 4) => {
+                self.addr_abs = self.read(self.ptr) as u16;
+                false
+            }
+            (AddressModes::Ind, _) => {
+                // The pointer does not carry into the next page, JMP ($xxFF) reads $xx00
+                let hi = (self.ptr & 0xFF00) | (self.ptr.wrapping_add(1) & 0x00FF);
+                self.addr_abs |= (self.read(hi) as u16) << 8;
+                true
+            }
+            (AddressModes::Izx, 2) | (AddressModes::Izy, 2) => {
+                self.ptr = self.read(self.pc) as u16;
+                self.pc = self.pc.wrapping_add(1);
+                false
+            }
+            (AddressModes::Izx, 3) => {
+                self.read(self.ptr);
+                self.ptr = (self.ptr + self.x as u16) & 0x00FF;
+                false
+            }
+            (AddressModes::Izx, 4) | (AddressModes::Izy, 3) => {
+                self.addr_abs = self.read(self.ptr) as u16;
+                false
+            }
+            (AddressModes::Izx, _) => {
+                self.addr_abs |= (self.read((self.ptr + 1) & 0x00FF) as u16) << 8;
+                true
+            }
+            (AddressModes::Izy, 4) => {
+                let base = ((self.read((self.ptr + 1) & 0x00FF) as u16) << 8) | self.addr_abs;
+                self.index(base, self.y, read)
+            }
+            (AddressModes::Izy, _) => self.fix_page(read),
+            // Implied, immediate and relative operands never get here
+            _ => true
+        };
+    }
 
-        // Reset takes time
-        self.cycles = 8;
+    fn index(&mut self, base: u16, index: u8, read: bool) -> bool {
+        self.addr_abs = base.wrapping_add(index as u16);
+        self.page_crossed = base & 0xFF00 != self.addr_abs & 0xFF00;
+        return read && !self.page_crossed;
     }
 
-    fn fetch(&mut self) -> u8 {
-        if self.lookup[self.opcode as usize].addr != AddressModes::Imp {
-            self.fetched = self.read(self.addr_abs);
+    // The dummy read from the address with the low byte indexed but the high byte not fixed yet
+    fn fix_page(&mut self, read: bool) -> bool {
+        let addr = if self.page_crossed { self.addr_abs.wrapping_sub(0x0100) } else { self.addr_abs };
+        self.read(addr);
+        if read {
+            self.cycles += 1;
         }
-        return self.fetched;
+        return true;
     }
 
-    fn irq(&mut self) {
-        if self.get_flag(I) == false {
-            self.write(0x0100 + self.stkp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-            self.stkp -= 1;
-            self.write(0x0100 + self.stkp as u16, (self.pc & 0x00FF) as u8);
-            self.stkp -= 1;
-            self.set_flag(B, false);
-            self.set_flag(U, true);
-            self.set_flag(I, true);
-            self.write(0x0100 + self.stkp as u16, self.status);
-            self.stkp -= 1;
-            // Read new program counter location from fixed address
-            self.addr_abs = 0xFFFE;
-            let lo = self.read(self.addr_abs + 0);
-            let hi = self.read(self.addr_abs + 1);
-            self.pc = ((hi << 8) | lo) as u16;
-
-            // IRQs take time
-            self.cycles = 7;
+    // Cycles of an IRQ or NMI, two dummy fetches and then the same as BRK
+    fn interrupt_cycle(&mut self, vector: u16) -> bool {
+        if self.step <= 2 {
+            self.read(self.pc);
+            return false;
         }
+        return self.push_interrupt(vector, false);
+    }
+
+    // Pushes PC and the status, then loads PC from the vector. Cycles 3 to 7
+    fn push_interrupt(&mut self, vector: u16, brk: bool) -> bool {
+        match self.step {
+            3 => self.push((self.pc >> 8) as u8),
+            4 => self.push((self.pc & 0x00FF) as u8),
+            5 => {
+                let status = if brk { self.status | B as u8 } else { self.status & !(B as u8) };
+                self.push(status | U as u8);
+                self.set_flag(I, true);
+
+                // An NMI that comes in by now hijacks the vector, the pushed B flag stays
+                self.ptr = vector;
+                if self.nmi_signal {
+                    self.nmi_signal = false;
+                    self.ptr = 0xFFFA;
+                }
+            }
+            6 => self.addr_abs = self.read(self.ptr) as u16,
+            _ => {
+                self.pc = ((self.read(self.ptr.wrapping_add(1)) as u16) << 8) | self.addr_abs;
+                return true;
+            }
+        }
+        return false;
     }
 
-    fn nmi(&mut self) {
-        self.write(0x0100 + self.stkp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-        self.stkp -= 1;
-        self.write(0x0100 + self.stkp as u16, (self.pc & 0x00FF) as u8);
-        self.stkp -= 1;
-        self.set_flag(B, false);
-        self.set_flag(U, true);
-        self.set_flag(I, true);
-
-        self.write(0x0100 + self.stkp as u16, self.status);
-        self.stkp -= 1;
-        self.addr_abs = 0xFFFA;
-        let lo = self.read(self.addr_abs + 0);
-        let hi = self.read(self.addr_abs + 1);
+    fn push(&mut self, data: u8) {
+        self.write(0x0100 + self.stkp as u16, data);
+        self.stkp = self.stkp.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.stkp = self.stkp.wrapping_add(1);
+        return self.read(0x0100 + self.stkp as u16);
+    }
+
+    // Runs the operation once its operand is latched in fetched
+    fn operate(&mut self) {
+        match self.lookup[self.opcode as usize].operate {
+            Opcodes::Nop => self.nop(),
+            Opcodes::Ldy => self.ldy(),
+            Opcodes::Cpy => self.cpy(),
+            Opcodes::Cpx => self.cpx(),
+            Opcodes::Ora => self.ora(),
+            Opcodes::And => self.and(),
+            Opcodes::Eor => self.eor(),
+            Opcodes::Adc => self.adc(),
+            Opcodes::Sta => self.sta(),
+            Opcodes::Lda => self.lda(),
+            Opcodes::Cmp => self.cmp(),
+            Opcodes::Sbc => self.sbc(),
+            Opcodes::Ldx => self.ldx(),
+            Opcodes::Bit => self.bit(),
+            Opcodes::Sty => self.sty(),
+            Opcodes::Asl => self.asl(),
+            Opcodes::Rol => self.rol(),
+            Opcodes::Lsr => self.lsr(),
+            Opcodes::Ror => self.ror(),
+            Opcodes::Stx => self.stx(),
+            Opcodes::Dec => self.dec(),
+            Opcodes::Inc => self.inc(),
+            Opcodes::Php => self.php(),
+            Opcodes::Clc => self.clc(),
+            Opcodes::Plp => self.plp(),
+            Opcodes::Sec => self.sec(),
+            Opcodes::Pha => self.pha(),
+            Opcodes::Cli => self.cli(),
+            Opcodes::Pla => self.pla(),
+            Opcodes::Sei => self.sei(),
+            Opcodes::Dey => self.dey(),
+            Opcodes::Tya => self.tya(),
+            Opcodes::Tay => self.tay(),
+            Opcodes::Clv => self.clv(),
+            Opcodes::Iny => self.iny(),
+            Opcodes::Cld => self.cld(),
+            Opcodes::Inx => self.inx(),
+            Opcodes::Sed => self.sed(),
+            Opcodes::Txa => self.txa(),
+            Opcodes::Txs => self.txs(),
+            Opcodes::Tax => self.tax(),
+            Opcodes::Tsx => self.tsx(),
+            Opcodes::Dex => self.dex(),
+            Opcodes::Jmp => self.jmp(),
+            Opcodes::Lax => self.lax(),
+            Opcodes::Sax => self.sax(),
+            Opcodes::Dcp => self.dcp(),
+            Opcodes::Isb => self.isb(),
+            Opcodes::Slo => self.slo(),
+            Opcodes::Rla => self.rla(),
+            Opcodes::Sre => self.sre(),
+            Opcodes::Rra => self.rra(),
+            Opcodes::Jam => self.jam(),
+            Opcodes::Anc => self.anc(),
+            Opcodes::Alr => self.alr(),
+            Opcodes::Arr => self.arr(),
+            Opcodes::Xaa => self.xaa(),
+            Opcodes::Lxa => self.lxa(),
+            Opcodes::Axs => self.axs(),
+            Opcodes::Sha => self.sha(),
+            Opcodes::Tas => self.tas(),
+            Opcodes::Shy => self.shy(),
+            Opcodes::Shx => self.shx(),
+            Opcodes::Las => self.las(),
+            // These run their own cycles
+            Opcodes::Brk | Opcodes::Jsr | Opcodes::Rti | Opcodes::Rts |
+            Opcodes::Bpl | Opcodes::Bmi | Opcodes::Bvc | Opcodes::Bvs |
+            Opcodes::Bcc | Opcodes::Bcs | Opcodes::Bne | Opcodes::Beq => {}
+        }
+    }
 
-        self.pc = ((hi << 8) | lo) as u16;
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
 
-        self.cycles = 8;
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
     }
 
-    fn imp(&mut self) -> u8 {
-        self.fetched = self.a;
-        0
+    // Registers for code driving the CPU from outside, PC is a public field
+    pub fn a(&self) -> u8 {
+        return self.a;
     }
 
-    fn imm(&mut self) -> u8 {
-        self.addr_abs = self.pc;
-        self.pc += 1;
-        0
+    pub fn set_a(&mut self, a: u8) {
+        self.a = a;
     }
 
-    fn zp0(&mut self) -> u8 {
-        self.addr_abs = self.read(self.pc) as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
+    pub fn x(&self) -> u8 {
+        return self.x;
     }
 
-    fn zpx(&mut self) -> u8 {
-        self.addr_abs = (self.read(self.pc).wrapping_add(self.x)) as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
+    pub fn set_x(&mut self, x: u8) {
+        self.x = x;
     }
 
-    fn zpy(&mut self) -> u8 {
-        self.addr_abs = (self.read(self.pc).wrapping_add(self.y)) as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
+    pub fn y(&self) -> u8 {
+        return self.y;
     }
 
-    fn rel(&mut self) -> u8 {
-        self.addr_rel = self.read(self.pc) as u16;
-        self.pc += 1;
-        if self.addr_rel & 0x80 > 0 {
-            self.addr_rel |= 0xFF00;
-        }
-        return 0;
+    pub fn set_y(&mut self, y: u8) {
+        self.y = y;
     }
 
-    fn abs(&mut self) -> u8 {
-        let lo = self.read(self.pc) as u16;
-        self.pc += 1;
-        let hi = self.read(self.pc) as u16;
-        self.pc += 1;
+    pub fn stkp(&self) -> u8 {
+        return self.stkp;
+    }
 
-        self.addr_abs = (hi << 8) | lo;
+    pub fn set_stkp(&mut self, stkp: u8) {
+        self.stkp = stkp;
+    }
 
-        return 0;
+    // Status flags as they would be pushed, test bits with Flags
+    pub fn status(&self) -> u8 {
+        return self.status;
     }
 
-    fn abx(&mut self) -> u8 {
-        let lo = self.read(self.pc) as u16;
-        self.pc += 1;
-        let hi = self.read(self.pc) as u16;
-        self.pc += 1;
+    pub fn set_status(&mut self, status: u8) {
+        self.status = status;
+    }
 
-        self.addr_abs = (hi << 8) | lo;
-        self.addr_abs += self.x as u16;
+    fn complete(&mut self) -> bool {
+        return self.cycles == 0;
+    }
 
-        if (self.addr_abs & 0xFF00) != (hi << 8) {
-            return 1;
+    fn get_flag(&mut self, f: Flags) -> bool {
+        return if (self.status & f as u8) > 0 {
+            true
         } else {
-            return 0;
+            false
         }
     }
 
-    fn aby(&mut self) -> u8 {
-        let lo = self.read(self.pc) as u16;
-        self.pc += 1;
-        let hi = self.read(self.pc) as u16;
-        self.pc += 1;
-
-        self.addr_abs = (hi << 8) | lo;
-        self.addr_abs = self.addr_abs.wrapping_add(self.y as u16);
-
-        if (self.addr_abs & 0xFF00) != (hi << 8) {
-            return 1;
+    pub(crate) fn set_flag(&mut self, f: Flags, v: bool) {
+        if v {
+            self.status |= f as u8;
         } else {
-            return 0;
+            self.status &= !(f as u8);
         }
     }
 
-    #[allow(arithmetic_overflow)]
-    fn ind(&mut self) -> u8 {
-        let ptr_lo = self.read(self.pc) as u16;
-        self.pc += 1;
-        let ptr_hi = self.read(self.pc) as u16;
-        self.pc += 1;
-
-        let ptr = (ptr_hi << 8) | ptr_lo;
-
-        if ptr_lo == 0x00FF {
-            self.addr_abs = ((self.read(ptr & 0xFF00) as u16) << 8) | self.read(ptr + 0) as u16;
-        } else {
-            self.addr_abs = ((self.read(ptr + 1) as u16) << 8) | self.read(ptr + 0) as u16;
+    fn read(&mut self, a: u16) -> u8 {
+        let data = self.bus.read(a);
+        if let Some(log) = self.bus_log.as_mut() {
+            log.push((a, data, false));
+        }
+        if self.watchpoints.contains(&a) {
+            self.watch_hit = Some(a);
         }
+        data
+    }
 
-        return 0;
+    pub fn write(&mut self, a: u16, d: u8) {
+        if let Some(log) = self.bus_log.as_mut() {
+            log.push((a, d, true));
+        }
+        if self.watchpoints.contains(&a) {
+            self.watch_hit = Some(a);
+        }
+        self.bus.write(a, d)
     }
 
-    fn izx(&mut self) -> u8 {
-        let t = self.read(self.pc) as u16;
-        self.pc += 1;
+    pub fn reset(&mut self) {
+        self.addr_abs = 0xFFFC;
+        let lo = self.read(self.addr_abs + 0) as u16;
+        let hi = self.read(self.addr_abs + 1) as u16;
+
+        self.pc = (hi << 8) | lo;
 
-        let addr = t + self.x as u16;
-        let lo = self.read(addr & 0x00FF) as u16;
-        let hi = self.read((addr + 1) & 0x00FF) as u16;
+        // Reset internal registers
+        self.a = 0;
+        self.x = 0;
+        self.y = 0;
+        self.stkp = 0xFD;
+        self.status = 0x00 | U as u8 | I as u8;
 
-        self.addr_abs = (hi << 8) | lo;
+        // Clear internal helper variables
+        self.halted = false;
+        self.watch_hit = None;
+        self.addr_rel = 0x0000;
+        self.addr_abs = 0x0000;
+        self.fetched = 0x00;
+        self.irq_signal = false;
+        self.nmi_signal = false;
+        self.nmi_edge = false;
+        self.irq_pending = false;
+        self.nmi_pending = false;
 
-        return 0;
+        // Reset takes the 7 cycles of an interrupt sequence, counted from power on like nestest.log
+        self.sequence = Sequence::Reset;
+        self.step = 0;
+        self.cycles = 7;
+        self.clock_count = 0;
     }
 
-    fn izy(&mut self) -> u8 {
-        let t = self.read(self.pc) as u16;
-        self.pc += 1;
+    // The cycle steps latch the operand before the operation runs
+    fn fetch(&mut self) -> u8 {
+        return self.fetched;
+    }
 
-        let lo = self.read(t & 0x00FF) as u16;
-        let hi = self.read((t + 1) & 0x00FF) as u16;
+    // Shifts and increments hand their result back, the cycle steps write it out
+    fn store_result(&mut self, value: u8) {
+        if self.lookup[self.opcode as usize].addr == AddressModes::Imp {
+            self.a = value;
+        } else {
+            self.fetched = value;
+        }
+    }
 
-        self.addr_abs = (hi << 8) | lo;
-        self.addr_abs = self.addr_abs.wrapping_add(self.y as u16);
+    // Level triggered, the CPU takes the IRQ while the line is held and I is clear
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
 
-        if self.addr_abs & 0xFF00 != (hi << 8) {
-            return 1;
+    // Edge triggered, asserting the line latches one NMI until it is taken
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_edge = true;
         }
-
-        return 0;
+        self.nmi_line = asserted;
     }
 
-    fn adc(&mut self) -> bool {
+    fn adc(&mut self) {
         self.fetch();
 
+        if self.decimal_mode() {
+            self.adc_decimal();
+            return;
+        }
+
         let temp = self.a as u16 + self.fetched as u16 + self.get_flag(C) as u16;
 
         self.set_flag(C, temp > 255);
@@ -432,16 +809,16 @@ impl Cpu {
         self.set_flag(N, temp & 0x80 > 0);
 
         self.a = (temp & 0x00FF) as u8;
-
-        return true;
     }
 
-    fn sbc(&mut self) -> bool {
+    fn sbc(&mut self) {
         self.fetch();
 
         let value = self.fetched as u16 ^ 0x00FF;
 
-        let temp = self.a as u16 + value + self.get_flag(C) as u16;
+        let carry = self.get_flag(C) as u8;
+
+        let temp = self.a as u16 + value + carry as u16;
 
         self.set_flag(C, (temp & 0xFF00) > 0);
 
@@ -451,272 +828,176 @@ impl Cpu {
 
         self.set_flag(N, temp & 0x0080 > 0);
 
-        self.a = (temp & 0x00FF) as u8;
+        if self.decimal_mode() {
+            // The flags above are the binary ones on the NMOS part, only the result changes
+            self.a = self.sbc_decimal_result(carry);
+            return;
+        }
 
-        return true;
+        self.a = (temp & 0x00FF) as u8;
     }
 
-    fn and(&mut self) -> bool {
-        self.fetch();
-
-        self.a = self.a & self.fetched;
-
-        self.set_flag(Z, self.a == 0x00);
-
-        self.set_flag(N, self.a & 0x80 > 0);
-
-        return true;
+    fn decimal_mode(&mut self) -> bool {
+        return self.variant == CpuVariant::Nmos6502 && self.get_flag(D);
     }
 
+    /**
+     * NMOS 6502 decimal ADC. Each nibble is corrected by 6 once it goes past 9. Z comes from
+     * the binary sum, while N and V are taken after the low nibble is corrected but before
+     * the high one, which is why they look odd for BCD results.
+     */
+    fn adc_decimal(&mut self) {
+        let a = self.a as u16;
+        let value = self.fetched as u16;
+        let carry = self.get_flag(C) as u16;
 
-    // Arithmetic Shift Left
-    fn asl(&mut self) -> bool {
-        self.fetch();
-
-        let temp = (self.fetched as u16) << 1;
-
-        self.set_flag(C, (temp & 0xFF00) > 0);
-
-        self.set_flag(Z, (temp & 0x00FF) == 0x00);
-
-        self.set_flag(N, (temp & 0x80) > 0);
+        self.set_flag(Z, (a + value + carry) & 0x00FF == 0);
 
-        if self.lookup[self.opcode as usize].addr == AddressModes::Imp {
-            self.a = (temp & 0x00FF) as u8;
-        } else {
-            self.write(self.addr_abs, (temp & 0x00FF) as u8);
+        let mut lo = (a & 0x0F) + (value & 0x0F) + carry;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
         }
+        let mut temp = (a & 0xF0) + (value & 0xF0) + lo;
 
-        return false;
-    }
-
-    fn bcc(&mut self) -> bool {
-        if self.get_flag(C) == false {
-            self.cycles += 1;
-            let rel = self.addr_rel as i16;
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
+        self.set_flag(N, temp & 0x0080 > 0);
+        self.set_flag(V, (!(a ^ value) & (a ^ temp)) & 0x0080 > 0);
 
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
+        if temp >= 0xA0 {
+            temp += 0x60;
         }
+        self.set_flag(C, temp >= 0x100);
 
-        return false;
+        self.a = (temp & 0x00FF) as u8;
     }
 
-    fn bcs(&mut self) -> bool {
-        if self.get_flag(C) == true {
-            self.cycles += 1;
-
-            let rel = self.addr_rel as i16;
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
+    // NMOS 6502 decimal SBC result, borrowing 6 from any nibble that went below zero
+    fn sbc_decimal_result(&self, carry: u8) -> u8 {
+        let a = self.a as i16;
+        let value = self.fetched as i16;
 
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
+        let mut lo = (a & 0x0F) - (value & 0x0F) + carry as i16 - 1;
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+        let mut temp = (a & 0xF0) - (value & 0xF0) + lo;
+        if temp < 0 {
+            temp -= 0x60;
         }
 
-        return false;
+        return (temp & 0x00FF) as u8;
     }
 
-    fn beq(&mut self) -> bool {
-        if self.get_flag(Z) == true {
-            self.cycles += 1;
+    fn and(&mut self) {
+        self.fetch();
 
-            let rel = self.addr_rel as i16;
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
+        self.a = self.a & self.fetched;
 
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
-        }
+        self.set_flag(Z, self.a == 0x00);
 
-        return false;
+        self.set_flag(N, self.a & 0x80 > 0);
     }
 
-    fn bmi(&mut self) -> bool {
-        if self.get_flag(N) == true {
-            self.cycles += 1;
-            let rel = self.addr_rel as i16;
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
 
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
-        }
-
-        return false;
-    }
-
-    fn bne(&mut self) -> bool {
-        if self.get_flag(Z) == false {
-            self.cycles += 1;
-            let rel = self.addr_rel as i16;
+    // Arithmetic Shift Left
+    fn asl(&mut self) {
+        self.fetch();
 
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
+        let temp = (self.fetched as u16) << 1;
 
-            if (self.addr_abs & 0xFF00) != (self.pc & 0xFF00) {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
-        }
+        self.set_flag(C, (temp & 0xFF00) > 0);
 
-        return false;
-    }
+        self.set_flag(Z, (temp & 0x00FF) == 0x00);
 
-    fn bpl(&mut self) -> bool {
-        if self.get_flag(N) == false {
-            self.cycles += 1;
+        self.set_flag(N, (temp & 0x80) > 0);
 
-            let rel = self.addr_rel as i16;
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
+        self.store_result((temp & 0x00FF) as u8);
+    }
+
+    fn branch_taken(&mut self) -> bool {
+        return match self.lookup[self.opcode as usize].operate {
+            Opcodes::Bpl => !self.get_flag(N),
+            Opcodes::Bmi => self.get_flag(N),
+            Opcodes::Bvc => !self.get_flag(V),
+            Opcodes::Bvs => self.get_flag(V),
+            Opcodes::Bcc => !self.get_flag(C),
+            Opcodes::Bcs => self.get_flag(C),
+            Opcodes::Bne => !self.get_flag(Z),
+            _ => self.get_flag(Z)
+        };
+    }
+
+    // A taken branch costs a cycle, and one more when the target is on another page
+    fn branch(&mut self) -> bool {
+        match self.step {
+            2 => {
+                self.addr_rel = self.read(self.pc) as i8 as u16;
+                self.pc = self.pc.wrapping_add(1);
+                if !self.branch_taken() {
+                    return true;
+                }
+                self.addr_abs = self.pc.wrapping_add(self.addr_rel);
+                self.page_crossed = self.addr_abs & 0xFF00 != self.pc & 0xFF00;
+                self.cycles += 1;
+                return false;
             }
-
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
+            3 => {
+                self.read(self.pc);
+                if !self.page_crossed {
+                    self.pc = self.addr_abs;
+                    return true;
+                }
+                // PC is fetched from with only the low byte updated
+                self.pc = (self.pc & 0xFF00) | (self.addr_abs & 0x00FF);
                 self.cycles += 1;
+                return false;
+            }
+            _ => {
+                self.read(self.pc);
+                self.pc = self.addr_abs;
+                return true;
             }
-            self.pc = self.addr_abs;
         }
-
-        return false;
     }
 
-    fn bit(&mut self) -> bool {
+    fn bit(&mut self) {
         self.fetch();
 
         let temp = self.a & self.fetched;
         self.set_flag(Z, (temp & 0x00FF) == 0x00);
         self.set_flag(N, self.fetched & (1 << 7) > 0);
         self.set_flag(V, self.fetched & (1 << 6) > 0);
-
-        return false;
     }
 
     fn brk(&mut self) -> bool {
-        self.pc += 1;
-
-        self.set_flag(I, true);
-
-        self.write((0x0100 + self.stkp as u16) as u16, ((self.pc >> 8) & 0x00FF) as u8);
-
-        if self.stkp > 0 {
-            self.stkp -= 1;
-        }
-
-        self.write((0x0100 + self.stkp as u16) as u16, (self.pc & 0x00FF) as u8);
-        if self.stkp > 0 {
-            self.stkp -= 1;
-        }
-
-        self.set_flag(B, true);
-
-        self.write((0x0100 + self.stkp as u16) as u16, self.status);
-
-        if self.stkp > 0 {
-            self.stkp -= 1;
+        if self.step == 2 {
+            // The byte after BRK is read and skipped
+            self.read(self.pc);
+            self.pc = self.pc.wrapping_add(1);
+            return false;
         }
-
-        self.set_flag(B, false);
-
-        let a = ((self.read(0xFFFF) as u16) << 8) as u8;
-
-        self.pc = self.read(0xFFFE) as u16 | (a) as u16;
-
-        return false;
-    }
-
-    fn bvc(&mut self) -> bool {
-        if self.get_flag(V) == false {
-            self.cycles += 1;
-            let rel = self.addr_rel as i16;
-
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
-
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
-        }
-
-        return false;
-    }
-
-    fn bvs(&mut self) -> bool {
-        if self.get_flag(V) == true {
-            self.cycles += 1;
-
-            let rel = self.addr_rel as i16;
-
-            if rel > 0 {
-                self.addr_abs = self.pc + rel.abs() as u16;
-            } else {
-                self.addr_abs = self.pc - rel.abs() as u16;
-            }
-
-            if self.addr_abs & 0xFF00 != self.pc & 0xFF00 {
-                self.cycles += 1;
-            }
-            self.pc = self.addr_abs;
-        }
-
-        return false;
+        return self.push_interrupt(0xFFFE, true);
     }
 
     /**
      * Clear cary
      */
-    fn clc(&mut self) -> bool {
+    fn clc(&mut self) {
         self.set_flag(C, false);
-        return false;
     }
 
-    fn cld(&mut self) -> bool {
+    fn cld(&mut self) {
         self.set_flag(D, false);
-        return false;
     }
 
-    fn cli(&mut self) -> bool {
+    fn cli(&mut self) {
         self.set_flag(I, false);
-        return false;
     }
 
-    fn clv(&mut self) -> bool {
+    fn clv(&mut self) {
         self.set_flag(V, false);
-        return false;
     }
 
-    fn cmp(&mut self) -> bool {
+    fn cmp(&mut self) {
         self.fetch();
 
 
@@ -728,11 +1009,9 @@ impl Cpu {
         self.set_flag(Z, (temp & 0x00FF) == 0x0000);
 
         self.set_flag(N, temp & 0x0080 > 0);
-
-        return true;
     }
 
-    fn cpx(&mut self) -> bool {
+    fn cpx(&mut self) {
         self.fetch();
 
         let temp = self.x.wrapping_sub(self.fetched);
@@ -742,11 +1021,9 @@ impl Cpu {
         self.set_flag(Z, (temp & 0x00FF) == 0x0000);
 
         self.set_flag(N, temp & 0x0080 > 0);
-
-        return false;
     }
 
-    fn cpy(&mut self) -> bool {
+    fn cpy(&mut self) {
         self.fetch();
 
         let temp = self.y.wrapping_sub(self.fetched);
@@ -756,45 +1033,37 @@ impl Cpu {
         self.set_flag(Z, (temp & 0x00FF) == 0x0000);
 
         self.set_flag(N, temp & 0x0080 > 0);
-
-        return false;
     }
 
-    fn dec(&mut self) -> bool {
+    fn dec(&mut self) {
         self.fetch();
 
         let temp = self.fetched.wrapping_sub(1);
 
-        self.write(self.addr_abs, temp & 0x00FF);
+        self.store_result(temp);
 
         self.set_flag(Z, (temp & 0x00FF) == 0x0000);
 
         self.set_flag(N, temp & 0x0080 > 0);
-
-        return false;
     }
 
-    fn dex(&mut self) -> bool {
+    fn dex(&mut self) {
         self.x = self.x.wrapping_sub(1);
 
         self.set_flag(Z, self.x == 0x00);
 
         self.set_flag(N, self.x & 0x80 > 0);
-
-        return false;
     }
 
-    fn dey(&mut self) -> bool {
+    fn dey(&mut self) {
         self.y = self.y.wrapping_sub(1);
 
         self.set_flag(Z, self.y == 0x00);
 
         self.set_flag(N, self.y & 0x80 > 0);
-
-        return false;
     }
 
-    fn eor(&mut self) -> bool {
+    fn eor(&mut self) {
         self.fetch();
 
         self.a = self.a ^ self.fetched;
@@ -802,64 +1071,62 @@ impl Cpu {
         self.set_flag(Z, self.a == 0x00);
 
         self.set_flag(N, self.a & 0x80 > 0);
-
-        return true;
     }
 
-    fn inc(&mut self) -> bool {
+    fn inc(&mut self) {
         self.fetch();
 
         let temp = self.fetched.wrapping_add(1);
 
-        self.write(self.addr_abs, temp & 0x00FF);
+        self.store_result(temp);
 
         self.set_flag(Z, (temp & 0x00FF) == 0x0000);
 
         self.set_flag(N, temp & 0x0080 > 0);
-
-        return false;
     }
 
-    fn inx(&mut self) -> bool {
+    fn inx(&mut self) {
         self.x = self.x.wrapping_add(1);
 
         self.set_flag(Z, self.x == 0x00);
 
         self.set_flag(N, self.x & 0x80 > 0);
-
-        return false;
     }
 
-    fn iny(&mut self) -> bool {
+    fn iny(&mut self) {
         self.y = self.y.wrapping_add(1);
 
         self.set_flag(Z, self.y == 0x00);
 
         self.set_flag(N, self.y & 0x80 > 0);
-
-        return false;
     }
 
-    fn jmp(&mut self) -> bool {
+    fn jmp(&mut self) {
         self.pc = self.addr_abs;
-        return false;
     }
 
     fn jsr(&mut self) -> bool {
-        self.pc -= 1;
-        self.write(0x0100 + self.stkp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-        self.stkp -= 1;
-        self.write(0x0100 + self.stkp as u16, (self.pc & 0x00FF) as u8);
-        self.stkp -= 1;
-
-        self.pc = self.addr_abs;
+        match self.step {
+            2 => {
+                self.addr_abs = self.read(self.pc) as u16;
+                self.pc = self.pc.wrapping_add(1);
+            }
+            3 => { self.read(0x0100 + self.stkp as u16); }
+            // PC still points at the high byte of the target, RTS adds the one back
+            4 => self.push((self.pc >> 8) as u8),
+            5 => self.push((self.pc & 0x00FF) as u8),
+            _ => {
+                self.pc = ((self.read(self.pc) as u16) << 8) | self.addr_abs;
+                return true;
+            }
+        }
         return false;
     }
 
     /**
     * Load accumulator
     */
-    fn lda(&mut self) -> bool {
+    fn lda(&mut self) {
         self.fetch();
 
         self.a = self.fetched;
@@ -867,11 +1134,9 @@ impl Cpu {
         self.set_flag(Z, self.a == 0x00);
 
         self.set_flag(N, self.a & 0x80 > 0);
-
-        return true;
     }
 
-    fn ldx(&mut self) -> bool {
+    fn ldx(&mut self) {
         self.fetch();
 
         self.x = self.fetched;
@@ -879,11 +1144,9 @@ impl Cpu {
         self.set_flag(Z, self.x == 0x00);
 
         self.set_flag(N, self.x & 0x80 > 0);
-
-        return true;
     }
 
-    fn lax(&mut self) -> bool {
+    fn lax(&mut self) {
         self.fetch();
         self.a = self.fetched;
         self.x = self.a;
@@ -891,31 +1154,24 @@ impl Cpu {
         self.set_flag(Z, self.x == 0x00);
 
         self.set_flag(N, self.x & 0x80 > 0);
-
-        return true;
     }
 
-    fn sax(&mut self) -> bool {
+    fn sax(&mut self) {
         let data = self.a & self.x;
         self.write(self.addr_abs, data);
-        return false;
     }
 
-    fn dcp(&mut self) -> bool {
+    fn dcp(&mut self) {
         self.fetch();
         let data = self.fetched.wrapping_sub(1);
-        self.write(self.addr_abs, data);
+        self.store_result(data);
 
-        if data <= self.a {
-            self.set_flag(C, true);
-        }
+        self.set_flag(C, self.a >= data);
 
         let tmp = self.a.wrapping_sub(data);
         self.set_flag(Z, tmp == 0x00);
 
         self.set_flag(N, tmp & 0x80 > 0);
-
-        return false;
     }
 
     /**
@@ -933,61 +1189,145 @@ impl Cpu {
     FLAG_V = (((A ^ tmp8) & (A ^ tmpi)) & 0x80) ? 1 : 0;
     A = (u8)tmpi;
     **/
-    fn isb(&mut self) -> bool {
+    fn isb(&mut self) {
         self.inc();
-
-        self.fetch();
-
-        let sum = (self.a as u16).wrapping_sub(self.fetched as u16)
-            .wrapping_sub(1 - self.get_flag(C) as u16);
-
-        self.set_flag(C, (sum & 0xFF00) == 0);
-
-        let result = sum as u8;
-
-        if (self.fetched ^ result) & (result ^ self.a) & 0x80 != 0 {
-            self.set_flag(V, true);
-        } else {
-            self.set_flag(V, false);
-        }
-
-        self.a = result;
-
-        self.set_flag(N, self.a & 0x80 > 0);
-        self.set_flag(Z, self.a == 0x00);
-
-        return false;
+        self.sbc();
     }
 
     // {adr}:={adr}rol A:=A and {adr}
-    fn rla(&mut self) -> bool {
+    fn rla(&mut self) {
         self.rol();
         self.and();
-        return false;
     }
 
     //	{adr}:={adr}*2 A:=A or {adr}
-    fn slo(&mut self) -> bool {
+    fn slo(&mut self) {
         self.asl();
         self.ora();
-        return false;
     }
 
     //	{adr}:={adr}/2 A:=A exor {adr}
-    fn sre(&mut self) -> bool {
+    fn sre(&mut self) {
         self.lsr();
         self.eor();
-        return false;
     }
 
     //	{adr}:={adr}ror A:=A adc {adr}
-    fn rra(&mut self) -> bool {
+    fn rra(&mut self) {
         self.ror();
         self.adc();
-        return false;
     }
 
-    fn ldy(&mut self) -> bool {
+    // Unofficial opcodes, behaviour as described in "NMOS 6510 Unintended Opcodes"
+
+    // The CPU locks up with the opcode on the bus, PC stays on it
+    fn jam(&mut self) {
+        self.pc = self.pc.wrapping_sub(1);
+        self.halted = true;
+    }
+
+    // A:=A and #{imm}, C is copied from N
+    fn anc(&mut self) {
+        self.and();
+        let negative = self.get_flag(N);
+        self.set_flag(C, negative);
+    }
+
+    // A:=(A and #{imm})/2
+    fn alr(&mut self) {
+        self.fetch();
+        let temp = self.a & self.fetched;
+        self.set_flag(C, temp & 0x01 > 0);
+        self.a = temp >> 1;
+        self.set_flag(Z, self.a == 0x00);
+        self.set_flag(N, false);
+    }
+
+    // A:=(A and #{imm}) ror 1, C from bit 6 and V from bit 6 xor bit 5 of the result
+    fn arr(&mut self) {
+        self.fetch();
+        let temp = self.a & self.fetched;
+        self.a = (temp >> 1) | ((self.get_flag(C) as u8) << 7);
+        self.set_flag(Z, self.a == 0x00);
+        self.set_flag(N, self.a & 0x80 > 0);
+        self.set_flag(C, self.a & 0x40 > 0);
+        self.set_flag(V, ((self.a >> 6) ^ (self.a >> 5)) & 0x01 > 0);
+    }
+
+    // Unstable, A:=(A or magic) and X and #{imm}. The magic constant differs between
+    // chips, $EE is the common one
+    fn xaa(&mut self) {
+        self.fetch();
+        self.a = (self.a | 0xEE) & self.x & self.fetched;
+        self.set_flag(Z, self.a == 0x00);
+        self.set_flag(N, self.a & 0x80 > 0);
+    }
+
+    // Unstable, A,X:=(A or magic) and #{imm}
+    fn lxa(&mut self) {
+        self.fetch();
+        self.a = (self.a | 0xEE) & self.fetched;
+        self.x = self.a;
+        self.set_flag(Z, self.a == 0x00);
+        self.set_flag(N, self.a & 0x80 > 0);
+    }
+
+    // X:=(A and X)-#{imm}, flags like CMP
+    fn axs(&mut self) {
+        self.fetch();
+        let temp = self.a & self.x;
+        self.x = temp.wrapping_sub(self.fetched);
+        self.set_flag(C, temp >= self.fetched);
+        self.set_flag(Z, self.x == 0x00);
+        self.set_flag(N, self.x & 0x80 > 0);
+    }
+
+    /**
+     * The SHA / SHX / SHY / TAS stores AND the value with the high byte of the base
+     * address plus one. When indexing crosses a page the result also replaces the high
+     * byte of the address that gets written.
+     */
+    fn store_and_high(&mut self, value: u8, index: u8) {
+        let base = self.addr_abs.wrapping_sub(index as u16);
+        let data = value & ((base >> 8) as u8).wrapping_add(1);
+        if base & 0xFF00 != self.addr_abs & 0xFF00 {
+            self.addr_abs = ((data as u16) << 8) | (self.addr_abs & 0x00FF);
+        }
+        self.write(self.addr_abs, data);
+    }
+
+    // {adr}:=A and X and H+1
+    fn sha(&mut self) {
+        self.store_and_high(self.a & self.x, self.y);
+    }
+
+    // S:=A and X, {adr}:=S and H+1
+    fn tas(&mut self) {
+        self.stkp = self.a & self.x;
+        self.store_and_high(self.stkp, self.y);
+    }
+
+    // {adr}:=Y and H+1
+    fn shy(&mut self) {
+        self.store_and_high(self.y, self.x);
+    }
+
+    // {adr}:=X and H+1
+    fn shx(&mut self) {
+        self.store_and_high(self.x, self.y);
+    }
+
+    // A,X,S:={adr} and S
+    fn las(&mut self) {
+        self.fetch();
+        self.a = self.fetched & self.stkp;
+        self.x = self.a;
+        self.stkp = self.a;
+        self.set_flag(Z, self.a == 0x00);
+        self.set_flag(N, self.a & 0x80 > 0);
+    }
+
+    fn ldy(&mut self) {
         self.fetch();
 
         self.y = self.fetched;
@@ -995,11 +1335,9 @@ impl Cpu {
         self.set_flag(Z, self.y == 0x00);
 
         self.set_flag(N, self.y & 0x80 > 0);
-
-        return true;
     }
 
-    fn lsr(&mut self) -> bool {
+    fn lsr(&mut self) {
         self.fetch();
 
         self.set_flag(C, (self.fetched & 0x0001) > 0);
@@ -1010,29 +1348,14 @@ impl Cpu {
 
         self.set_flag(N, (temp & 0x0080) > 0);
 
-        if self.lookup[self.opcode as usize].addr == AddressModes::Imp {
-            self.a = (temp & 0x00FF) as u8;
-        } else {
-            self.write(self.addr_abs, (temp & 0x00FF) as u8);
-        }
-
-        return false;
+        self.store_result((temp & 0x00FF) as u8);
     }
 
-    fn nop(&mut self) -> bool {
-        match self.opcode {
-            0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74 | 0xd4 | 0xf4 | 0x0c | 0x1c
-            | 0x3c | 0x5c | 0x7c | 0xdc | 0xfc => {
-                self.fetch();
-                self.addr_abs = self.addr_abs.wrapping_add(1);
-                return true;
-            }
-            _ => {}
-        }
-        return false;
+    // The unofficial NOPs with an operand read it like any other read instruction
+    fn nop(&mut self) {
     }
 
-    fn ora(&mut self) -> bool {
+    fn ora(&mut self) {
         self.fetch();
 
         self.a = self.a | self.fetched;
@@ -1040,43 +1363,31 @@ impl Cpu {
         self.set_flag(Z, self.a == 0x00);
 
         self.set_flag(N, self.a & 0x80 > 0);
-
-        return true;
     }
 
-    fn pha(&mut self) -> bool {
-        self.write(0x0100 + self.stkp as u16, self.a);
-        self.stkp -= 1;
-
-        return false;
+    fn pha(&mut self) {
+        self.push(self.a);
     }
 
-    fn php(&mut self) -> bool {
-        self.write(0x0100 + self.stkp as u16, self.status | B as u8 | U as u8);
+    fn php(&mut self) {
+        self.push(self.status | B as u8 | U as u8);
         self.set_flag(B, false);
         self.set_flag(U, false);
-        self.stkp -= 1;
-
-        return false;
     }
 
-    fn pla(&mut self) -> bool {
-        self.stkp += 1;
-        self.a = self.read(0x0100 + self.stkp as u16);
+    fn pla(&mut self) {
+        self.a = self.pull();
         self.set_flag(Z, self.a == 0x00);
         self.set_flag(N, self.a & 0x80 > 0);
-
-        return false;
     }
 
-    fn plp(&mut self) -> bool {
-        self.stkp += 1;
-        self.status = self.read(0x0100 + self.stkp as u16);
+    // B only exists on the stack
+    fn plp(&mut self) {
+        self.status = self.pull() & !(B as u8);
         self.set_flag(U, true);
-        return false;
     }
 
-    fn rol(&mut self) -> bool {
+    fn rol(&mut self) {
         self.fetch();
 
         let temp = ((self.fetched as u16) << 1) | self.get_flag(C) as u16;
@@ -1087,16 +1398,10 @@ impl Cpu {
 
         self.set_flag(N, (temp & 0x0080) > 0);
 
-        if self.lookup[self.opcode as usize].addr == AddressModes::Imp {
-            self.a = (temp & 0x00FF) as u8;
-        } else {
-            self.write(self.addr_abs, (temp & 0x00FF) as u8);
-        }
-
-        return false;
+        self.store_result((temp & 0x00FF) as u8);
     }
 
-    fn ror(&mut self) -> bool {
+    fn ror(&mut self) {
         self.fetch();
 
         let temp = ((self.get_flag(C) as u16) << 7) | (self.fetched as u16 >> 1);
@@ -1107,25 +1412,24 @@ impl Cpu {
 
         self.set_flag(N, (temp & 0x0080) > 0);
 
-        if self.lookup[self.opcode as usize].addr == AddressModes::Imp {
-            self.a = (temp & 0x00FF) as u8;
-        } else {
-            self.write(self.addr_abs, (temp & 0x00FF) as u8);
-        }
-
-        return false;
+        self.store_result((temp & 0x00FF) as u8);
     }
 
     fn rti(&mut self) -> bool {
-        self.stkp += 1;
-        self.status = self.read(0x0100 + self.stkp as u16);
-        self.status &= !(B as u8);
-        self.status &= !(U as u8);
-        self.stkp += 1;
-        self.pc = self.read(0x0100 + self.stkp as u16) as u16;
-        self.stkp += 1;
-        self.pc |= (self.read(0x0100 + self.stkp as u16) as u16) << 8;
-
+        match self.step {
+            2 => { self.read(self.pc); }
+            3 => { self.read(0x0100 + self.stkp as u16); }
+            4 => {
+                self.status = self.pull();
+                self.status &= !(B as u8);
+                self.status &= !(U as u8);
+            }
+            5 => self.pc = self.pull() as u16,
+            _ => {
+                self.pc |= (self.pull() as u16) << 8;
+                return true;
+            }
+        }
         return false;
     }
 
@@ -1144,175 +1448,136 @@ impl Cpu {
     }
     */
     fn rts(&mut self) -> bool {
-        self.stkp += 1;
-        self.pc = self.read(0x0100 + self.stkp as u16) as u16;
-        self.stkp += 1;
-
-        let tmp = (self.read(0x0100 + self.stkp as u16) as u16) << 8;
-        self.pc |= tmp;
-
-        self.pc += 1;
-
+        match self.step {
+            2 => { self.read(self.pc); }
+            3 => { self.read(0x0100 + self.stkp as u16); }
+            4 => self.pc = self.pull() as u16,
+            5 => self.pc |= (self.pull() as u16) << 8,
+            _ => {
+                self.read(self.pc);
+                self.pc = self.pc.wrapping_add(1);
+                return true;
+            }
+        }
         return false;
     }
 
-    fn sec(&mut self) -> bool {
+    fn sec(&mut self) {
         self.set_flag(C, true);
-        return false;
     }
 
-    fn sed(&mut self) -> bool {
+    fn sed(&mut self) {
         self.set_flag(D, true);
-        return false;
     }
 
-    fn sei(&mut self) -> bool {
+    fn sei(&mut self) {
         self.set_flag(I, true);
-        return false;
     }
 
-    fn sta(&mut self) -> bool {
+    fn sta(&mut self) {
         self.write(self.addr_abs, self.a);
-        return false;
     }
 
-    fn stx(&mut self) -> bool {
+    fn stx(&mut self) {
         self.write(self.addr_abs, self.x);
-        return false;
     }
 
-    fn sty(&mut self) -> bool {
+    fn sty(&mut self) {
         self.write(self.addr_abs, self.y);
-        return false;
     }
 
-    fn tax(&mut self) -> bool {
+    fn tax(&mut self) {
         self.x = self.a;
         self.set_flag(Z, self.x == 0x00);
         self.set_flag(N, (self.x & 0x80) > 0);
-        return false;
     }
 
-    fn tay(&mut self) -> bool {
+    fn tay(&mut self) {
         self.y = self.a;
         self.set_flag(Z, self.y == 0x00);
         self.set_flag(N, (self.y & 0x80) > 0);
-        return false;
     }
 
-    fn tsx(&mut self) -> bool {
+    fn tsx(&mut self) {
         self.x = self.stkp;
         self.set_flag(Z, self.x == 0x00);
         self.set_flag(N, (self.x & 0x80) > 0);
-        return false;
     }
 
-    fn txa(&mut self) -> bool {
+    fn txa(&mut self) {
         self.a = self.x;
         self.set_flag(Z, self.a == 0x00);
         self.set_flag(N, (self.a & 0x80) > 0);
-        return false;
     }
 
-    fn txs(&mut self) -> bool {
+    fn txs(&mut self) {
         self.stkp = self.x;
-        return false;
     }
 
-    fn tya(&mut self) -> bool {
+    fn tya(&mut self) {
         self.a = self.y;
         self.set_flag(Z, self.a == 0x00);
         self.set_flag(N, (self.a & 0x80) > 0);
-        return false;
     }
 
-    fn xxx(&mut self) -> bool {
-        return false;
+    pub fn variant(&self) -> CpuVariant {
+        return self.variant;
     }
 
-    pub(crate) fn disassemble(&mut self) -> HashMap<u32, String> {
-        let mut addr: u32 = 0;
-        let stop_addr = 0xFFFF;
-
-        let mut map = HashMap::new();
-
-        while addr <= stop_addr {
-            let line_addr = addr;
-
-            let hex_str = hex::encode(&addr.to_be_bytes());
-            let mut dis_string = format!("${}: ", &hex_str[hex_str.len()-4..]);
-
-            let opcode = self.read(addr as u16);
-            addr += 1;
-
-            dis_string = format!("{}{} ", dis_string, self.lookup[opcode as usize].name);
-
-            if self.lookup[opcode as usize].addr == AddressModes::Imp {
-                dis_string = format!("{}{}", dis_string, "{IMP}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Imm {
-                let value = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}#${} {}", dis_string, hex::encode(&value.to_be_bytes()), "{IMM}")
-            } else if self.lookup[opcode as usize].addr == AddressModes::Zp0 {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}${} {}", dis_string, hex::encode(&lo.to_be_bytes()), "{ZP0}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Zpx {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}${}, X {}", dis_string, hex::encode(&lo.to_be_bytes()), "{ZPX}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Zpy {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}${}, Y {}", dis_string, hex::encode(&lo.to_be_bytes()), "{ZPY}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Izx {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}(${}, X) {}", dis_string, hex::encode(&lo.to_be_bytes()), "{IZX}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Izy {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                dis_string = format!("{}(${}, Y) {}", dis_string, hex::encode(&lo.to_be_bytes()), "{IZY}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Abs {
-                let lo = self.read(addr as u16) as u16;
-                addr += 1;
-                let hi = self.read(addr as u16) as u16;
-                addr += 1;
-                let value = (hi << 8 | lo) as u8;
-                dis_string = format!("{}${} {}", dis_string, hex::encode(&value.to_be_bytes()), "{ABS}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Abx {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                let hi = self.read(addr as u16);
-                addr += 1;
-                let value = (((hi as u16) << 8) | lo as u16) as u8;
-                dis_string = format!("{}${}, X {}", dis_string, hex::encode(&value.to_be_bytes()), "{ABX}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Aby {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                let hi = self.read(addr as u16);
-                addr += 1;
-                let value = (((hi as u16) << 8) | lo as u16) as u8;
-                dis_string = format!("{}${}, Y {}", dis_string, hex::encode(&value.to_be_bytes()), "{ABY}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Ind {
-                let lo = self.read(addr as u16);
-                addr += 1;
-                let hi = self.read(addr as u16);
-                addr += 1;
-                let value = (((hi as u16) << 8) | lo as u16) as u8;
-                dis_string = format!("{}(${}) {}", dis_string, hex::encode(&value.to_be_bytes()), "{IND}");
-            } else if self.lookup[opcode as usize].addr == AddressModes::Rel {
-                let value = self.read(addr as u16);
-                addr += 1;
-                let addr_value = addr as u16 + value as u16;
-                dis_string = format!("{}${} [${}] {}",
-                                     dis_string,
-                                     hex::encode(&value.to_be_bytes()),
-                                     hex::encode(&addr_value.to_be_bytes()),
-                                     "{REL}");
-            }
-            map.insert(line_addr as u32, dis_string);
+    // Ricoh2A03 by default, switch to Nmos6502 for programs that use decimal mode
+    pub fn set_variant(&mut self, variant: CpuVariant) {
+        self.variant = variant;
+    }
+
+    // A JAM opcode stopped the CPU
+    pub fn is_halted(&self) -> bool {
+        return self.halted;
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    // Reads and writes the CPU makes itself, including dummy accesses, DMA is not watched
+    pub fn add_watchpoint(&mut self, addr: u16) {
+        self.watchpoints.insert(addr);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16) {
+        self.watchpoints.remove(&addr);
+    }
+
+    // Decodes the instruction at addr without side effects, the hints use the current X and Y
+    pub fn decode(&self, addr: u16) -> DecodedInstruction {
+        return decode(&self.lookup, addr, self.x, self.y, |a| self.bus.peek(a));
+    }
+
+    // Cycles run since the last reset
+    pub fn clock_count(&self) -> u32 {
+        return self.clock_count;
+    }
+
+    // Decodes instructions one after another from start, up to and including end
+    pub fn disassemble_range(&self, start: u16, end: u16) -> Vec<DecodedInstruction> {
+        let mut instructions = vec![];
+        let mut addr = start as u32;
+        while addr <= end as u32 {
+            let instruction = self.decode(addr as u16);
+            addr += instruction.len() as u32;
+            instructions.push(instruction);
         }
-        map
+        return instructions;
+    }
+
+    // The whole address space as text for the debug views, keyed by address
+    pub(crate) fn disassemble(&self) -> HashMap<u32, String> {
+        return self.disassemble_range(0x0000, 0xFFFF)
+            .into_iter()
+            .map(|instruction| (instruction.address as u32, format!("${:04X}: {}", instruction.address, instruction.to_ca65())))
+            .collect();
     }
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+/**
+ * Everything the 6502 core needs from the system around it. The CPU makes exactly one
+ * read or write per cycle through this, the NES bus (State) decodes the address to RAM,
+ * the PPU and APU registers and the cartridge, other systems can map whatever they like.
+ */
+pub trait CpuBus {
+    // A read as the CPU makes it, registers are free to react to it
+    fn read(&mut self, addr: u16) -> u8;
+
+    fn write(&mut self, addr: u16, data: u8);
+
+    // What read would return, for debuggers and the disassembler
+    fn peek(&self, addr: u16) -> u8;
+
+    /**
+     * One CPU cycle passes for everything else on the bus, called before the CPU's own
+     * access. Returns whether something holds the IRQ line low.
+     */
+    fn tick(&mut self) -> bool {
+        return false;
+    }
+}
+
+// 64K of RAM and nothing else, for running plain 6502 code and test suites
+pub struct FlatBus {
+    pub ram: Vec<u8>,
+}
+
+impl Default for FlatBus {
+
+    fn default() -> FlatBus {
+        FlatBus::new()
+    }
+}
+
+impl FlatBus {
+
+    pub fn new() -> FlatBus {
+        FlatBus {
+            ram: vec![0; 64 * 1024]
+        }
+    }
+
+    // Copies code to offset and points the reset vector at it
+    pub fn load(&mut self, code: &[u8], offset: u16) {
+        for (i, &byte) in code.iter().enumerate() {
+            self.ram[offset.wrapping_add(i as u16) as usize] = byte;
+        }
+        self.ram[0xFFFC] = (offset & 0x00FF) as u8;
+        self.ram[0xFFFD] = (offset >> 8) as u8;
+    }
+}
+
+impl CpuBus for FlatBus {
+
+    fn read(&mut self, addr: u16) -> u8 {
+        return self.ram[addr as usize];
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.ram[addr as usize] = data;
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        return self.ram[addr as usize];
+    }
+}
@@ -0,0 +1,169 @@
+use crate::cpu::cpu_6502::Instruction;
+use crate::cpu::{AddressModes, Opcodes};
+
+// Unofficial opcodes ca65 assembles in 6502X mode, under its own names
+const CA65_UNOFFICIAL: &[&str] = &["ALR", "ANC", "ARR", "AXS", "DCP", "ISC", "JAM", "LAS", "LAX", "RLA", "RRA", "SAX", "SLO", "SRE"];
+
+/**
+ * One decoded instruction. The hints are worked out from memory and the X and Y registers
+ * at the time of decoding, so they are only exact for the instruction at PC:
+ *
+ * effective  address the instruction accesses, branch or jump target
+ * pointer    ($nn,X) the zero page pointer after adding X, ($nn),Y the address before adding Y
+ * value      byte at the effective address for instructions that read or write memory
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInstruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub unofficial: bool,
+    pub mode: AddressModes,
+    // The operand byte or word as written in the instruction
+    pub operand: u16,
+    pub effective: Option<u16>,
+    pub pointer: Option<u16>,
+    pub value: Option<u8>,
+}
+
+pub(crate) fn decode(lookup: &[Instruction], addr: u16, x: u8, y: u8, read: impl Fn(u16) -> u8) -> DecodedInstruction {
+    let opcode = read(addr);
+    let instruction = &lookup[opcode as usize];
+    let mode = instruction.addr;
+    let length = match mode {
+        AddressModes::Imp => 1,
+        AddressModes::Abs | AddressModes::Abx | AddressModes::Aby | AddressModes::Ind => 3,
+        _ => 2
+    };
+
+    let bytes: Vec<u8> = (0..length).map(|i| read(addr.wrapping_add(i))).collect();
+    let operand = match length {
+        1 => 0,
+        2 => bytes[1] as u16,
+        _ => (bytes[2] as u16) << 8 | bytes[1] as u16
+    };
+    let read_word_zp = |ptr: u8| (read(ptr.wrapping_add(1) as u16) as u16) << 8 | read(ptr as u16) as u16;
+
+    let mut pointer = None;
+    let effective = match mode {
+        AddressModes::Imp | AddressModes::Imm => None,
+        AddressModes::Zp0 | AddressModes::Abs => Some(operand),
+        AddressModes::Zpx => Some((operand + x as u16) & 0x00FF),
+        AddressModes::Zpy => Some((operand + y as u16) & 0x00FF),
+        AddressModes::Abx => Some(operand.wrapping_add(x as u16)),
+        AddressModes::Aby => Some(operand.wrapping_add(y as u16)),
+        AddressModes::Rel => Some(addr.wrapping_add(2).wrapping_add(bytes[1] as i8 as u16)),
+        AddressModes::Ind => {
+            // JMP ($xxFF) takes the high byte from $xx00
+            let hi = (operand & 0xFF00) | (operand.wrapping_add(1) & 0x00FF);
+            Some((read(hi) as u16) << 8 | read(operand) as u16)
+        }
+        AddressModes::Izx => {
+            let ptr = (operand as u8).wrapping_add(x);
+            pointer = Some(ptr as u16);
+            Some(read_word_zp(ptr))
+        }
+        AddressModes::Izy => {
+            let base = read_word_zp(operand as u8);
+            pointer = Some(base);
+            Some(base.wrapping_add(y as u16))
+        }
+    };
+
+    let accesses_memory = match instruction.operate {
+        Opcodes::Jmp | Opcodes::Jsr => false,
+        _ => !matches!(mode, AddressModes::Imp | AddressModes::Imm | AddressModes::Rel | AddressModes::Ind)
+    };
+
+    return DecodedInstruction {
+        address: addr,
+        mnemonic: instruction.name.trim_start_matches('*').to_uppercase(),
+        unofficial: instruction.name.starts_with('*'),
+        mode,
+        operand,
+        value: if accesses_memory { effective.map(|address| read(address)) } else { None },
+        effective,
+        pointer,
+        bytes
+    };
+}
+
+impl DecodedInstruction {
+
+    pub fn len(&self) -> u16 {
+        return self.bytes.len() as u16;
+    }
+
+    fn is_accumulator(&self) -> bool {
+        return self.mode == AddressModes::Imp && matches!(self.mnemonic.as_str(), "ASL" | "LSR" | "ROL" | "ROR");
+    }
+
+    /**
+     * The instruction the way nestest.log prints it, without the register columns:
+     *
+     * C72A  B1 89    LDA ($89),Y = 0300 @ 0300 = 89
+     * C6BD  04 A9   *NOP $A9 = 00
+     */
+    pub fn to_nestest(&self) -> String {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let effective = self.effective.unwrap_or(0);
+        let value = self.value.map(|value| format!(" = {:02X}", value)).unwrap_or_default();
+
+        let operand = match self.mode {
+            AddressModes::Imp if self.is_accumulator() => "A".to_string(),
+            AddressModes::Imp => String::new(),
+            AddressModes::Imm => format!("#${:02X}", self.operand),
+            AddressModes::Zp0 => format!("${:02X}{}", self.operand, value),
+            AddressModes::Zpx => format!("${:02X},X @ {:02X}{}", self.operand, effective, value),
+            AddressModes::Zpy => format!("${:02X},Y @ {:02X}{}", self.operand, effective, value),
+            AddressModes::Abs => format!("${:04X}{}", self.operand, value),
+            AddressModes::Abx => format!("${:04X},X @ {:04X}{}", self.operand, effective, value),
+            AddressModes::Aby => format!("${:04X},Y @ {:04X}{}", self.operand, effective, value),
+            AddressModes::Ind => format!("(${:04X}) = {:04X}", self.operand, effective),
+            AddressModes::Izx => format!("(${:02X},X) @ {:02X} = {:04X}{}", self.operand, self.pointer.unwrap_or(0), effective, value),
+            AddressModes::Izy => format!("(${:02X}),Y = {:04X} @ {:04X}{}", self.operand, self.pointer.unwrap_or(0), effective, value),
+            AddressModes::Rel => format!("${:04X}", effective)
+        };
+
+        let text = format!("{:04X}  {:<8} {}{}", self.address, bytes.join(" "), if self.unofficial { "*" } else { " " }, self.mnemonic);
+        return if operand.is_empty() { text } else { format!("{} {}", text, operand) };
+    }
+
+    /**
+     * The instruction as ca65 source. Absolute operands below $100 get the a: prefix so
+     * they are not shortened to zero page, and unofficial opcodes ca65 has no exact
+     * encoding for are written out as .byte with the instruction in a comment.
+     */
+    pub fn to_ca65(&self) -> String {
+        let mnemonic = if self.mnemonic == "ISB" { "ISC" } else { self.mnemonic.as_str() };
+        let force_abs = if self.operand < 0x0100 { "a:" } else { "" };
+
+        let operand = match self.mode {
+            AddressModes::Imp if self.is_accumulator() => "a".to_string(),
+            AddressModes::Imp => String::new(),
+            AddressModes::Imm => format!("#${:02X}", self.operand),
+            AddressModes::Zp0 => format!("${:02X}", self.operand),
+            AddressModes::Zpx => format!("${:02X},x", self.operand),
+            AddressModes::Zpy => format!("${:02X},y", self.operand),
+            AddressModes::Abs => format!("{}${:04X}", force_abs, self.operand),
+            AddressModes::Abx => format!("{}${:04X},x", force_abs, self.operand),
+            AddressModes::Aby => format!("{}${:04X},y", force_abs, self.operand),
+            AddressModes::Ind => format!("(${:04X})", self.operand),
+            AddressModes::Izx => format!("(${:02X},x)", self.operand),
+            AddressModes::Izy => format!("(${:02X}),y", self.operand),
+            AddressModes::Rel => format!("${:04X}", self.effective.unwrap_or(0))
+        };
+
+        let text = if operand.is_empty() {
+            mnemonic.to_lowercase()
+        } else {
+            format!("{} {}", mnemonic.to_lowercase(), operand)
+        };
+
+        if self.unofficial && !CA65_UNOFFICIAL.contains(&mnemonic) {
+            let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("${:02X}", byte)).collect();
+            return format!(".byte {} ; {}", bytes.join(", "), text);
+        }
+        return text;
+    }
+}
@@ -1,9 +1,12 @@
 pub(crate) mod cpu_6502;
+pub(crate) mod cpu_bus;
+pub(crate) mod disassembler;
+#[cfg(test)]
 mod tests;
-mod instructions;
+pub(crate) mod instructions;
 
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum Flags
+pub enum Flags
 {
     // Carry Bit
     C = (1 << 0),
@@ -11,7 +14,7 @@ pub(crate) enum Flags
     Z = (1 << 1),
     // Disable Interrupts
     I = (1 << 2),
-    // Decimal Mode (unused in this implementation)
+    // Decimal Mode (only honoured by CpuVariant::Nmos6502)
     D = (1 << 3),
     // Break
     B = (1 << 4),
@@ -23,6 +26,38 @@ pub(crate) enum Flags
     N = (1 << 7),
 }
 
+/**
+ * Which chip the core behaves as. The 2A03 in the NES has the decimal mode circuitry cut
+ * out, so the D flag can be set but ADC and SBC stay binary. A stock NMOS 6502 does BCD
+ * arithmetic while D is set, which generic 6502 programs may rely on.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CpuVariant {
+    Ricoh2A03,
+    Nmos6502,
+}
+
+/**
+ * Why a run function handed control back. Everything but FrameComplete and
+ * InstructionComplete means the program stopped before the caller's goal was reached.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // PC reached the end of the code loaded with State::load
+    ProgramEnd,
+    // The next instruction starts at a breakpoint
+    Breakpoint(u16),
+    // The last cycle read or wrote a watched address
+    Watchpoint(u16),
+    // A JAM opcode at this address locked up the CPU until the next reset
+    Jam(u16),
+    // There is nothing to run, no cartridge and no code loaded
+    InvalidState,
+    FrameComplete,
+    InstructionComplete,
+    CycleBudgetExhausted,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Opcodes {
     Brk,
@@ -50,7 +85,6 @@ pub(crate) enum Opcodes {
     Lax,
     Cmp,
     Sbc,
-    Xxx,
     Ldx,
     Bit,
     Sty,
@@ -89,11 +123,23 @@ pub(crate) enum Opcodes {
     Tax,
     Tsx,
     Dex,
-    Jmp
+    Jmp,
+    Jam,
+    Anc,
+    Alr,
+    Arr,
+    Xaa,
+    Lxa,
+    Axs,
+    Sha,
+    Tas,
+    Shy,
+    Shx,
+    Las
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) enum AddressModes {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddressModes {
     Imp,
     // Immediate
     Imm,
@@ -115,22 +161,22 @@ pub(crate) enum AddressModes {
 
 use crate::bus::clock;
 use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::cpu_bus::CpuBus;
 use crate::ppu::Ppu;
-use crate::state::State;
 
 
-pub(crate) fn cpu_loop(cpu: &mut Cpu) {
+pub(crate) fn cpu_loop<B: CpuBus>(cpu: &mut Cpu<B>) -> StopReason {
     loop {
-        if let Err(_) = cpu.clock() {
-            break
+        if let Err(reason) = cpu.clock() {
+            return reason;
         }
     }
 }
 
-pub(crate) fn real_loop(ppu: &mut Ppu, cpu: &mut Cpu) {
+pub(crate) fn real_loop(ppu: &mut Ppu, cpu: &mut Cpu) -> StopReason {
     loop {
-        if let Err(_) = clock(ppu, cpu) {
-            break
+        if let Err(reason) = clock(ppu, cpu) {
+            return reason;
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+use crate::asm::assemble;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::cpu_bus::{CpuBus, FlatBus};
+use crate::cpu::Flags;
+
+// Runs instruction by instruction until the program jumps to itself
+fn run_to_trap<B: CpuBus>(cpu: &mut Cpu<B>, max_cycles: usize) -> u16 {
+    let mut last_pc = None;
+    while (cpu.clock_count() as usize) < max_cycles {
+        let _ = cpu.step();
+        if last_pc == Some(cpu.pc) {
+            return cpu.pc;
+        }
+        last_pc = Some(cpu.pc);
+    }
+    panic!("No trap within {} cycles, PC at {:04X}", max_cycles, cpu.pc);
+}
+
+#[test]
+fn test_flat_bus_runs_plain_6502_code() {
+    let program = assemble("
+        .org $8000
+        ldx #10
+        lda #0
+        clc
+    loop:
+        adc #3
+        dex
+        bne loop
+        sta $FF00
+    done:
+        jmp done
+    ").unwrap();
+    let mut bus = FlatBus::new();
+    bus.load(&program.bytes, program.origin);
+    let mut cpu = Cpu::with_bus(bus);
+    cpu.reset();
+
+    assert_eq!(run_to_trap(&mut cpu, 1000), program.labels["done"]);
+    assert_eq!(cpu.bus().ram[0xFF00], 30);
+    assert_eq!((cpu.a(), cpu.x()), (30, 0));
+    assert_eq!(cpu.status() & Flags::Z as u8, Flags::Z as u8);
+}
+
+#[test]
+fn test_registers_set_from_outside() {
+    let program = assemble("
+        .org $0200
+        pha
+        txa
+        iny
+    done:
+        jmp done
+    ").unwrap();
+    let mut bus = FlatBus::new();
+    bus.load(&program.bytes, program.origin);
+    let mut cpu = Cpu::with_bus(bus);
+    cpu.reset();
+
+    cpu.set_a(0x42);
+    cpu.set_x(0x80);
+    cpu.set_y(0xFF);
+    cpu.set_stkp(0xF0);
+    cpu.set_status(Flags::U as u8);
+    assert_eq!(run_to_trap(&mut cpu, 100), program.labels["done"]);
+
+    assert_eq!(cpu.bus().ram[0x01F0], 0x42);
+    assert_eq!(cpu.stkp(), 0xEF);
+    assert_eq!((cpu.a(), cpu.x(), cpu.y()), (0x80, 0x80, 0x00));
+    assert_eq!(cpu.status(), Flags::U as u8 | Flags::Z as u8);
+}
+
+// A made up machine, a character port at $F001 and a timer that raises IRQ every 100 cycles
+struct PortBus {
+    ram: Vec<u8>,
+    output: String,
+    cycles: usize,
+    reads: usize,
+}
+
+impl CpuBus for PortBus {
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.reads += 1;
+        if addr == 0xF002 {
+            // Reading the timer acknowledges it
+            self.cycles = 0;
+        }
+        return self.ram[addr as usize];
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xF001 => self.output.push(data as char),
+            _ => self.ram[addr as usize] = data
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        return self.ram[addr as usize];
+    }
+
+    fn tick(&mut self) -> bool {
+        self.cycles += 1;
+        return self.cycles >= 100;
+    }
+}
+
+#[test]
+fn test_user_defined_bus_with_port_and_timer_irq() {
+    let program = assemble("
+        .org $C000
+    reset:
+        ldx #0
+        cli
+    wait:
+        cpx #3
+        bne wait
+    done:
+        jmp done
+    irq:
+        lda $F002
+        lda #'*'
+        sta $F001
+        inx
+        rti
+        .org $FFFC
+        .word reset, irq
+    ").unwrap();
+    let mut ram = vec![0; 64 * 1024];
+    ram[0xC000..0xC000 + program.bytes.len()].copy_from_slice(&program.bytes);
+    let mut cpu = Cpu::with_bus(PortBus { ram, output: String::new(), cycles: 0, reads: 0 });
+    cpu.reset();
+
+    assert_eq!(run_to_trap(&mut cpu, 2000), program.labels["done"]);
+    assert_eq!(cpu.bus().output, "***");
+
+    // Decoding goes through peek, the bus sees no reads
+    let reads = cpu.bus().reads;
+    assert_eq!(cpu.decode(program.labels["irq"]).to_ca65(), "lda $F002");
+    assert_eq!(cpu.bus().reads, reads);
+}
@@ -0,0 +1,118 @@
+
+use crate::cartridge::Cartridge;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+
+// Clocks the CPU through a number of whole instructions
+fn run_instructions(cpu: &mut Cpu, count: usize) {
+    for _ in 0..count {
+        let _ = cpu.clock();
+        while cpu.cycles > 0 {
+            let _ = cpu.clock();
+        }
+    }
+}
+
+// Runs the setup instructions, then counts the cycles of the instruction after them
+fn cycles_of(code: Vec<u8>, offset: u16, setup: usize) -> u32 {
+    create_devices!(_ppu, cpu);
+    cpu.get_state_mut().load(code, offset);
+    cpu.reset();
+    cpu.cycles = 0;
+    run_instructions(&mut cpu, setup);
+
+    let mut cycles = 1;
+    let _ = cpu.clock();
+    while cpu.cycles > 0 {
+        let _ = cpu.clock();
+        cycles += 1;
+    }
+    cycles
+}
+
+#[test]
+fn test_indexed_reads_pay_for_page_crossing() {
+    // ldx #$00, lda $0280,x
+    assert_eq!(cycles_of(vec![0xA2, 0x00, 0xBD, 0x80, 0x02], 0, 1), 4);
+    // ldx #$FF, lda $0280,x
+    assert_eq!(cycles_of(vec![0xA2, 0xFF, 0xBD, 0x80, 0x02], 0, 1), 5);
+    // ldy #$FF, lda ($10),y with $10 pointing at $0280
+    assert_eq!(cycles_of(vec![0xA0, 0xFF, 0xA9, 0x80, 0x85, 0x10, 0xA9, 0x02, 0x85, 0x11, 0xB1, 0x10], 0, 5), 6);
+}
+
+#[test]
+fn test_stores_and_read_modify_write_take_fixed_cycles() {
+    // ldx #$00, sta $0280,x
+    assert_eq!(cycles_of(vec![0xA2, 0x00, 0x9D, 0x80, 0x02], 0, 1), 5);
+    // ldx #$00, inc $0280,x
+    assert_eq!(cycles_of(vec![0xA2, 0x00, 0xFE, 0x80, 0x02], 0, 1), 7);
+    // inc $0280
+    assert_eq!(cycles_of(vec![0xEE, 0x80, 0x02], 0, 0), 6);
+}
+
+#[test]
+fn test_branch_cycles() {
+    // ldx #$00, bne +0 falls through
+    assert_eq!(cycles_of(vec![0xA2, 0x00, 0xD0, 0x00], 0, 1), 2);
+    // ldx #$01, bne +0
+    assert_eq!(cycles_of(vec![0xA2, 0x01, 0xD0, 0x00], 0, 1), 3);
+    // From $00FA, bne +$10 lands on the next page
+    assert_eq!(cycles_of(vec![0xA2, 0x01, 0xD0, 0x10], 0x00FA, 1), 4);
+}
+
+#[test]
+fn test_stack_instruction_cycles() {
+    // pha
+    assert_eq!(cycles_of(vec![0x48], 0, 0), 3);
+    // pla
+    assert_eq!(cycles_of(vec![0x68], 0, 0), 4);
+    // jsr $0010
+    assert_eq!(cycles_of(vec![0x20, 0x10, 0x00], 0, 0), 6);
+    // brk
+    assert_eq!(cycles_of(vec![0x00], 0, 0), 7);
+}
+
+#[test]
+fn test_read_modify_write_stores_on_last_cycle() {
+    create_devices!(_ppu, cpu);
+    // lda #$05, sta $0280, inc $0280
+    cpu.get_state_mut().load(vec![0xA9, 0x05, 0x8D, 0x80, 0x02, 0xEE, 0x80, 0x02], 0);
+    run_instructions(&mut cpu, 2);
+
+    for _ in 0..5 {
+        let _ = cpu.clock();
+        assert_eq!(cpu.get_state().cpu_ram[0x0280], 0x05);
+    }
+    let _ = cpu.clock();
+    assert_eq!(cpu.cycles, 0);
+    assert_eq!(cpu.get_state().cpu_ram[0x0280], 0x06);
+}
+
+#[test]
+fn test_page_crossing_dummy_read_hits_the_unfixed_address() {
+    // NROM with the program at $C000: ldx #$FF, lda $4016,x. The dummy read lands on
+    // $4015 before the high byte is fixed up, which acknowledges the frame IRQ
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut prg = vec![0xEA; 0x4000];
+    prg[0..5].copy_from_slice(&[0xA2, 0xFF, 0xBD, 0x16, 0x40]);
+    prg[0x3FFC] = 0x00;
+    prg[0x3FFD] = 0xC0;
+    rom.extend(prg);
+    rom.extend(vec![0; 0x2000]);
+
+    create_devices!(_ppu, cpu);
+    cpu.get_state_mut().connect_cartridge(Some(Cartridge::from_bytes(&rom)));
+    cpu.get_state_mut().code_end = usize::MAX;
+    cpu.reset();
+    cpu.cycles = 0;
+
+    // The frame counter raises its IRQ at the end of the four step sequence
+    for _ in 0..30000 {
+        cpu.get_state_mut().apu.clock(0.0);
+    }
+    assert!(cpu.get_state().apu.irq_state());
+
+    run_instructions(&mut cpu, 2);
+    assert_eq!(cpu.pc, 0xC005);
+    assert!(!cpu.get_state().apu.irq_state());
+}
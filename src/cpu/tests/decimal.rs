@@ -0,0 +1,76 @@
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::real_loop;
+use crate::cpu::tests::create_devices;
+use crate::cpu::{CpuVariant, Flags};
+use crate::cpu::Flags::{C, N, V, Z};
+
+fn run_code(variant: CpuVariant, code: Vec<u8>, check: impl Fn(&mut Cpu)) {
+    create_devices!(ppu, cpu);
+    cpu.set_variant(variant);
+    cpu.get_state_mut().load(code, 0);
+    real_loop(&mut ppu, &mut cpu);
+    check(&mut cpu);
+}
+
+fn flag(cpu: &Cpu, f: Flags) -> bool {
+    cpu.status & f as u8 > 0
+}
+
+#[test]
+fn test_2a03_ignores_decimal_flag() {
+    // sed, clc, lda #$58, adc #$46
+    run_code(CpuVariant::Ricoh2A03, vec![0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46], |cpu| {
+        assert_eq!(cpu.a, 0x9E);
+        assert!(!flag(cpu, C));
+    });
+}
+
+#[test]
+fn test_decimal_adc_carries_into_next_digit() {
+    // sed, clc, lda #$58, adc #$46
+    run_code(CpuVariant::Nmos6502, vec![0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46], |cpu| {
+        assert_eq!(cpu.a, 0x04);
+        assert!(flag(cpu, C));
+    });
+}
+
+#[test]
+fn test_decimal_adc_flags_from_intermediate_result() {
+    // sed, clc, lda #$99, adc #$01
+    run_code(CpuVariant::Nmos6502, vec![0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01], |cpu| {
+        assert_eq!(cpu.a, 0x00);
+        assert!(flag(cpu, C));
+        // Z follows the binary sum $9A, N the value before the high digit was corrected
+        assert!(!flag(cpu, Z));
+        assert!(flag(cpu, N));
+        assert!(!flag(cpu, V));
+    });
+}
+
+#[test]
+fn test_decimal_sbc() {
+    // sed, sec, lda #$46, sbc #$12
+    run_code(CpuVariant::Nmos6502, vec![0xF8, 0x38, 0xA9, 0x46, 0xE9, 0x12], |cpu| {
+        assert_eq!(cpu.a, 0x34);
+        assert!(flag(cpu, C));
+    });
+}
+
+#[test]
+fn test_decimal_sbc_borrow() {
+    // sed, sec, lda #$12, sbc #$21
+    run_code(CpuVariant::Nmos6502, vec![0xF8, 0x38, 0xA9, 0x12, 0xE9, 0x21], |cpu| {
+        assert_eq!(cpu.a, 0x91);
+        assert!(!flag(cpu, C));
+        assert!(flag(cpu, N));
+    });
+}
+
+#[test]
+fn test_decimal_isb() {
+    // sed, lda #$04, sta $20, lda #$20, sec, isb $20
+    run_code(CpuVariant::Nmos6502, vec![0xF8, 0xA9, 0x04, 0x85, 0x20, 0xA9, 0x20, 0x38, 0xE7, 0x20], |cpu| {
+        assert_eq!(cpu.a, 0x15);
+        assert!(flag(cpu, C));
+    });
+}
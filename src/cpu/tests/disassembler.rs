@@ -0,0 +1,80 @@
+use crate::cpu::tests::create_devices;
+use crate::cpu::AddressModes;
+
+#[test]
+fn test_decode_keeps_absolute_operands() {
+    create_devices!(_ppu, cpu);
+    // jmp $C5F5, lda $0300,x
+    cpu.get_state_mut().load(vec![0x4C, 0xF5, 0xC5, 0xBD, 0x00, 0x03], 0x0600);
+    cpu.x = 0x05;
+
+    let jmp = cpu.decode(0x0600);
+    assert_eq!(jmp.bytes, vec![0x4C, 0xF5, 0xC5]);
+    assert_eq!(jmp.mnemonic, "JMP");
+    assert_eq!(jmp.mode, AddressModes::Abs);
+    assert_eq!(jmp.operand, 0xC5F5);
+    assert_eq!(jmp.value, None);
+    assert_eq!(jmp.to_nestest(), "0600  4C F5 C5  JMP $C5F5");
+
+    let lda = cpu.decode(0x0603);
+    assert_eq!(lda.effective, Some(0x0305));
+    assert_eq!(lda.to_nestest(), "0603  BD 00 03  LDA $0300,X @ 0305 = 00");
+    assert_eq!(lda.to_ca65(), "lda $0300,x");
+}
+
+#[test]
+fn test_indirect_hints() {
+    create_devices!(_ppu, cpu);
+    // lda ($80,x), lda ($89),y, jmp ($02FF)
+    cpu.get_state_mut().load(vec![0xA1, 0x80, 0xB1, 0x89, 0x6C, 0xFF, 0x02], 0x0600);
+    {
+        let mut state = cpu.get_state_mut();
+        state.cpu_ram[0x0082] = 0x00;
+        state.cpu_ram[0x0083] = 0x02;
+        state.cpu_ram[0x0200] = 0x5A;
+        state.cpu_ram[0x0089] = 0x00;
+        state.cpu_ram[0x008A] = 0x03;
+        state.cpu_ram[0x0304] = 0x89;
+        state.cpu_ram[0x02FF] = 0x7E;
+        state.cpu_ram[0x0300] = 0xDB;
+    }
+    cpu.x = 0x02;
+    cpu.y = 0x04;
+
+    let instructions = cpu.disassemble_range(0x0600, 0x0606);
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[0].to_nestest(), "0600  A1 80     LDA ($80,X) @ 82 = 0200 = 5A");
+    assert_eq!(instructions[1].to_nestest(), "0602  B1 89     LDA ($89),Y = 0300 @ 0304 = 89");
+    // The pointer's high byte comes from $0200, not $0300
+    assert_eq!(instructions[2].to_nestest(), "0604  6C FF 02  JMP ($02FF) = 5A7E");
+    assert_eq!(instructions[2].to_ca65(), "jmp ($02FF)");
+}
+
+#[test]
+fn test_branch_accumulator_and_unofficial_formatting() {
+    create_devices!(_ppu, cpu);
+    // bcs -4, lsr a, *nop $A9, *isb $10, brk
+    cpu.get_state_mut().load(vec![0xB0, 0xFC, 0x4A, 0x04, 0xA9, 0xE7, 0x10, 0x00], 0x0600);
+
+    let instructions = cpu.disassemble_range(0x0600, 0x0607);
+    assert_eq!(instructions[0].effective, Some(0x05FE));
+    assert_eq!(instructions[0].to_nestest(), "0600  B0 FC     BCS $05FE");
+    assert_eq!(instructions[0].to_ca65(), "bcs $05FE");
+    assert_eq!(instructions[1].to_nestest(), "0602  4A        LSR A");
+    assert_eq!(instructions[1].to_ca65(), "lsr a");
+    assert!(instructions[2].unofficial);
+    assert_eq!(instructions[2].to_nestest(), "0603  04 A9    *NOP $A9 = 00");
+    assert_eq!(instructions[2].to_ca65(), ".byte $04, $A9 ; nop $A9");
+    assert_eq!(instructions[3].to_nestest(), "0605  E7 10    *ISB $10 = 00");
+    assert_eq!(instructions[3].to_ca65(), "isc $10");
+    assert_eq!(instructions[4].len(), 1);
+    assert_eq!(instructions[4].to_ca65(), "brk");
+}
+
+#[test]
+fn test_ca65_keeps_absolute_addressing_for_zero_page_operands() {
+    create_devices!(_ppu, cpu);
+    // lda $0010
+    cpu.get_state_mut().load(vec![0xAD, 0x10, 0x00], 0x0600);
+    assert_eq!(cpu.decode(0x0600).to_ca65(), "lda a:$0010");
+}
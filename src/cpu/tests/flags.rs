@@ -1,7 +1,6 @@
 
 use crate::cpu::Flags::{I, B, C, Z, V, N, D};
 use crate::cpu::tests::create_devices;
-use crate::ppu::Ppu;
 
 
 
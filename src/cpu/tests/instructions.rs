@@ -1,4 +1,4 @@
-use crate::{advance, create_system};
+use crate::Nes;
 
 #[test]
 fn test_wrapping_inc() {
@@ -11,18 +11,12 @@ stx $00
 inc $00
     */
     let code = vec![0xA2, 0xff, 0x86, 0x00, 0xE6, 0x00];
-    let (_bus_ref, cpu_ref, _ppu_ref, state_ref) = create_system();
+    let mut nes = Nes::new();
+    nes.load(code, 0);
 
-    {
-        let mut state = state_ref.as_ref().borrow_mut();
-        state.load(code, 0);
-    }
-
-    let mut cpu = cpu_ref.as_ref().borrow_mut();
-    let mut ppu = _ppu_ref.as_ref().borrow_mut();
-    let _ = advance(&mut ppu, &mut cpu);
-    let _ = advance(&mut ppu, &mut cpu);
-    let _ = advance(&mut ppu, &mut cpu);
+    let _ = nes.step();
+    let _ = nes.step();
+    let _ = nes.step();
 
     assert_eq!(1, 1);
 }
\ No newline at end of file
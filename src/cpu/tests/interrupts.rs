@@ -0,0 +1,143 @@
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+use crate::cpu::Flags::{B, I, U};
+
+const IRQ_HANDLER: u16 = 0x0300;
+const NMI_HANDLER: u16 = 0x0400;
+
+// Loads the program at $0000 with NOPs at both handlers
+fn setup(cpu: &mut Cpu, code: Vec<u8>, status: u8) {
+    {
+        let mut state = cpu.get_state_mut();
+        state.load(code, 0);
+        state.cpu_ram[0xFFFA] = (NMI_HANDLER & 0x00FF) as u8;
+        state.cpu_ram[0xFFFB] = (NMI_HANDLER >> 8) as u8;
+        state.cpu_ram[0xFFFE] = (IRQ_HANDLER & 0x00FF) as u8;
+        state.cpu_ram[0xFFFF] = (IRQ_HANDLER >> 8) as u8;
+        for i in 0..0x10 {
+            state.cpu_ram[(IRQ_HANDLER + i) as usize] = 0xEA;
+            state.cpu_ram[(NMI_HANDLER + i) as usize] = 0xEA;
+        }
+    }
+    cpu.reset();
+    cpu.cycles = 0;
+    cpu.status = status | U as u8;
+}
+
+// Runs one instruction, or one interrupt sequence
+fn step(cpu: &mut Cpu) {
+    let _ = cpu.clock();
+    while cpu.cycles > 0 {
+        let _ = cpu.clock();
+    }
+}
+
+fn stack(cpu: &Cpu, offset: u8) -> u8 {
+    cpu.get_state().cpu_ram[0x0100 + cpu.stkp.wrapping_add(offset) as usize]
+}
+
+#[test]
+fn test_irq_pushes_return_address_and_status_without_b() {
+    create_devices!(_ppu, cpu);
+    setup(&mut cpu, vec![0xEA, 0xEA, 0xEA], 0);
+    cpu.set_irq_line(true);
+
+    // The first NOP polls before the CPU has seen the line
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, 0x0002);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, IRQ_HANDLER);
+    assert_eq!(stack(&cpu, 1) & (B as u8 | U as u8), U as u8);
+    assert_eq!(stack(&cpu, 2), 0x02);
+    assert_eq!(stack(&cpu, 3), 0x00);
+    assert!(cpu.status & I as u8 > 0);
+}
+
+#[test]
+fn test_cli_delays_irq_by_one_instruction() {
+    create_devices!(_ppu, cpu);
+    // nop, cli, nop, nop
+    setup(&mut cpu, vec![0xEA, 0x58, 0xEA, 0xEA], I as u8);
+    cpu.set_irq_line(true);
+
+    step(&mut cpu);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, 0x0003);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, IRQ_HANDLER);
+    assert_eq!(stack(&cpu, 2), 0x03);
+}
+
+#[test]
+fn test_sei_still_takes_pending_irq() {
+    create_devices!(_ppu, cpu);
+    // nop, sei, nop
+    setup(&mut cpu, vec![0xEA, 0x78, 0xEA], 0);
+    cpu.set_irq_line(true);
+
+    step(&mut cpu);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, IRQ_HANDLER);
+    // The pushed status already has I set
+    assert!(stack(&cpu, 1) & I as u8 > 0);
+}
+
+#[test]
+fn test_taken_branch_on_same_page_delays_irq() {
+    create_devices!(_ppu, cpu);
+    // bne +0, nop, nop
+    setup(&mut cpu, vec![0xD0, 0x00, 0xEA, 0xEA], 0);
+    cpu.set_irq_line(true);
+
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, 0x0003);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, IRQ_HANDLER);
+
+    create_devices!(_ppu2, cpu2);
+    // lda $10, nop, nop takes the same three cycles but polls on its second
+    setup(&mut cpu2, vec![0xA5, 0x10, 0xEA, 0xEA], 0);
+    cpu2.set_irq_line(true);
+
+    step(&mut cpu2);
+    step(&mut cpu2);
+    assert_eq!(cpu2.pc, IRQ_HANDLER);
+}
+
+#[test]
+fn test_nmi_is_edge_triggered() {
+    create_devices!(_ppu, cpu);
+    setup(&mut cpu, vec![0xEA, 0xEA, 0xEA], I as u8);
+    cpu.set_nmi_line(true);
+
+    step(&mut cpu);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, NMI_HANDLER);
+
+    // Holding the line does not fire again
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.pc, NMI_HANDLER + 2);
+}
+
+#[test]
+fn test_nmi_hijacks_brk() {
+    create_devices!(_ppu, cpu);
+    setup(&mut cpu, vec![0x00, 0x00, 0xEA], I as u8);
+    cpu.set_nmi_line(true);
+
+    step(&mut cpu);
+    assert_eq!(cpu.pc, NMI_HANDLER);
+    // BRK still pushes B and skips its padding byte
+    assert!(stack(&cpu, 1) & B as u8 > 0);
+    assert_eq!(stack(&cpu, 2), 0x02);
+
+    // The NMI was used up by BRK
+    step(&mut cpu);
+    assert_eq!(cpu.pc, NMI_HANDLER + 1);
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use crate::asm::assemble;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+use crate::cpu::CpuVariant;
+
+// Directory holding the binaries built from Klaus Dormann's 6502_65C02_functional_tests
+const TESTS_DIR_VAR: &str = "KLAUS_TESTS";
+const TESTS_DIR: &str = "assets/klaus";
+
+// The functional test takes a little under 100 million cycles
+const MAX_INSTRUCTIONS: usize = 50_000_000;
+
+/**
+ * Where a test binary goes and how it tells a pass from a fail:
+ *
+ * success    trap address reached once every test passed
+ * test_case  byte holding the number of the test that was running
+ * error      byte that is left at zero when nothing failed, for tests that always end in the same trap
+ */
+struct Suite {
+    file: &'static str,
+    origin: u16,
+    start: u16,
+    success: Option<u16>,
+    test_case: u16,
+    error: Option<u16>,
+}
+
+// The listings of the default builds give these addresses, other builds need their own
+const FUNCTIONAL_TEST: Suite = Suite {
+    file: "6502_functional_test.bin",
+    origin: 0x0000,
+    start: 0x0400,
+    success: Some(0x3469),
+    test_case: 0x0200,
+    error: None,
+};
+
+const DECIMAL_TEST: Suite = Suite {
+    file: "6502_decimal_test.bin",
+    origin: 0x0200,
+    start: 0x0200,
+    success: None,
+    test_case: 0x0000,
+    error: Some(0x000B),
+};
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Passed,
+    // Trapped somewhere else, with the address and the test number
+    Failed(u16, u8),
+    // Never settled into a trap
+    TimedOut(u16),
+}
+
+/**
+ * Runs whole instructions until one jumps or branches to itself. The code_end sentinel only
+ * knows where the loaded code ends, these tests finish (and fail) in loops in the middle of it.
+ */
+fn run_until_trap(cpu: &mut Cpu, max_instructions: usize) -> Option<u16> {
+    for _ in 0..max_instructions {
+        let pc = cpu.pc;
+        let _ = cpu.clock();
+        while cpu.cycles > 0 {
+            let _ = cpu.clock();
+        }
+        if cpu.pc == pc || cpu.is_halted() {
+            return Some(pc);
+        }
+    }
+    return None;
+}
+
+fn run_suite(cpu: &mut Cpu, suite: &Suite, code: Vec<u8>, max_instructions: usize) -> Outcome {
+    cpu.get_state_mut().load(code, suite.origin);
+    cpu.set_variant(CpuVariant::Nmos6502);
+    cpu.reset();
+    while cpu.cycles > 0 {
+        let _ = cpu.clock();
+    }
+    cpu.pc = suite.start;
+
+    let trap = match run_until_trap(cpu, max_instructions) {
+        Some(trap) => trap,
+        None => return Outcome::TimedOut(cpu.pc)
+    };
+    let state = cpu.get_state();
+    let failed = match (suite.success, suite.error) {
+        (Some(success), _) => trap != success,
+        (None, Some(error)) => state.cpu_ram[error as usize] != 0x00,
+        (None, None) => false
+    };
+    return if failed {
+        Outcome::Failed(trap, state.cpu_ram[suite.test_case as usize])
+    } else {
+        Outcome::Passed
+    };
+}
+
+fn run_binary(suite: &Suite) {
+    let dir = std::env::var(TESTS_DIR_VAR).unwrap_or_else(|_| TESTS_DIR.to_string());
+    let path = Path::new(&dir).join(suite.file);
+    let code = std::fs::read(&path)
+        .unwrap_or_else(|err| panic!("Could not read {}: {}, set {} to the built binaries", path.display(), err, TESTS_DIR_VAR));
+
+    create_devices!(_ppu, cpu);
+    let outcome = run_suite(&mut cpu, suite, code, MAX_INSTRUCTIONS);
+    assert_eq!(outcome, Outcome::Passed, "{} trapped at the wrong place, see the listing for the test number", suite.file);
+}
+
+// The binaries are not bundled, assemble them into assets/klaus (or point KLAUS_TESTS at
+// them) and run with --ignored
+#[test]
+#[ignore = "needs the functional test binary in assets/klaus"]
+fn test_functional_test_binary() {
+    run_binary(&FUNCTIONAL_TEST);
+}
+
+#[test]
+#[ignore = "needs the decimal test binary in assets/klaus"]
+fn test_decimal_test_binary() {
+    run_binary(&DECIMAL_TEST);
+}
+
+#[test]
+fn test_trap_reports_the_failing_test_number() {
+    let program = assemble("
+        .org $0400
+        test_case = $0200
+        start:
+            lda #1
+            sta test_case
+            lda #$40
+            cmp #$40
+            bne *
+            inc test_case
+            clc
+            lda #$7F
+            adc #1
+            bvc *
+            inc test_case
+            sec
+            bcs *
+        success:
+            jmp success
+    ").unwrap();
+    let success = program.label("success").unwrap();
+    let suite = Suite { origin: program.origin, start: program.origin, success: Some(success), ..FUNCTIONAL_TEST };
+
+    create_devices!(_ppu, cpu);
+    // The last test branches to itself instead of reaching success
+    assert_eq!(run_suite(&mut cpu, &suite, program.bytes.clone(), 1000), Outcome::Failed(success - 2, 3));
+
+    let mut passing = program.bytes.clone();
+    passing[(success - 2 - program.origin) as usize] = 0x90;
+    create_devices!(_ppu, cpu);
+    assert_eq!(run_suite(&mut cpu, &suite, passing, 1000), Outcome::Passed);
+}
+
+#[test]
+fn test_loop_without_a_trap_times_out() {
+    let program = assemble("
+        .org $0400
+        loop:
+            inx
+            jmp loop
+    ").unwrap();
+    let suite = Suite { origin: program.origin, start: program.origin, success: None, error: None, ..FUNCTIONAL_TEST };
+
+    create_devices!(_ppu, cpu);
+    assert!(matches!(run_suite(&mut cpu, &suite, program.bytes, 1000), Outcome::TimedOut(_)));
+}
@@ -0,0 +1,81 @@
+
+use crate::bus::{advance, clock, reset};
+use crate::cartridge::Cartridge;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+use crate::ppu::Ppu;
+
+// Not part of the repo, download it from the nes-test-roms collection to run the full comparison
+const REFERENCE_LOG: &str = "assets/nestest.log";
+
+// Traces nestest in automation mode from $C000, one line per instruction
+fn trace_nestest(ppu: &mut Ppu, cpu: &mut Cpu, lines: usize) -> Vec<String> {
+    cpu.get_state_mut().connect_cartridge(Some(Cartridge::new("assets/nestest.nes")));
+    reset(ppu, cpu);
+    advance(ppu, cpu);
+    cpu.pc = 0xC000;
+
+    let mut trace = vec![];
+    while trace.len() < lines && !cpu.is_halted() {
+        // The log gives the PPU position as the instruction's first cycle starts
+        while cpu.get_state().n_system_clock_counter % 3 != 0 {
+            let _ = clock(ppu, cpu);
+        }
+        trace.push(cpu.trace());
+        advance(ppu, cpu);
+    }
+    return trace;
+}
+
+// The first line that differs, with its number counted from 1
+fn first_difference(expected: &[&str], actual: &[String]) -> Option<(usize, String, String)> {
+    for (i, line) in expected.iter().enumerate() {
+        let traced = actual.get(i).map(|line| line.as_str()).unwrap_or("<end of trace>");
+        if line.trim_end() != traced {
+            return Some((i + 1, line.trim_end().to_string(), traced.to_string()));
+        }
+    }
+    return None;
+}
+
+#[test]
+fn test_trace_matches_nestest_log_format() {
+    create_devices!(ppu, cpu);
+    let trace = trace_nestest(&mut ppu, &mut cpu, 3);
+
+    assert_eq!(trace, vec![
+        "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+        "C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10",
+        "C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12",
+    ]);
+}
+
+#[test]
+fn test_trace_reads_the_ppu_position() {
+    create_devices!(ppu, cpu);
+    // The PPU moves on without the CPU, the trace follows it rather than the cycle count
+    for _ in 0..341 * 2 + 5 {
+        ppu.clock(cpu.get_state_mut());
+    }
+    assert!(cpu.trace().ends_with("PPU:  2,  5 CYC:0"), "{}", cpu.trace());
+}
+
+// The reference log is not bundled, save it as assets/nestest.log and run with --ignored
+#[test]
+#[ignore = "needs assets/nestest.log"]
+fn test_nestest_matches_reference_log() {
+    let reference = std::fs::read_to_string(REFERENCE_LOG)
+        .unwrap_or_else(|err| panic!("Could not read {}: {}, save the nestest reference log there", REFERENCE_LOG, err));
+    let expected: Vec<&str> = reference.lines().filter(|line| !line.trim().is_empty()).collect();
+
+    create_devices!(ppu, cpu);
+    let trace = trace_nestest(&mut ppu, &mut cpu, expected.len());
+
+    if let Some((line, expected_line, traced)) = first_difference(&expected, &trace) {
+        let previous = if line > 1 { trace[line - 2].as_str() } else { "" };
+        panic!("nestest diverges at line {}\n    after: {}\n expected: {}\n   traced: {}", line, previous, expected_line, traced);
+    }
+    // The official and unofficial result codes
+    assert_eq!(cpu.get_state().cpu_ram[0x0002], 0x00);
+    assert_eq!(cpu.get_state().cpu_ram[0x0003], 0x00);
+}
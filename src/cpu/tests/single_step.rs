@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+
+// Point this at the nes6502/v1 directory of the SingleStepTests ProcessorTests, 00.json to ff.json
+const TESTS_DIR_VAR: &str = "SINGLE_STEP_TESTS";
+const TESTS_DIR: &str = "assets/nes6502/v1";
+
+// The vectors keep reading the bus after a JAM, a halted Cpu stops clocking
+const JAM_OPCODES: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+// How many mismatches to print per opcode, the rest are only counted
+const REPORTED_PER_OPCODE: usize = 3;
+
+fn byte(value: &Value) -> u8 {
+    return value.as_u64().expect("Expected a number") as u8;
+}
+
+// Puts the registers and RAM of the state object on the flat 64K bus
+fn load_state(cpu: &mut Cpu, state: &Value) {
+    cpu.pc = state["pc"].as_u64().expect("Missing pc") as u16;
+    cpu.stkp = byte(&state["s"]);
+    cpu.a = byte(&state["a"]);
+    cpu.x = byte(&state["x"]);
+    cpu.y = byte(&state["y"]);
+    cpu.status = byte(&state["p"]);
+
+    let memory = cpu.get_state_mut();
+    for entry in state["ram"].as_array().expect("Missing ram") {
+        memory.cpu_ram[entry[0].as_u64().expect("Bad address") as usize] = byte(&entry[1]);
+    }
+}
+
+// Everything the final state and the cycle list disagree with
+fn check_case(cpu: &mut Cpu, case: &Value) -> Vec<String> {
+    load_state(cpu, &case["initial"]);
+    cpu.bus_log = Some(vec![]);
+    let _ = cpu.clock();
+    while cpu.cycles > 0 {
+        let _ = cpu.clock();
+    }
+    let log = cpu.bus_log.take().unwrap_or_default();
+
+    let mut errors = vec![];
+    let expected = &case["final"];
+    let registers = [
+        ("pc", expected["pc"].as_u64().unwrap_or(0) as u16, cpu.pc),
+        ("s", byte(&expected["s"]) as u16, cpu.stkp as u16),
+        ("a", byte(&expected["a"]) as u16, cpu.a as u16),
+        ("x", byte(&expected["x"]) as u16, cpu.x as u16),
+        ("y", byte(&expected["y"]) as u16, cpu.y as u16),
+        ("p", byte(&expected["p"]) as u16, cpu.status as u16),
+    ];
+    for (name, expected, actual) in registers.iter() {
+        if expected != actual {
+            errors.push(format!("{} expected {:02X} got {:02X}", name, expected, actual));
+        }
+    }
+
+    for entry in expected["ram"].as_array().expect("Missing ram") {
+        let addr = entry[0].as_u64().expect("Bad address") as usize;
+        let actual = cpu.get_state().cpu_ram[addr];
+        if actual != byte(&entry[1]) {
+            errors.push(format!("${:04X} expected {:02X} got {:02X}", addr, byte(&entry[1]), actual));
+        }
+    }
+
+    let cycles: Vec<(u16, u8, bool)> = case["cycles"].as_array().expect("Missing cycles")
+        .iter()
+        .map(|cycle| (cycle[0].as_u64().unwrap_or(0) as u16, byte(&cycle[1]), cycle[2] == "write"))
+        .collect();
+    if cycles != log {
+        let describe = |accesses: &[(u16, u8, bool)]| accesses.iter()
+            .map(|(addr, data, write)| format!("{} {:04X}={:02X}", if *write { "W" } else { "R" }, addr, data))
+            .collect::<Vec<String>>()
+            .join(", ");
+        errors.push(format!("cycles expected [{}] got [{}]", describe(&cycles), describe(&log)));
+    }
+
+    // Leave the bus clean for the next case
+    let memory = cpu.get_state_mut();
+    for entry in expected["ram"].as_array().unwrap().iter().chain(case["initial"]["ram"].as_array().unwrap()) {
+        memory.cpu_ram[entry[0].as_u64().unwrap() as usize] = 0x00;
+    }
+    return errors;
+}
+
+// The vectors are not bundled, unpack them into assets/nes6502/v1 (or point SINGLE_STEP_TESTS
+// at them) and run with --ignored
+#[test]
+#[ignore = "needs the single step vectors in assets/nes6502/v1"]
+fn test_single_step_vectors() {
+    let dir = std::env::var(TESTS_DIR_VAR).unwrap_or_else(|_| TESTS_DIR.to_string());
+    assert!(Path::new(&dir).is_dir(), "{} not found, set {} to the single step vectors", dir, TESTS_DIR_VAR);
+
+    create_devices!(_ppu, cpu);
+    cpu.get_state_mut().code_end = usize::MAX;
+    cpu.reset();
+    while cpu.cycles > 0 {
+        let _ = cpu.clock();
+    }
+
+    let mut report = vec![];
+    for opcode in 0x00..=0xFFu8 {
+        if JAM_OPCODES.contains(&opcode) {
+            continue;
+        }
+        let path = Path::new(&dir).join(format!("{:02x}.json", opcode));
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => continue
+        };
+        let cases: Value = serde_json::from_str(&text).expect("Invalid test file");
+
+        let mut failed = 0;
+        for case in cases.as_array().expect("Expected a list of cases") {
+            let errors = check_case(&mut cpu, case);
+            if !errors.is_empty() {
+                if failed < REPORTED_PER_OPCODE {
+                    report.push(format!("{:02X} {}: {}", opcode, case["name"].as_str().unwrap_or(""), errors.join("; ")));
+                }
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            report.push(format!("{:02X}: {} of {} cases failed", opcode, failed, cases.as_array().unwrap().len()));
+        }
+    }
+    assert!(report.is_empty(), "\n{}", report.join("\n"));
+}
+
+#[test]
+fn test_single_step_case_checks_registers_memory_and_cycles() {
+    create_devices!(_ppu, cpu);
+    cpu.get_state_mut().code_end = usize::MAX;
+
+    // LDA $10FF,X crossing into $1100, with the dummy read of $1000 on the way
+    let case: Value = serde_json::from_str(r#"{
+        "name": "bd ff 10",
+        "initial": {"pc": 512, "s": 253, "a": 0, "x": 1, "y": 0, "p": 36,
+                    "ram": [[512, 189], [513, 255], [514, 16], [4096, 17], [4352, 128]]},
+        "final": {"pc": 515, "s": 253, "a": 128, "x": 1, "y": 0, "p": 164,
+                  "ram": [[512, 189], [513, 255], [514, 16], [4096, 17], [4352, 128]]},
+        "cycles": [[512, 189, "read"], [513, 255, "read"], [514, 16, "read"], [4096, 17, "read"], [4352, 128, "read"]]
+    }"#).unwrap();
+    assert_eq!(check_case(&mut cpu, &case), Vec::<String>::new());
+
+    // The same case with the dummy read left out has to be reported
+    let mut wrong = case.clone();
+    wrong["cycles"].as_array_mut().unwrap().remove(3);
+    wrong["final"]["a"] = Value::from(0x7F);
+    let errors = check_case(&mut cpu, &wrong);
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].starts_with("a expected 7F got 80"));
+    assert!(errors[1].starts_with("cycles expected"));
+}
@@ -0,0 +1,95 @@
+use crate::asm::assemble;
+use crate::bus::{advance, clock, run, system_clock};
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::tests::create_devices;
+use crate::cpu::{real_loop, StopReason};
+
+fn load(cpu: &mut Cpu, source: &str) {
+    let program = assemble(source).unwrap();
+    cpu.get_state_mut().load(program.bytes, program.origin);
+    cpu.reset();
+}
+
+#[test]
+fn test_loaded_code_stops_at_its_end() {
+    create_devices!(ppu, cpu);
+    load(&mut cpu, "
+        lda #1
+        sta $10
+    ");
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::ProgramEnd);
+    assert_eq!(cpu.get_state().cpu_ram[0x10], 0x01);
+}
+
+#[test]
+fn test_jam_reports_its_address_every_time() {
+    create_devices!(ppu, cpu);
+    load(&mut cpu, "
+        nop
+        .byte $02
+        nop
+    ");
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::Jam(0x0001));
+    assert_eq!(advance(&mut ppu, &mut cpu), StopReason::Jam(0x0001));
+}
+
+#[test]
+fn test_breakpoint_stops_before_the_instruction_and_resumes() {
+    create_devices!(ppu, cpu);
+    load(&mut cpu, "
+        ldx #0
+    loop:
+        inx
+        cpx #3
+        bne loop
+    ");
+    cpu.add_breakpoint(0x0002);
+
+    for x in 0..3 {
+        assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::Breakpoint(0x0002));
+        assert_eq!(cpu.x, x);
+    }
+    cpu.remove_breakpoint(0x0002);
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::ProgramEnd);
+    assert_eq!(cpu.x, 3);
+}
+
+#[test]
+fn test_watchpoint_sees_reads_and_writes() {
+    create_devices!(ppu, cpu);
+    load(&mut cpu, "
+        lda #7
+        sta $0200
+        lda $0200
+        inc $0300
+    ");
+    cpu.add_watchpoint(0x0200);
+
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::Watchpoint(0x0200));
+    assert_eq!(cpu.get_state().cpu_ram[0x0200], 0x07);
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::Watchpoint(0x0200));
+    assert_eq!(cpu.a, 0x07);
+
+    cpu.remove_watchpoint(0x0200);
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::ProgramEnd);
+}
+
+#[test]
+fn test_run_functions_say_why_they_returned() {
+    create_devices!(ppu, cpu);
+    load(&mut cpu, "
+    loop:
+        jmp loop
+    ");
+
+    assert_eq!(advance(&mut ppu, &mut cpu), StopReason::InstructionComplete);
+    assert_eq!(run(&mut ppu, &mut cpu, 100), StopReason::CycleBudgetExhausted);
+    assert_eq!(system_clock(&mut ppu, &mut cpu), StopReason::FrameComplete);
+}
+
+#[test]
+fn test_cpu_without_state_is_invalid() {
+    create_devices!(ppu, cpu);
+    assert_eq!(clock(&mut ppu, &mut cpu), Err(StopReason::InvalidState));
+    assert_eq!(real_loop(&mut ppu, &mut cpu), StopReason::InvalidState);
+}
@@ -0,0 +1,120 @@
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::real_loop;
+use crate::cpu::tests::create_devices;
+use crate::cpu::Flags;
+use crate::cpu::Flags::{C, N, V, Z};
+
+fn run_code(code: Vec<u8>, check: impl Fn(&mut Cpu)) {
+    create_devices!(ppu, cpu);
+    cpu.get_state_mut().load(code, 0);
+    real_loop(&mut ppu, &mut cpu);
+    check(&mut cpu);
+}
+
+fn flag(cpu: &Cpu, f: Flags) -> bool {
+    cpu.status & f as u8 > 0
+}
+
+#[test]
+fn test_anc_copies_negative_into_carry() {
+    // lda #$F0, anc #$81
+    run_code(vec![0xA9, 0xF0, 0x0B, 0x81], |cpu| {
+        assert_eq!(cpu.a, 0x80);
+        assert!(flag(cpu, C));
+        assert!(flag(cpu, N));
+    });
+}
+
+#[test]
+fn test_dcp_decrements_then_compares() {
+    // lda #$21, sta $20, lda #$20, clc, dcp $20
+    run_code(vec![0xA9, 0x21, 0x85, 0x20, 0xA9, 0x20, 0x18, 0xC7, 0x20], |cpu| {
+        assert_eq!(cpu.get_state().cpu_ram[0x20], 0x20);
+        assert!(flag(cpu, C));
+        assert!(flag(cpu, Z));
+    });
+
+    // Same with lda #$10 and sec, the compare has to clear carry again
+    run_code(vec![0xA9, 0x21, 0x85, 0x20, 0xA9, 0x10, 0x38, 0xC7, 0x20], |cpu| {
+        assert_eq!(cpu.get_state().cpu_ram[0x20], 0x20);
+        assert!(!flag(cpu, C));
+        assert!(!flag(cpu, Z));
+        assert!(flag(cpu, N));
+    });
+}
+
+#[test]
+fn test_alr_and_then_shift() {
+    // lda #$FF, alr #$03
+    run_code(vec![0xA9, 0xFF, 0x4B, 0x03], |cpu| {
+        assert_eq!(cpu.a, 0x01);
+        assert!(flag(cpu, C));
+        assert!(!flag(cpu, N));
+    });
+}
+
+#[test]
+fn test_arr_flags_from_result() {
+    // sec, lda #$FF, arr #$FF
+    run_code(vec![0x38, 0xA9, 0xFF, 0x6B, 0xFF], |cpu| {
+        assert_eq!(cpu.a, 0xFF);
+        assert!(flag(cpu, C));
+        assert!(!flag(cpu, V));
+        assert!(flag(cpu, N));
+    });
+
+    // clc, lda #$C0, arr #$C0 gives $60: bit 6 set, bit 5 set
+    run_code(vec![0x18, 0xA9, 0xC0, 0x6B, 0xC0], |cpu| {
+        assert_eq!(cpu.a, 0x60);
+        assert!(flag(cpu, C));
+        assert!(!flag(cpu, V));
+    });
+
+    // clc, lda #$80, arr #$80 gives $40: V from bit 6 xor bit 5
+    run_code(vec![0x18, 0xA9, 0x80, 0x6B, 0x80], |cpu| {
+        assert_eq!(cpu.a, 0x40);
+        assert!(flag(cpu, V));
+    });
+}
+
+#[test]
+fn test_axs_subtracts_from_a_and_x() {
+    // lda #$0F, ldx #$F3, axs #$02
+    run_code(vec![0xA9, 0x0F, 0xA2, 0xF3, 0xCB, 0x02], |cpu| {
+        assert_eq!(cpu.x, 0x01);
+        assert!(flag(cpu, C));
+    });
+}
+
+#[test]
+fn test_las_loads_a_x_and_stack() {
+    // lda #$5A, sta $0200, ldy #$00, las $0200,y with S = $FF
+    run_code(vec![0xA2, 0xFF, 0x9A, 0xA9, 0x5A, 0x8D, 0x00, 0x02, 0xA0, 0x00, 0xBB, 0x00, 0x02], |cpu| {
+        assert_eq!((cpu.a, cpu.x, cpu.stkp), (0x5A, 0x5A, 0x5A));
+    });
+}
+
+#[test]
+fn test_shx_and_shy_mask_with_high_byte() {
+    // ldx #$FF, ldy #$01, shx $0300,y then shy $0300,x
+    run_code(vec![0xA2, 0xFF, 0xA0, 0x01, 0x9E, 0x00, 0x03, 0x9C, 0x00, 0x03], |cpu| {
+        let state = cpu.get_state();
+        assert_eq!(state.cpu_ram[0x0301], 0x04);
+        // Crossing into $03FF is not a page change, the value is Y and 4
+        assert_eq!(state.cpu_ram[0x03FF], 0x00);
+    });
+}
+
+#[test]
+fn test_jam_halts_until_reset() {
+    // lda #$01, jam, lda #$02
+    run_code(vec![0xA9, 0x01, 0x02, 0xA9, 0x02], |cpu| {
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.a, 0x01);
+        assert_eq!(cpu.pc, 0x0002);
+        assert!(cpu.clock().is_err());
+
+        cpu.reset();
+        assert!(!cpu.is_halted());
+    });
+}
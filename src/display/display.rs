@@ -1,10 +1,8 @@
-use std::cell::RefCell;
-use std::rc::Rc;
 use glutin_window::GlutinWindow as Window;
 use graphics::{Transformed};
 use graphics::Context;
 use opengl_graphics::{GlGraphics};
-use crate::{Cpu, Ppu, State};
+use crate::Nes;
 use crate::display::{EMU_HEIGHT, EMU_WIDTH};
 
 pub(crate) fn get_scaled_context(c: Context) -> Context {
@@ -17,9 +15,7 @@ pub(crate) fn get_scaled_context(c: Context) -> Context {
 pub struct NesSystem {
     pub(crate) window: Window,
     pub(crate) gl: GlGraphics,
-    pub(crate) state: Rc<RefCell<State>>,
-    pub(crate) cpu: Rc<RefCell<Cpu>>,
-    pub(crate) ppu: Rc<RefCell<Ppu>>
+    pub(crate) nes: Nes
 }
 
 pub trait Game {
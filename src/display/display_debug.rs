@@ -1,9 +1,7 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::rc::Rc;
 
 use graphics::clear;
 use image::{ImageBuffer, Rgba};
@@ -12,7 +10,7 @@ use piston::{Button, Key, PressEvent};
 use piston::event_loop::{Events, EventSettings};
 use piston::input::{RenderArgs, RenderEvent, UpdateEvent};
 
-use crate::{Cpu, Ppu, State};
+use crate::Nes;
 use crate::advance;
 use crate::display::{EMU_HEIGHT, EMU_WIDTH};
 use crate::display::display::{Game, NesSystem};
@@ -30,16 +28,14 @@ pub struct NesDebug(NesSystem, Debug);
 
 impl NesDebug {
     pub fn new(
-        state: Rc<RefCell<State>>,
-        cpu: Rc<RefCell<Cpu>>,
-        ppu: Rc<RefCell<Ppu>>,
+        nes: Nes,
         visible_pages: Vec<u16>,
         event_settings: EventSettings,
         start_instruction: u16,
         last_instruction: u16
     ) -> NesDebug {
         NesDebug(
-            NesSystem::new(state, cpu, ppu, EMU_WIDTH, EMU_HEIGHT, 0),
+            NesSystem::new(nes, EMU_WIDTH, EMU_HEIGHT, 0),
             Debug {
                 data: HashMap::default(),
                 visible_pages,
@@ -54,7 +50,7 @@ impl NesDebug {
         if let Some(items) = self.1.data.remove("disassembly") {
             return items;
         } else {
-            let disassembly = self.0.cpu.as_ref().borrow_mut().disassemble();
+            let disassembly = self.0.nes.cpu.disassemble();
             return disassembly;
         }
     }
@@ -71,8 +67,8 @@ impl NesDebug {
         let disassembly = self.get_disassembly();
 
         {
-            let state = self.0.state.as_ref().borrow();
-            let cpu = self.0.cpu.as_ref().borrow();
+            let state = self.0.nes.state();
+            let cpu = &self.0.nes.cpu;
             let visible_pages = &self.1.visible_pages;
             let mut glyphs: GlyphCache = GlyphCache::new("assets/PixelEmulator-xq08.ttf", (), TextureSettings::new()).unwrap();
 
@@ -98,24 +94,23 @@ impl Game for NesDebug {
         let mut texture = Texture::from_image(&d_img, &TextureSettings::new());
         // Main loop
         let mut running = false;
-        let _ = fs::remove_file("junk/debug.csv");
-        let _ = File::create("junk/debug.csv");
+        let _ = fs::remove_file("junk/trace.log");
+        let _ = File::create("junk/trace.log");
         let mut file = OpenOptions::new()
             .write(true)
             .append(true)
-            .open("junk/debug.csv")
+            .open("junk/trace.log")
             .unwrap();
 
         {
-            let mut cpu = self.0.cpu.as_ref().borrow_mut();
-            let mut ppu = self.0.ppu.as_ref().borrow_mut();
+            let cpu = &mut self.0.nes.cpu;
+            let ppu = &mut self.0.nes.ppu;
             if self.1.start_instruction != 0x0000 {
                 while cpu.pc != self.1.start_instruction {
-                    let write_string = hex::encode(&cpu.pc.to_be_bytes());
-                    if let Err(e) = writeln!(file, "{}", write_string.to_uppercase()) {
+                    if let Err(e) = writeln!(file, "{}", cpu.trace()) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
-                    let _ = advance(&mut ppu, &mut cpu);
+                    let _ = advance(ppu, cpu);
                 }
             }
         }
@@ -127,14 +122,13 @@ impl Game for NesDebug {
                         if key.eq(&Key::Space) {
                             running = !running;
                         } else {
-                            let mut ppu = self.0.ppu.as_ref().borrow_mut();
-                            let mut cpu = self.0.cpu.as_ref().borrow_mut();
-                            let write_string = hex::encode(&cpu.pc.to_be_bytes());
-                            if let Err(e) = writeln!(file, "{}", write_string.to_uppercase()) {
+                            let ppu = &mut self.0.nes.ppu;
+                            let cpu = &mut self.0.nes.cpu;
+                            if let Err(e) = writeln!(file, "{}", cpu.trace()) {
                                 eprintln!("Couldn't write to file: {}", e);
                             }
 
-                            let _ = advance(&mut ppu, &mut cpu);
+                            let _ = advance(ppu, cpu);
                         }
                     }
                     _ => {}
@@ -147,19 +141,18 @@ impl Game for NesDebug {
 
             if let Some(_args) = e.update_args() {
                 if running {
-                    let mut ppu = self.0.ppu.as_ref().borrow_mut();
-                    let mut cpu = self.0.cpu.as_ref().borrow_mut();
-                    let write_string = hex::encode(&cpu.pc.to_be_bytes());
-                    if let Err(e) = writeln!(file, "{}", write_string.to_uppercase()) {
+                    let ppu = &mut self.0.nes.ppu;
+                    let cpu = &mut self.0.nes.cpu;
+                    if let Err(e) = writeln!(file, "{}", cpu.trace()) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
 
-                    let _ = advance(&mut ppu, &mut cpu);
+                    let _ = advance(ppu, cpu);
                 }
             }
 
             {
-                let cpu = self.0.cpu.as_ref().borrow_mut();
+                let cpu = &self.0.nes.cpu;
                 if cpu.pc == self.1.last_instruction {
                     break
                 }
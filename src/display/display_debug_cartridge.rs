@@ -1,9 +1,7 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::rc::Rc;
 
 use graphics::{clear, Image, Transformed};
 use image::{ImageBuffer, Rgba};
@@ -12,9 +10,7 @@ use piston::{Button, Key, PressEvent};
 use piston::event_loop::{Events, EventSettings};
 use piston::input::{RenderArgs, RenderEvent, UpdateEvent};
 
-use crate::{Cpu, Ppu, State};
-use crate::advance;
-use crate::bus::system_clock;
+use crate::Nes;
 use crate::display::{EMU_HEIGHT, EMU_WIDTH};
 use crate::display::display::{Game, get_scaled_context, NesSystem};
 use crate::display::draw_debug::{draw_cart_debug, draw_debug};
@@ -29,14 +25,12 @@ pub struct NesDebugCartridge(NesSystem, Debug);
 
 impl NesDebugCartridge {
     pub fn new(
-        state: Rc<RefCell<State>>,
-        cpu: Rc<RefCell<Cpu>>,
-        ppu: Rc<RefCell<Ppu>>,
+        nes: Nes,
         visible_pages: Vec<u16>,
         event_settings: EventSettings,
     ) -> NesDebugCartridge {
         NesDebugCartridge(
-            NesSystem::new(state, cpu, ppu, EMU_WIDTH, EMU_HEIGHT, 300),
+            NesSystem::new(nes, EMU_WIDTH, EMU_HEIGHT, 300),
             Debug {
                 data: HashMap::default(),
                 visible_pages,
@@ -49,7 +43,7 @@ impl NesDebugCartridge {
         if let Some(items) = self.1.data.remove("disassembly") {
             return items;
         } else {
-            let disassembly = self.0.cpu.as_ref().borrow_mut().disassemble();
+            let disassembly = self.0.nes.cpu.disassemble();
             return disassembly;
         }
     }
@@ -66,8 +60,9 @@ impl NesDebugCartridge {
         let disassembly = self.get_disassembly();
 
         {
-            let state = self.0.state.as_ref().borrow();
-            let cpu = self.0.cpu.as_ref().borrow();
+            let state = self.0.nes.state();
+            let cpu = &self.0.nes.cpu;
+            let screen = self.0.nes.screen();
             let visible_pages = &self.1.visible_pages;
             let mut glyphs: GlyphCache = GlyphCache::new("assets/PixelEmulator-xq08.ttf", (), TextureSettings::new()).unwrap();
 
@@ -86,7 +81,7 @@ impl NesDebugCartridge {
 
                 for row in 0..EMU_HEIGHT {
                     for col in 0..EMU_WIDTH {
-                        d_img.put_pixel(col, row, state.screen[row as usize][col as usize]);
+                        d_img.put_pixel(col, row, screen.pixel(col, row));
                     }
                 }
 
@@ -119,9 +114,7 @@ impl Game for NesDebugCartridge {
                         if key.eq(&Key::Space) {
                             running = !running;
                         } else {
-                            let mut ppu = self.0.ppu.as_ref().borrow_mut();
-                            let mut cpu = self.0.cpu.as_ref().borrow_mut();
-                            let _ = system_clock(&mut ppu, &mut cpu);
+                            self.0.nes.run_frame();
                         }
                     }
                     _ => {}
@@ -134,9 +127,7 @@ impl Game for NesDebugCartridge {
 
             if let Some(_args) = e.update_args() {
                 if running {
-                    let mut ppu = self.0.ppu.as_ref().borrow_mut();
-                    let mut cpu = self.0.cpu.as_ref().borrow_mut();
-                    let _ = system_clock(&mut ppu, &mut cpu);
+                    self.0.nes.run_frame();
                     updated = true;
                 }
             }
@@ -1,5 +1,3 @@
-use std::cell::RefCell;
-use std::rc::Rc;
 use glutin_window::OpenGL;
 use graphics::{clear};
 use image::{ImageBuffer, Rgba};
@@ -9,14 +7,11 @@ use piston::event_loop::{Events, EventSettings};
 use piston::input::{RenderArgs, RenderEvent};
 use crate::display::display::{Game, get_scaled_context, NesSystem};
 use crate::display::draw_pixels::draw_pixels;
-use crate::{Cpu, State, Ppu};
-use crate::{advance};
+use crate::Nes;
 
 impl NesSystem {
     pub fn new(
-        state: Rc<RefCell<State>>,
-        cpu: Rc<RefCell<Cpu>>,
-        ppu: Rc<RefCell<Ppu>>,
+        nes: Nes,
         width: u32,
         height: u32,
         extra_width: u32
@@ -35,9 +30,7 @@ impl NesSystem {
         NesSystem {
             window,
             gl,
-            state,
-            cpu,
-            ppu
+            nes
         }
     }
 
@@ -46,8 +39,7 @@ impl NesSystem {
               mut d_img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
               mut texture: &mut Texture
     ) {
-        let state = self.state.as_ref().borrow();
-        let cpu = self.cpu.as_ref().borrow();
+        let screen = self.nes.screen();
 
         self.gl.draw(args.viewport(), |c, gl| {
             //Clear the screen
@@ -55,7 +47,7 @@ impl NesSystem {
 
             let context = get_scaled_context(c);
 
-            draw_pixels(&*state, d_img, texture, context, gl);
+            draw_pixels(screen, d_img, texture, context, gl);
         });
     }
 }
@@ -64,7 +56,8 @@ impl Game for NesSystem {
     fn start(&mut self){
         let mut events = Events::new(EventSettings {
             max_fps: 60,
-            ups: 100,
+            // One update runs a whole frame
+            ups: 60,
             swap_buffers: true,
             bench_mode: false,
             lazy: false,
@@ -96,10 +89,7 @@ impl Game for NesSystem {
 
             if let Some(_args) = e.update_args() {
                 if running {
-                    let mut ppu = self.ppu.as_ref().borrow_mut();
-                    let mut cpu = self.cpu.as_ref().borrow_mut();
-
-                    let _ = advance(&mut ppu, &mut cpu);
+                    self.nes.run_frame();
                 }
             }
         }
@@ -6,7 +6,7 @@ use piston::event_loop::{Events, EventSettings};
 use piston::input::{RenderArgs, RenderEvent, UpdateEvent};
 use crate::display::display::{Game, NesSystem};
 use crate::display::{EMU_HEIGHT, EMU_WIDTH};
-use crate::{COLOR_BLACK, COLOR_BLUE, COLOR_CYAN, COLOR_GRAY, COLOR_GREEN, COLOR_MAGENTA, COLOR_RED, COLOR_WHITE, COLOR_YELLOW, Cpu, Ppu, State};
+use crate::{COLOR_BLACK, COLOR_BLUE, COLOR_CYAN, COLOR_GRAY, COLOR_GREEN, COLOR_MAGENTA, COLOR_RED, COLOR_WHITE, COLOR_YELLOW, Nes};
 use std::cell::RefCell;
 use std::rc::Rc;
 use rand::{Rng, thread_rng};
@@ -31,8 +31,8 @@ fn color(byte: u8) -> [u8; 4] {
 
 impl SnakeGame {
 
-    pub fn new(state: Rc<RefCell<State>>, cpu: Rc<RefCell<Cpu>>, ppu: Rc<RefCell<Ppu>>) -> SnakeGame {
-        SnakeGame(NesSystem::new(state, cpu, ppu, EMU_WIDTH, EMU_HEIGHT, 0))
+    pub fn new(nes: Nes) -> SnakeGame {
+        SnakeGame(NesSystem::new(nes, EMU_WIDTH, EMU_HEIGHT, 0))
     }
 
     fn render(&mut self,
@@ -40,7 +40,7 @@ impl SnakeGame {
               d_img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
               texture: &mut Texture
     ) {
-        let state = self.0.state.as_ref().borrow();
+        let state = self.0.nes.state();
 
         self.0.gl.draw(args.viewport(), |c, gl| {
             //Clear the screen
@@ -69,18 +69,18 @@ impl SnakeGame {
     }
 
     fn key_press(&mut self, args: Button) {
-        let mut state = self.0.state.as_ref().borrow_mut();
+        let state = self.0.nes.state_mut();
         match args {
             Button::Keyboard(key) => {
                 println!("{:?}", key);
                 if key.eq(&Key::Up) {
-                    mem_write(&mut state, 0xff, 0x77);
+                    mem_write(state, 0xff, 0x77);
                 } else if key.eq(&Key::Down) {
-                    mem_write(&mut state, 0xff, 0x73);
+                    mem_write(state, 0xff, 0x73);
                 } else if key.eq(&Key::Left) {
-                    mem_write(&mut state, 0xff, 0x61);
+                    mem_write(state, 0xff, 0x61);
                 } else if key.eq(&Key::Right) {
-                    mem_write(&mut state, 0xff, 0x64);
+                    mem_write(state, 0xff, 0x64);
                 }
             }
             _ => {}
@@ -125,13 +125,13 @@ impl Game for SnakeGame {
 
             if let Some(_args) = e.update_args() {
                 if running {
-                    let mut ppu = self.0.ppu.as_ref().borrow_mut();
-                    let mut cpu = self.0.cpu.as_ref().borrow_mut();
+                    let ppu = &mut self.0.nes.ppu;
+                    let cpu = &mut self.0.nes.cpu;
 
                     let val = rng.as_ref().borrow_mut().gen_range(1, 16);
                     cpu.write(0xfe, val);
 
-                    let _ = advance(&mut ppu, &mut cpu);
+                    let _ = advance(ppu, cpu);
                 }
             }
         }
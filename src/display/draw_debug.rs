@@ -3,7 +3,8 @@ use graphics::{Context, text, Transformed};
 use graphics::types::Color;
 use opengl_graphics::{GlGraphics, GlyphCache};
 use crate::{COLOR_BLUE, COLOR_WHITE, COLOR_GREEN, COLOR_RED, Cpu, State};
-use crate::bus::mem_read;
+use crate::bus::mem_peek;
+use crate::ppu::ppu_peek;
 use crate::display::IColor;
 use crate::cpu::Flags;
 use crate::display::{EMU_HEIGHT, EMU_WIDTH};
@@ -135,6 +136,16 @@ pub(crate) fn draw_cart_debug(
         }
         c_addr += 1;
     }
+
+    // Background then sprite palettes, eight entries a line
+    for line in 0..4 {
+        let mut write_string = format!("${:04X}:", 0x3F00 + line * 8);
+        for entry in 0..8 {
+            write_string = format!("{} {:02X}", write_string, ppu_peek(state, 0x3F00 + line * 8 + entry));
+        }
+        draw_string!(&write_string, x, y, COLOR_WHITE);
+        y += text_size;
+    }
 }
 
 
@@ -276,7 +287,7 @@ pub(crate) fn draw_debug(
         for _ in 0..16 {
             let mut write_string = format!("${}:", hex::encode(&(addr as u16).to_be_bytes()));
             for _ in 0..16 {
-                let value = hex::encode(&mem_read(&state, addr as u16, true).to_be_bytes());
+                let value = hex::encode(&mem_peek(&state, addr as u16).to_be_bytes());
                 write_string = format!("{} {}", write_string, &value[value.len()-2..]);
                 addr += 1;
             }
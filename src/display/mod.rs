@@ -3,8 +3,7 @@ extern crate graphics;
 extern crate glutin_window;
 extern crate opengl_graphics;
 
-pub(crate) const EMU_WIDTH: u32 = 256;
-pub(crate) const EMU_HEIGHT: u32 = 240;
+pub(crate) use crate::ppu::{EMU_HEIGHT, EMU_WIDTH};
 
 #[derive(Debug)]
 pub(crate) struct IColor {
@@ -0,0 +1,383 @@
+use crate::apu::fds_audio::FdsAudio;
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+
+// CPU cycles the drive takes to spin up, and to stream one byte at 96.4 kbit/s
+const SPIN_UP_CYCLES: u32 = 50000;
+const BYTE_CYCLES: u32 = 149;
+
+/**
+ * Famicom Disk System RAM adapter
+ *
+ * $6000-$DFFF  32 KB PRG RAM
+ * $E000-$FFFF  BIOS, held in the cartridge PRG memory
+ * $4020/$4021  timer IRQ reload value
+ * $4022        timer IRQ control, bit 0 repeat and bit 1 enable
+ * $4023        master I/O enable, bit 0 disk registers and bit 1 sound
+ * $4024        write data
+ * $4025        drive control: motor, transfer reset, read mode, mirroring, CRC, start, IRQ
+ * $4030        status: timer IRQ, byte transferred, CRC error and end of disk
+ * $4031        read data
+ * $4032        drive status: no disk, not ready and write protected
+ * $4040-$4092  sound, see FdsAudio
+ *
+ * The drive streams the inserted side one byte every BYTE_CYCLES while the motor runs,
+ * and skips ahead to the $80 mark at the end of each gap once reading starts.
+ */
+pub struct Fds {
+    prg_ram: Vec<u8>,
+    sides: Vec<Vec<u8>>,
+    inserted: Option<usize>,
+
+    disk_enabled: bool,
+    sound_enabled: bool,
+    control: u8,
+
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_repeat: bool,
+    timer_enabled: bool,
+    timer_irq: bool,
+
+    position: usize,
+    delay: u32,
+    scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+    transfer_complete: bool,
+    disk_irq: bool,
+    read_data: u8,
+    write_data: u8,
+
+    audio: FdsAudio,
+}
+
+impl Fds {
+
+    pub fn new(sides: Vec<Vec<u8>>) -> Fds {
+        let inserted = if sides.is_empty() { None } else { Some(0) };
+        Fds {
+            prg_ram: vec![0; 32 * 1024],
+            sides,
+            inserted,
+            disk_enabled: false,
+            sound_enabled: false,
+            control: 0,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_repeat: false,
+            timer_enabled: false,
+            timer_irq: false,
+            position: 0,
+            delay: 0,
+            scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+            transfer_complete: false,
+            disk_irq: false,
+            read_data: 0,
+            write_data: 0,
+            audio: FdsAudio::new()
+        }
+    }
+
+    fn motor_on(&self) -> bool {
+        self.control & 0x01 > 0
+    }
+
+    fn read_mode(&self) -> bool {
+        self.control & 0x04 > 0
+    }
+
+    // Reading $4030 or $4031 acknowledges the disk and timer interrupts
+    fn read_register(&mut self, addr: u16) -> Option<u8> {
+        let data = self.peek_register(addr);
+        if data.is_some() {
+            match addr {
+                0x4030 => {
+                    self.timer_irq = false;
+                    self.transfer_complete = false;
+                    self.disk_irq = false;
+                }
+                0x4031 => {
+                    self.transfer_complete = false;
+                    self.disk_irq = false;
+                }
+                _ => {}
+            }
+        }
+        return data;
+    }
+
+    fn peek_register(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x4040 {
+            if !self.sound_enabled {
+                return None;
+            }
+            return self.audio.read(addr);
+        }
+        if !self.disk_enabled {
+            return None;
+        }
+
+        match addr {
+            0x4030 => {
+                let mut status = 0x00;
+                if self.timer_irq { status |= 0x01; }
+                if self.transfer_complete { status |= 0x02; }
+                if self.end_of_head { status |= 0x40; }
+                Some(status)
+            }
+            0x4031 => Some(self.read_data),
+            0x4032 => {
+                let mut status = 0x40;
+                if self.inserted.is_none() { status |= 0x05; }
+                if self.inserted.is_none() || !self.scanning { status |= 0x02; }
+                Some(status)
+            }
+            // Battery good
+            0x4033 => Some(0x80),
+            _ => None
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        if addr == 0x4023 {
+            self.disk_enabled = data & 0x01 > 0;
+            self.sound_enabled = data & 0x02 > 0;
+            if !self.disk_enabled {
+                self.timer_enabled = false;
+                self.timer_irq = false;
+            }
+            return;
+        }
+        if addr >= 0x4040 {
+            if self.sound_enabled {
+                self.audio.write(addr, data);
+            }
+            return;
+        }
+        if !self.disk_enabled {
+            return;
+        }
+
+        match addr {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | data as u16,
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | ((data as u16) << 8),
+            0x4022 => {
+                self.timer_repeat = data & 0x01 > 0;
+                self.timer_enabled = data & 0x02 > 0;
+                if self.timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4024 => {
+                self.write_data = data;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 => {
+                self.control = data;
+                self.disk_irq = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.timer_enabled || !self.disk_enabled {
+            return;
+        }
+        if self.timer_counter == 0 {
+            self.timer_irq = true;
+            self.timer_counter = self.timer_reload;
+            if !self.timer_repeat {
+                self.timer_enabled = false;
+            }
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn clock_drive(&mut self) {
+        let side = match self.inserted {
+            Some(side) => side,
+            None => return
+        };
+
+        if !self.motor_on() {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+        // Transfer reset holds the head at the start until it is released
+        if self.control & 0x02 > 0 && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = SPIN_UP_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let start = self.control & 0x40 > 0;
+        let irq_enabled = self.control & 0x80 > 0;
+
+        if self.read_mode() {
+            let data = self.sides[side][self.position];
+            if !start {
+                self.gap_ended = false;
+            } else if data != 0 && !self.gap_ended {
+                // The gap end mark itself is not handed to the CPU
+                self.gap_ended = true;
+            } else if self.gap_ended {
+                self.read_data = data;
+                self.transfer_complete = true;
+                self.disk_irq = irq_enabled;
+            }
+        } else {
+            // CRC bytes are generated by the drive while bit 4 is set, nothing to hand over
+            let crc = self.control & 0x10 > 0;
+            if !crc {
+                self.transfer_complete = true;
+                self.disk_irq = irq_enabled;
+            }
+            self.sides[side][self.position] = if start && !crc { self.write_data } else { 0 };
+            self.gap_ended = false;
+        }
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.end_of_head = true;
+            self.scanning = false;
+        } else {
+            self.delay = BYTE_CYCLES;
+        }
+    }
+}
+
+impl Mapper for Fds {
+
+    fn cpu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr == 0x4030 || addr == 0x4031 {
+            if let Some(value) = self.read_register(addr) {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = value;
+                return true;
+            }
+            return false;
+        }
+        return self.cpu_map_peek(addr, mapped_addr, data);
+    }
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        match addr {
+            0x4030..=0x4033 | 0x4040..=0x407F | 0x4090 | 0x4092 => {
+                if let Some(value) = self.peek_register(addr) {
+                    *mapped_addr = MAPPER_HANDLED;
+                    *data = value;
+                    return true;
+                }
+                false
+            }
+            0x6000..=0xDFFF => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.prg_ram[(addr - 0x6000) as usize];
+                true
+            }
+            0xE000..=0xFFFF => {
+                *mapped_addr = (addr & 0x1FFF) as u32;
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        match addr {
+            0x4020..=0x4026 | 0x4040..=0x408A => {
+                self.write_register(addr, data);
+                false
+            }
+            0x6000..=0xDFFF => {
+                *mapped_addr = MAPPER_HANDLED;
+                self.prg_ram[(addr - 0x6000) as usize] = data;
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            *mapped_addr = addr as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, _data: u8) -> bool {
+        if addr <= 0x1FFF {
+            *mapped_addr = addr as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        if self.control & 0x08 > 0 { Mirror::Horizontal } else { Mirror::Vertical }
+    }
+
+    fn irq_state(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    fn cpu_clock(&mut self) {
+        self.clock_timer();
+        self.clock_drive();
+        if self.sound_enabled {
+            self.audio.clock();
+        }
+    }
+
+    fn audio_sample(&self) -> f32 {
+        if !self.sound_enabled {
+            return 0.0;
+        }
+        self.audio.output()
+    }
+
+    fn disk_sides(&self) -> usize {
+        self.sides.len()
+    }
+
+    fn insert_disk(&mut self, side: Option<usize>) -> bool {
+        if let Some(index) = side {
+            if index >= self.sides.len() {
+                return false;
+            }
+        }
+        self.inserted = side;
+        self.end_of_head = true;
+        self.scanning = false;
+        return true;
+    }
+
+    fn reset(&mut self) {
+        self.control = 0;
+        self.timer_enabled = false;
+        self.timer_irq = false;
+        self.disk_irq = false;
+        self.end_of_head = true;
+        self.scanning = false;
+    }
+}
@@ -1,15 +1,32 @@
-use crate::mapper::Mapper;
-use crate::State;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
 
-pub struct Mapper0;
+pub struct Mapper0 {
+    n_prgbanks: u8,
+    n_chrbanks: u8,
+    // 8 KB at $6000, test ROMs report their results there
+    prg_ram: Vec<u8>,
+}
+
+impl Mapper0 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper0 {
+        Mapper0 {
+            n_prgbanks,
+            n_chrbanks,
+            prg_ram: vec![0; 8192]
+        }
+    }
+}
 
 impl Mapper for Mapper0 {
 
-    fn cpu_map_read(&mut self, state: &State, addr: u16, mapped_addr: &mut u32) -> bool {
-        if addr >= 0x8000 && addr <= 0xffff {
-            let cart = state.cartridge.as_ref().expect("Missing cart").as_ref().borrow_mut();
-            let banks = cart.n_prgbanks;
-            let mapped = (addr & if banks > 1 {
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            return true;
+        } else if addr >= 0x8000 {
+            let mapped = (addr & if self.n_prgbanks > 1 {
                 0x7fff
             } else {
                 0x3fff
@@ -21,11 +38,13 @@ impl Mapper for Mapper0 {
         return false;
     }
 
-    fn cpu_map_write(&mut self, state: &State, addr: u16, mapped_addr: &mut u32) -> bool {
-        if addr >= 0x8000 && addr <= 0xffff {
-            let cart = state.cartridge.as_ref().expect("Missing cart").as_ref().borrow_mut();
-            let banks = cart.n_prgbanks;
-            let mapped = (addr & if banks > 1 {
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            self.prg_ram[(addr & 0x1FFF) as usize] = data;
+            return true;
+        } else if addr >= 0x8000 {
+            let mapped = (addr & if self.n_prgbanks > 1 {
                 0x7fff
             } else {
                 0x3fff
@@ -37,26 +56,21 @@ impl Mapper for Mapper0 {
         return false;
     }
 
-    fn ppu_map_read(&mut self, state: State, addr: u16, mapped_addr: &mut u32) -> bool {
-        if addr >= 0x0000 && addr <= 0x1fff {
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1fff {
             *mapped_addr = addr as u32;
             return true;
         }
         return false;
     }
 
-    fn ppu_map_write(&mut self, state: State, addr: u16, mapped_addr: &mut u32) -> bool {
-        if addr >= 0x0000 && addr <= 0x1FFF {
-            let cart = state.cartridge.as_ref().expect("Missing cart").as_ref().borrow_mut();
-            let banks = cart.n_prgbanks;
-
-            if banks == 0 {
-                // Treat as RAM
-                *mapped_addr = addr as u32;
-                return true;
-            }
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, _data: u8) -> bool {
+        if addr <= 0x1FFF && self.n_chrbanks == 0 {
+            // Treat as RAM
+            *mapped_addr = addr as u32;
+            return true;
         }
 
         return false;
     }
-}
\ No newline at end of file
+}
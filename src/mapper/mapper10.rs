@@ -0,0 +1,100 @@
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+use crate::mapper::mapper9::ChrLatch;
+
+/**
+ * MMC4 (Fire Emblem, Famicom Wars)
+ *
+ * $6000-$7FFF  8 KB PRG RAM
+ * $8000-$BFFF  16 KB switchable PRG bank, selected by $A000
+ * $C000-$FFFF  the last 16 KB PRG bank, fixed
+ * CHR latches behave like MMC2 except both latches trigger on the whole tile row
+ */
+pub struct Mapper10 {
+    n_prgbanks: u8,
+    n_chrbanks: u8,
+    prg_bank: u8,
+    prg_ram: Vec<u8>,
+    chr: ChrLatch,
+    mirror: Mirror,
+}
+
+impl Mapper10 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper10 {
+        Mapper10 {
+            n_prgbanks,
+            n_chrbanks,
+            prg_bank: 0,
+            prg_ram: vec![0; 8192],
+            chr: ChrLatch::new(n_chrbanks),
+            mirror: Mirror::Vertical
+        }
+    }
+}
+
+impl Mapper for Mapper10 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            return true;
+        } else if addr >= 0x8000 && addr <= 0xBFFF {
+            let bank = self.prg_bank as u32 % self.n_prgbanks as u32;
+            *mapped_addr = bank * 0x4000 + (addr & 0x3FFF) as u32;
+            return true;
+        } else if addr >= 0xC000 {
+            let bank = self.n_prgbanks as u32 - 1;
+            *mapped_addr = bank * 0x4000 + (addr & 0x3FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            self.prg_ram[(addr & 0x1FFF) as usize] = data;
+            return true;
+        } else if addr >= 0xA000 {
+            match addr & 0xF000 {
+                0xA000 => self.prg_bank = data & 0x0F,
+                0xF000 => self.mirror = if data & 0x01 > 0 { Mirror::Horizontal } else { Mirror::Vertical },
+                _ => {
+                    self.chr.write_register(addr, data);
+                }
+            }
+        }
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            *mapped_addr = self.chr.map(addr);
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if self.ppu_map_peek(addr, mapped_addr, data) {
+            self.chr.observe(addr, false);
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr = ChrLatch::new(self.n_chrbanks);
+    }
+}
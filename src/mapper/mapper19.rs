@@ -0,0 +1,185 @@
+use crate::apu::n163_audio::N163Audio;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+
+/**
+ * Namco 163 (mapper 19)
+ *
+ * $4800        sound RAM data port
+ * $5000/$5800  IRQ counter low / high, bit 7 of $5800 enables it
+ * $8000-$B800  1 KB CHR banks
+ * $C000-$D800  name table banks, $E0 and up select CIRAM, anything else a CHR ROM page
+ * $E000        PRG bank at $8000, bit 6 disables sound
+ * $E800        PRG bank at $A000
+ * $F000        PRG bank at $C000
+ * $F800        sound RAM address
+ *
+ * $E000-$FFFF is fixed to the last 8 KB bank. The 15 bit IRQ counter counts up every CPU
+ * cycle and fires at $7FFF. CHR values of $E0 and up, which select CIRAM as pattern
+ * memory on the real board, are treated as ordinary CHR ROM banks.
+ */
+pub struct Mapper19 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+    chr_regs: [u8; 8],
+    nt_regs: [u8; 4],
+    prg_regs: [u8; 3],
+    sound_disabled: bool,
+    prg_ram: Vec<u8>,
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_pending: bool,
+    audio: N163Audio,
+}
+
+impl Mapper19 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper19 {
+        Mapper19 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(1),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            chr_regs: [0; 8],
+            nt_regs: [0xE0, 0xE1, 0xE0, 0xE1],
+            prg_regs: [0; 3],
+            sound_disabled: false,
+            prg_ram: vec![0; 8192],
+            irq_counter: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            audio: N163Audio::new()
+        }
+    }
+}
+
+impl Mapper for Mapper19 {
+
+    // Reading the sound RAM port moves its address on when auto increment is set
+    fn cpu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x4800 && addr <= 0x4FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.audio.read_data();
+            return true;
+        }
+        return self.cpu_map_peek(addr, mapped_addr, data);
+    }
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        match addr {
+            0x4800..=0x4FFF => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.audio.peek_data();
+                true
+            }
+            0x5000..=0x57FF => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.irq_counter as u8;
+                true
+            }
+            0x5800..=0x5FFF => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = ((self.irq_counter >> 8) as u8) | if self.irq_enabled { 0x80 } else { 0 };
+                true
+            }
+            0x6000..=0x7FFF => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.prg_ram[(addr & 0x1FFF) as usize];
+                true
+            }
+            0x8000..=0xFFFF => {
+                let bank = match addr {
+                    0x8000..=0x9FFF => self.prg_regs[0] as u32,
+                    0xA000..=0xBFFF => self.prg_regs[1] as u32,
+                    0xC000..=0xDFFF => self.prg_regs[2] as u32,
+                    _ => self.prg_banks_8k - 1
+                };
+                *mapped_addr = (bank % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        match addr {
+            0x4800..=0x4FFF => self.audio.write_data(data),
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | (((data & 0x7F) as u16) << 8);
+                self.irq_enabled = data & 0x80 > 0;
+                self.irq_pending = false;
+            }
+            0x6000..=0x7FFF => self.prg_ram[(addr & 0x1FFF) as usize] = data,
+            0x8000..=0xBFFF => self.chr_regs[((addr - 0x8000) / 0x0800) as usize] = data,
+            0xC000..=0xDFFF => self.nt_regs[((addr - 0xC000) / 0x0800) as usize] = data,
+            0xE000..=0xE7FF => {
+                self.prg_regs[0] = data & 0x3F;
+                self.sound_disabled = data & 0x40 > 0;
+            }
+            0xE800..=0xEFFF => self.prg_regs[1] = data & 0x3F,
+            0xF000..=0xF7FF => self.prg_regs[2] = data & 0x3F,
+            0xF800..=0xFFFF => self.audio.write_address(data),
+            _ => return false
+        }
+        *mapped_addr = MAPPER_HANDLED;
+        return true;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        let bank = if addr <= 0x1FFF {
+            self.chr_regs[(addr / 0x0400) as usize]
+        } else if addr <= 0x3EFF {
+            let bank = self.nt_regs[((addr & 0x0FFF) / 0x0400) as usize];
+            if bank >= 0xE0 {
+                return false;
+            }
+            bank
+        } else {
+            return false;
+        };
+        *mapped_addr = (bank as u32 % self.chr_banks_1k) * 0x0400 + (addr & 0x03FF) as u32;
+        return true;
+    }
+
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, _data: u8) -> bool {
+        // Name tables paged to CHR ROM swallow the write
+        if addr >= 0x2000 && addr <= 0x3EFF && self.nt_regs[((addr & 0x0FFF) / 0x0400) as usize] < 0xE0 {
+            *mapped_addr = MAPPER_HANDLED;
+            return true;
+        }
+        return false;
+    }
+
+    fn ciram_page(&self, quadrant: usize) -> Option<usize> {
+        Some((self.nt_regs[quadrant] & 0x01) as usize)
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.irq_enabled && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7FFF {
+                self.irq_pending = true;
+            }
+        }
+        if !self.sound_disabled {
+            self.audio.clock();
+        }
+    }
+
+    fn audio_sample(&self) -> f32 {
+        if self.sound_disabled {
+            return 0.0;
+        }
+        self.audio.output()
+    }
+
+    fn reset(&mut self) {
+        self.irq_enabled = false;
+        self.irq_pending = false;
+    }
+}
@@ -0,0 +1,485 @@
+use crate::apu::mmc5_audio::Mmc5Audio;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+
+// fetch_count while the PPU is not rendering
+const IDLE: u32 = u32::MAX;
+
+/**
+ * MMC5 (Castlevania III, Just Breed, Uncharted Waters)
+ *
+ * $5100        PRG mode, 0 = 32 KB, 1 = 16 KB x 2, 2 = 16 KB + 8 KB x 2, 3 = 8 KB x 4
+ * $5101        CHR mode, 0 = 8 KB, 1 = 4 KB, 2 = 2 KB, 3 = 1 KB pages
+ * $5102/$5103  PRG RAM write protect, writes only land when they hold 2 and 1
+ * $5104        ExRAM mode, 0 = name table, 1 = extended attributes, 2 = RAM, 3 = read only RAM
+ * $5105        name table mapping, two bits per quadrant: CIRAM 0, CIRAM 1, ExRAM, fill
+ * $5106/$5107  fill mode tile and colour
+ * $5113-$5117  PRG banks, bit 7 selects ROM over RAM for $5114-$5116
+ * $5120-$512B  CHR banks, $5120-$5127 is set A (sprites) and $5128-$512B set B (background)
+ * $5130        upper CHR bank bits
+ * $5200-$5202  vertical split control, scroll and CHR page
+ * $5203/$5204  scanline IRQ compare value and enable / status
+ * $5205/$5206  8 x 8 -> 16 bit unsigned multiplier
+ * $5C00-$5FFF  1 KB ExRAM
+ * $5000-$5015  expansion audio, see Mmc5Audio
+ *
+ * The MMC5 has no scanline input, it finds the start of each line by watching for the
+ * three identical name table fetches the PPU makes at the end of every rendered line
+ * and then counts the fetches that follow to know whether the PPU is drawing the
+ * background or fetching sprites.
+ */
+pub struct Mapper5 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    prg_regs: [u8; 5],
+    prg_ram: Vec<u8>,
+
+    chr_regs: [u16; 12],
+    chr_upper: u8,
+    // True when set B ($5128-$512B) was written last, used when sprites are 8x8
+    last_chr_set_b: bool,
+
+    exram_mode: u8,
+    exram: Vec<u8>,
+    nt_mapping: u8,
+    fill_tile: u8,
+    fill_color: u8,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_page: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    // Snooped from the PPU registers
+    sprite_8x16: bool,
+    rendering: bool,
+
+    // Scanline detection state
+    in_frame: bool,
+    scanline: u8,
+    last_nt_addr: u16,
+    nt_matches: u8,
+    fetch_count: u32,
+    // ExRAM byte fetched alongside the current tile in extended attribute mode
+    ext_attribute: u8,
+    // Set while the current background tile comes from the split region
+    in_split: bool,
+
+    audio: Mmc5Audio,
+}
+
+impl Mapper5 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper5 {
+        Mapper5 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(1),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0, 0],
+            prg_regs: [0, 0, 0, 0, 0xFF],
+            prg_ram: vec![0; 64 * 1024],
+            chr_regs: [0; 12],
+            chr_upper: 0,
+            last_chr_set_b: false,
+            exram_mode: 0,
+            exram: vec![0; 1024],
+            nt_mapping: 0,
+            fill_tile: 0,
+            fill_color: 0,
+            split_control: 0,
+            split_scroll: 0,
+            split_page: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            sprite_8x16: false,
+            rendering: false,
+            in_frame: false,
+            scanline: 0,
+            last_nt_addr: 0,
+            nt_matches: 0,
+            fetch_count: IDLE,
+            ext_attribute: 0,
+            in_split: false,
+            audio: Mmc5Audio::new()
+        }
+    }
+
+    // Maps a CPU address in $6000-$FFFF to (is_rom, 8 KB bank)
+    fn prg_bank(&self, addr: u16) -> (bool, u32) {
+        if addr < 0x8000 {
+            return (false, (self.prg_regs[0] & 0x07) as u32);
+        }
+
+        let slot = ((addr - 0x8000) / 0x2000) as u8;
+        let (reg, size) = match self.prg_mode {
+            0 => (4, 4),
+            1 => if slot < 2 { (2, 2) } else { (4, 2) },
+            2 => if slot < 2 { (2, 2) } else { (slot + 1, 1) },
+            _ => (slot + 1, 1)
+        };
+
+        let value = self.prg_regs[reg as usize];
+        let is_rom = reg == 4 || value & 0x80 > 0;
+        // Larger pages ignore the low bits of the register and use the address instead
+        let bank = (value & 0x7F) as u32 & !(size as u32 - 1);
+        (is_rom, bank | (slot as u32 & (size as u32 - 1)))
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect[0] == 0x02 && self.prg_ram_protect[1] == 0x01
+    }
+
+    fn chr_map(&self, addr: u16) -> u32 {
+        let use_set_b = if self.sprite_8x16 && self.in_frame {
+            // Set A for sprites, set B for the background
+            !self.fetching_sprites()
+        } else {
+            self.last_chr_set_b
+        };
+
+        let (page_size, index) = match self.chr_mode {
+            0 => (8, if use_set_b { 11 } else { 7 }),
+            1 => (4, if use_set_b { 11 } else { (addr / 0x1000) as usize * 4 + 3 }),
+            2 => (2, if use_set_b { 9 + ((addr & 0x0FFF) / 0x0800) as usize * 2 } else { (addr / 0x0800) as usize * 2 + 1 }),
+            _ => (1, if use_set_b { 8 + ((addr & 0x0FFF) / 0x0400) as usize } else { (addr / 0x0400) as usize })
+        };
+
+        let bank = self.chr_regs[index] as u32 * page_size;
+        let offset = (addr as u32) & (page_size * 0x0400 - 1);
+        ((bank % self.chr_banks_1k) * 0x0400 + offset) % (self.chr_banks_1k * 0x0400)
+    }
+
+    fn fetching_sprites(&self) -> bool {
+        self.fetch_count >= 128 && self.fetch_count < 160
+    }
+
+    // Tile column the PPU is fetching for, and whether it belongs to the next scanline
+    fn fetch_column(&self) -> Option<(u32, bool)> {
+        if self.fetch_count < 128 {
+            Some((self.fetch_count / 4 + 2, false))
+        } else if self.fetch_count >= 160 && self.fetch_count < 168 {
+            Some(((self.fetch_count - 160) / 4, true))
+        } else {
+            None
+        }
+    }
+
+    fn split_active(&self, column: u32) -> bool {
+        if self.split_control & 0x80 == 0 || self.exram_mode > 1 || column >= 32 {
+            return false;
+        }
+        let threshold = (self.split_control & 0x1F) as u32;
+        if self.split_control & 0x40 > 0 {
+            column >= threshold
+        } else {
+            column < threshold
+        }
+    }
+
+    fn split_y(&self, next_line: bool) -> u32 {
+        let line = self.scanline as u32 + if next_line { 1 } else { 0 };
+        (self.split_scroll as u32 + line) % 240
+    }
+
+    // Leaves fetch_count holding the index of this fetch within the scanline
+    fn detect_scanline(&mut self, addr: u16) {
+        if addr >= 0x2000 && addr <= 0x2FFF && addr == self.last_nt_addr {
+            self.nt_matches += 1;
+            if self.nt_matches == 2 {
+                self.nt_matches = 0;
+                if self.in_frame {
+                    self.scanline = self.scanline.wrapping_add(1);
+                    if self.scanline == self.irq_compare {
+                        self.irq_pending = true;
+                    }
+                } else {
+                    self.in_frame = true;
+                    self.scanline = 0;
+                }
+                // The read that completed the match is the first fetch of the new line
+                self.fetch_count = 0;
+            }
+        } else {
+            self.nt_matches = 0;
+        }
+        self.last_nt_addr = addr;
+    }
+
+    fn replicate(bits: u8) -> u8 {
+        let bits = bits & 0x03;
+        bits | (bits << 2) | (bits << 4) | (bits << 6)
+    }
+
+    // A tile fetch decides whether the split is showing and picks up the extended attribute
+    fn track_name_table_fetch(&mut self, addr: u16) {
+        let offset = (addr & 0x03FF) as usize;
+        if offset >= 0x03C0 {
+            return;
+        }
+        if let Some((column, _)) = self.fetch_column() {
+            self.in_split = self.split_active(column);
+            if !self.in_split && self.exram_mode == 1 {
+                self.ext_attribute = self.exram[offset];
+            }
+        }
+    }
+
+    fn peek_name_table(&self, addr: u16, data: &mut u8) -> bool {
+        let offset = (addr & 0x03FF) as usize;
+        let is_attribute = offset >= 0x03C0;
+
+        if let Some((column, next_line)) = self.fetch_column() {
+            if self.in_split {
+                let y = self.split_y(next_line);
+                let row = y / 8;
+                *data = if is_attribute {
+                    let value = self.exram[0x03C0 + ((row / 4) * 8 + column / 4) as usize];
+                    let shift = ((row & 0x02) << 1) | (column & 0x02);
+                    Mapper5::replicate(value >> shift)
+                } else {
+                    self.exram[(row * 32 + column) as usize]
+                };
+                return true;
+            }
+
+            if self.exram_mode == 1 && is_attribute {
+                *data = Mapper5::replicate(self.ext_attribute >> 6);
+                return true;
+            }
+        }
+
+        let quadrant = ((addr & 0x0FFF) / 0x0400) as u8;
+        match (self.nt_mapping >> (quadrant * 2)) & 0x03 {
+            2 => {
+                *data = if self.exram_mode <= 1 { self.exram[offset] } else { 0 };
+                true
+            }
+            3 => {
+                *data = if is_attribute { Mapper5::replicate(self.fill_color) } else { self.fill_tile };
+                true
+            }
+            // CIRAM, handled by the PPU through ciram_page
+            _ => false
+        }
+    }
+}
+
+impl Mapper for Mapper5 {
+
+    fn cpu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        let handled = self.cpu_map_peek(addr, mapped_addr, data);
+        match addr {
+            // Reading the status acknowledges the scanline IRQ
+            0x5204 => self.irq_pending = false,
+            // The CPU fetching the NMI vector marks the end of the frame
+            0xFFFA | 0xFFFB => {
+                self.in_frame = false;
+                self.last_nt_addr = 0;
+                self.fetch_count = IDLE;
+            }
+            _ => {}
+        }
+        handled
+    }
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        match addr {
+            0x5015 => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.audio.read_status();
+                true
+            }
+            0x5204 => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = (if self.irq_pending { 0x80 } else { 0 }) | (if self.in_frame { 0x40 } else { 0 });
+                true
+            }
+            0x5205 => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = (self.multiplicand as u16 * self.multiplier as u16) as u8;
+                true
+            }
+            0x5206 => {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8;
+                true
+            }
+            0x5C00..=0x5FFF => {
+                if self.exram_mode < 2 {
+                    return false;
+                }
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.exram[(addr - 0x5C00) as usize];
+                true
+            }
+            0x6000..=0xFFFF => {
+                let (is_rom, bank) = self.prg_bank(addr);
+                if is_rom {
+                    *mapped_addr = (bank % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+                } else {
+                    *mapped_addr = MAPPER_HANDLED;
+                    *data = self.prg_ram[((bank & 0x07) * 0x2000 + (addr & 0x1FFF) as u32) as usize];
+                }
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        // Everything the MMC5 claims is a register or board RAM, never PRG ROM
+        *mapped_addr = MAPPER_HANDLED;
+        match addr {
+            // Watch the PPU control and mask registers but let the PPU have them
+            0x2000..=0x3FFF => {
+                match addr & 0x0007 {
+                    0x0000 => self.sprite_8x16 = data & 0x20 > 0,
+                    0x0001 => {
+                        self.rendering = data & 0x18 > 0;
+                        if !self.rendering {
+                            self.in_frame = false;
+                            self.fetch_count = IDLE;
+                        }
+                    }
+                    _ => {}
+                }
+                false
+            }
+            0x5000..=0x5015 => { self.audio.write(addr, data); true }
+            0x5100 => { self.prg_mode = data & 0x03; true }
+            0x5101 => { self.chr_mode = data & 0x03; true }
+            0x5102 => { self.prg_ram_protect[0] = data & 0x03; true }
+            0x5103 => { self.prg_ram_protect[1] = data & 0x03; true }
+            0x5104 => { self.exram_mode = data & 0x03; true }
+            0x5105 => { self.nt_mapping = data; true }
+            0x5106 => { self.fill_tile = data; true }
+            0x5107 => { self.fill_color = data & 0x03; true }
+            0x5113..=0x5117 => { self.prg_regs[(addr - 0x5113) as usize] = data; true }
+            0x5120..=0x512B => {
+                self.chr_regs[(addr - 0x5120) as usize] = data as u16 | ((self.chr_upper as u16) << 8);
+                self.last_chr_set_b = addr >= 0x5128;
+                true
+            }
+            0x5130 => { self.chr_upper = data & 0x03; true }
+            0x5200 => { self.split_control = data; true }
+            0x5201 => { self.split_scroll = data; true }
+            0x5202 => { self.split_page = data; true }
+            0x5203 => { self.irq_compare = data; true }
+            0x5204 => { self.irq_enabled = data & 0x80 > 0; true }
+            0x5205 => { self.multiplicand = data; true }
+            0x5206 => { self.multiplier = data; true }
+            0x5C00..=0x5FFF => {
+                let offset = (addr - 0x5C00) as usize;
+                match self.exram_mode {
+                    // Only writable while the PPU is drawing, otherwise zero is stored
+                    0 | 1 => self.exram[offset] = if self.in_frame { data } else { 0 },
+                    2 => self.exram[offset] = data,
+                    _ => {}
+                }
+                true
+            }
+            0x6000..=0xFFFF => {
+                let (is_rom, bank) = self.prg_bank(addr);
+                if !is_rom && self.prg_ram_writable() {
+                    self.prg_ram[((bank & 0x07) * 0x2000 + (addr & 0x1FFF) as u32) as usize] = data;
+                }
+                true
+            }
+            _ => false
+        }
+    }
+
+    // Every fetch counts towards the scanline detection and the position within the line
+    fn ppu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if self.rendering {
+            self.detect_scanline(addr);
+        }
+        if addr >= 0x2000 && addr <= 0x3EFF {
+            self.track_name_table_fetch(addr);
+        }
+
+        let handled = self.ppu_map_peek(addr, mapped_addr, data);
+
+        if self.rendering {
+            self.fetch_count = self.fetch_count.saturating_add(1);
+        }
+        handled
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        return if addr <= 0x1FFF {
+            *mapped_addr = if self.in_split && self.in_frame && !self.fetching_sprites() {
+                // Split tiles use the $5202 page and the split's own fine y
+                let fine_y = self.split_y(self.fetch_column().map_or(false, |c| c.1)) & 0x07;
+                let addr = (addr & 0x0FF8) as u32 | fine_y;
+                ((self.split_page as u32 * 4) % self.chr_banks_1k) * 0x0400 + addr
+            } else if self.exram_mode == 1 && self.in_frame && !self.fetching_sprites() {
+                let bank = (self.ext_attribute & 0x3F) as u32 | ((self.chr_upper as u32) << 6);
+                ((bank * 4) % self.chr_banks_1k) * 0x0400 + (addr & 0x0FFF) as u32
+            } else {
+                self.chr_map(addr)
+            };
+            true
+        } else if addr <= 0x3EFF {
+            let handled = self.peek_name_table(addr, data);
+            if handled {
+                *mapped_addr = MAPPER_HANDLED;
+            }
+            handled
+        } else {
+            false
+        };
+    }
+
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x2000 && addr <= 0x3EFF {
+            let quadrant = ((addr & 0x0FFF) / 0x0400) as u8;
+            if (self.nt_mapping >> (quadrant * 2)) & 0x03 == 2 {
+                if self.exram_mode <= 1 {
+                    self.exram[(addr & 0x03FF) as usize] = data;
+                }
+                *mapped_addr = MAPPER_HANDLED;
+                return true;
+            }
+        }
+        return false;
+    }
+
+    fn ciram_page(&self, quadrant: usize) -> Option<usize> {
+        Some(((self.nt_mapping >> (quadrant * 2)) & 0x01) as usize)
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq_pending && self.irq_enabled
+    }
+
+    fn cpu_clock(&mut self) {
+        self.audio.clock();
+    }
+
+    fn audio_sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn reset(&mut self) {
+        self.prg_mode = 3;
+        self.prg_regs[4] = 0xFF;
+        self.in_frame = false;
+        self.fetch_count = IDLE;
+        self.irq_pending = false;
+        self.irq_enabled = false;
+    }
+}
@@ -0,0 +1,166 @@
+use crate::apu::sunsoft5b_audio::Sunsoft5bAudio;
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+
+/**
+ * Sunsoft FME-7 and 5B (mapper 69, Gimmick!, Batman: Return of the Joker)
+ *
+ * $8000  command select
+ * $A000  command parameter
+ *
+ *   $0-$7  1 KB CHR banks
+ *   $8     $6000 bank, bit 6 selects RAM and bit 7 enables it
+ *   $9-$B  8 KB PRG banks at $8000, $A000 and $C000
+ *   $C     mirroring
+ *   $D     IRQ control, bit 0 enables the IRQ and bit 7 the counter
+ *   $E/$F  IRQ counter low / high
+ *
+ * $C000 / $E000  5B audio register select and data
+ *
+ * $E000-$FFFF is fixed to the last 8 KB bank. The IRQ counter counts down every CPU cycle
+ * and fires when it wraps from $0000 to $FFFF.
+ */
+pub struct Mapper69 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+    command: u8,
+    chr_regs: [u8; 8],
+    prg_regs: [u8; 4],
+    mirror: Mirror,
+    prg_ram: Vec<u8>,
+    irq_enabled: bool,
+    counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Mapper69 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper69 {
+        Mapper69 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(1),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            command: 0,
+            chr_regs: [0; 8],
+            prg_regs: [0; 4],
+            mirror: Mirror::Vertical,
+            prg_ram: vec![0; 8192],
+            irq_enabled: false,
+            counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Sunsoft5bAudio::new()
+        }
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_regs[self.command as usize] = data,
+            0x8..=0xB => self.prg_regs[(self.command - 0x8) as usize] = data,
+            0xC => {
+                self.mirror = match data & 0x03 {
+                    0 => Mirror::Vertical,
+                    1 => Mirror::Horizontal,
+                    2 => Mirror::OneScreenLo,
+                    _ => Mirror::OneScreenHi
+                };
+            }
+            0xD => {
+                self.irq_enabled = data & 0x01 > 0;
+                self.counter_enabled = data & 0x80 > 0;
+                self.irq_pending = false;
+            }
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | ((data as u16) << 8)
+        }
+    }
+}
+
+impl Mapper for Mapper69 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            let bank = self.prg_regs[0];
+            if bank & 0x40 == 0 {
+                *mapped_addr = ((bank & 0x3F) as u32 % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+            } else if bank & 0x80 > 0 {
+                *mapped_addr = MAPPER_HANDLED;
+                *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            } else {
+                return false;
+            }
+            return true;
+        } else if addr >= 0x8000 {
+            let bank = match addr {
+                0x8000..=0x9FFF => self.prg_regs[1] as u32,
+                0xA000..=0xBFFF => self.prg_regs[2] as u32,
+                0xC000..=0xDFFF => self.prg_regs[3] as u32,
+                _ => self.prg_banks_8k - 1
+            };
+            *mapped_addr = ((bank & 0x3F) % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        match addr {
+            0x6000..=0x7FFF => {
+                if self.prg_regs[0] & 0xC0 != 0xC0 {
+                    return false;
+                }
+                *mapped_addr = MAPPER_HANDLED;
+                self.prg_ram[(addr & 0x1FFF) as usize] = data;
+                return true;
+            }
+            0x8000..=0x9FFF => self.command = data & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(data),
+            0xC000..=0xDFFF => self.audio.write_select(data),
+            0xE000..=0xFFFF => self.audio.write_data(data),
+            _ => {}
+        }
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            let bank = self.chr_regs[(addr / 0x0400) as usize] as u32;
+            *mapped_addr = (bank % self.chr_banks_1k) * 0x0400 + (addr & 0x03FF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.counter_enabled {
+            if self.irq_counter == 0 && self.irq_enabled {
+                self.irq_pending = true;
+            }
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+        }
+        self.audio.clock();
+    }
+
+    fn audio_sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn reset(&mut self) {
+        self.irq_enabled = false;
+        self.counter_enabled = false;
+        self.irq_pending = false;
+    }
+}
@@ -0,0 +1,161 @@
+use crate::cartridge::Mirror;
+use crate::mapper::Mapper;
+
+// MMC2 / MMC4 CHR latches. Each pattern table half has two 4 KB banks, one used while its
+// latch holds $FD and one while it holds $FE. The latch flips *after* the PPU fetches the
+// tile that triggers it, so the triggering tile is still drawn from the old bank.
+pub(crate) struct ChrLatch {
+    pub(crate) latch: [u8; 2],
+    pub(crate) banks_fd: [u8; 2],
+    pub(crate) banks_fe: [u8; 2],
+    banks_4k: u32,
+}
+
+impl ChrLatch {
+
+    pub(crate) fn new(n_chrbanks: u8) -> ChrLatch {
+        ChrLatch {
+            latch: [0xFE, 0xFE],
+            banks_fd: [0, 0],
+            banks_fe: [0, 0],
+            banks_4k: (n_chrbanks as u32 * 2).max(1)
+        }
+    }
+
+    pub(crate) fn map(&self, addr: u16) -> u32 {
+        let half = (addr >> 12) as usize & 0x01;
+        let bank = if self.latch[half] == 0xFD {
+            self.banks_fd[half]
+        } else {
+            self.banks_fe[half]
+        };
+        (bank as u32 % self.banks_4k) * 0x1000 + (addr & 0x0FFF) as u32
+    }
+
+    // MMC2 only watches the single address $0FD8 / $0FE8 for the left table, MMC4
+    // (and both chips for the right table) watch the whole 8 byte tile row
+    pub(crate) fn observe(&mut self, addr: u16, exact_left: bool) {
+        match addr {
+            0x0FD8 => self.latch[0] = 0xFD,
+            0x0FE8 => self.latch[0] = 0xFE,
+            0x0FD9..=0x0FDF if !exact_left => self.latch[0] = 0xFD,
+            0x0FE9..=0x0FEF if !exact_left => self.latch[0] = 0xFE,
+            0x1FD8..=0x1FDF => self.latch[1] = 0xFD,
+            0x1FE8..=0x1FEF => self.latch[1] = 0xFE,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, addr: u16, data: u8) -> bool {
+        match addr & 0xF000 {
+            0xB000 => self.banks_fd[0] = data & 0x1F,
+            0xC000 => self.banks_fe[0] = data & 0x1F,
+            0xD000 => self.banks_fd[1] = data & 0x1F,
+            0xE000 => self.banks_fe[1] = data & 0x1F,
+            _ => return false
+        }
+        true
+    }
+}
+
+/**
+ * MMC2 (Punch-Out!!)
+ *
+ * $8000-$9FFF  8 KB switchable PRG bank, selected by $A000
+ * $A000-$FFFF  the last three 8 KB PRG banks, fixed
+ * $0000-$0FFF  4 KB CHR bank chosen by latch 0 from $B000 / $C000
+ * $1000-$1FFF  4 KB CHR bank chosen by latch 1 from $D000 / $E000
+ * $F000        mirroring, 0 = vertical, 1 = horizontal
+ */
+pub struct Mapper9 {
+    n_prgbanks: u8,
+    n_chrbanks: u8,
+    prg_bank: u8,
+    chr: ChrLatch,
+    mirror: Mirror,
+}
+
+impl Mapper9 {
+
+    pub fn new(n_prgbanks: u8, n_chrbanks: u8) -> Mapper9 {
+        Mapper9 {
+            n_prgbanks,
+            n_chrbanks,
+            prg_bank: 0,
+            chr: ChrLatch::new(n_chrbanks),
+            mirror: Mirror::Vertical
+        }
+    }
+
+    fn banks_8k(&self) -> u32 {
+        (self.n_prgbanks as u32 * 2).max(1)
+    }
+
+    // Counting back from the last bank, wrapping round for ROMs smaller than 32 KB
+    fn bank_from_end(&self, from_end: u32) -> u32 {
+        let last = self.banks_8k();
+        (last - from_end % last) % last
+    }
+}
+
+impl Mapper for Mapper9 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr >= 0x8000 {
+            let last = self.banks_8k();
+            let bank = match addr {
+                0x8000..=0x9FFF => self.prg_bank as u32 % last,
+                0xA000..=0xBFFF => self.bank_from_end(3),
+                0xC000..=0xDFFF => self.bank_from_end(2),
+                _ => self.bank_from_end(1)
+            };
+            *mapped_addr = bank * 0x2000 + (addr & 0x1FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, _mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0xA000 {
+            match addr & 0xF000 {
+                0xA000 => self.prg_bank = data & 0x0F,
+                0xF000 => self.mirror = if data & 0x01 > 0 { Mirror::Horizontal } else { Mirror::Vertical },
+                _ => {
+                    self.chr.write_register(addr, data);
+                }
+            }
+        }
+        // Registers only, nothing is ever written into PRG memory
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            *mapped_addr = self.chr.map(addr);
+            return true;
+        }
+        return false;
+    }
+
+    // The latches switch after the tile that triggered them has been fetched
+    fn ppu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if self.ppu_map_peek(addr, mapped_addr, data) {
+            self.chr.observe(addr, true);
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr = ChrLatch::new(self.n_chrbanks);
+    }
+}
@@ -1,11 +1,101 @@
+pub mod fds;
 pub mod mapper0;
+pub mod mapper5;
+pub mod mapper9;
+pub mod mapper10;
+pub mod mapper19;
+pub mod mapper69;
+pub mod nsf;
+pub mod vrc_irq;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+#[cfg(test)]
+mod tests;
 
-use crate::State;
+use crate::cartridge::Mirror;
+use crate::mapper::mapper0::Mapper0;
+use crate::mapper::mapper5::Mapper5;
+use crate::mapper::mapper9::Mapper9;
+use crate::mapper::mapper10::Mapper10;
+use crate::mapper::mapper19::Mapper19;
+use crate::mapper::mapper69::Mapper69;
+use crate::mapper::vrc4::Vrc4;
+use crate::mapper::vrc6::Vrc6;
+use crate::mapper::vrc7::Vrc7;
 
-pub trait Mapper {
-    fn cpu_map_read(&mut self, state: &State, addr: u16, mapped_addr: &mut u32) -> bool;
-    fn cpu_map_write(&mut self, state: &State, addr: u16, mapped_addr: &mut u32) -> bool;
-    fn ppu_map_read(&mut self, state: State, addr: u16, mapped_addr: &mut u32) -> bool;
-    fn ppu_map_write(&mut self, state: State, addr: u16, mapped_addr: &mut u32) -> bool;
+// Returned through mapped_addr when the mapper supplied (or consumed) the data itself,
+// for example when the address hits RAM that lives on the cartridge board
+pub(crate) const MAPPER_HANDLED: u32 = 0xFFFF_FFFF;
+
+pub trait Mapper: Send {
+    /**
+     * What a read would give without its side effects, no IRQ acknowledged, no port address
+     * moved on and no latch switched, so debuggers can look at any address. Mappers whose
+     * reads have side effects override the matching read, the rest only implement these.
+     */
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool;
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool;
+
+    fn cpu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        self.cpu_map_peek(addr, mapped_addr, data)
+    }
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool;
+    fn ppu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        self.ppu_map_peek(addr, mapped_addr, data)
+    }
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool;
+
+    // Mirroring selected by the mapper, Hardware means use the solder pads from the header
+    fn mirror(&self) -> Mirror {
+        Mirror::Hardware
+    }
+
+    // CIRAM page (0 or 1) for a name table quadrant, for mappers that can route each
+    // quadrant on its own. None falls back to mirror()
+    fn ciram_page(&self, _quadrant: usize) -> Option<usize> {
+        None
+    }
+
+    // Level of the cartridge IRQ line
+    fn irq_state(&self) -> bool {
+        false
+    }
+
+    // Called once per CPU cycle, for mappers with cycle counting IRQs
+    fn cpu_clock(&mut self) {}
+
+    // Expansion audio output for the current CPU cycle, on the same scale as the 2A03
+    // mixer output so the APU can add the two together
+    fn audio_sample(&self) -> f32 {
+        0.0
+    }
+
+    // Disk System only, the number of disk sides and inserting one. None ejects the disk
+    fn disk_sides(&self) -> usize {
+        0
+    }
+
+    fn insert_disk(&mut self, _side: Option<usize>) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {}
 }
 
+// None for a mapper number that has no implementation yet
+pub fn create_mapper(mapper_id: u16, submapper: u8, n_prgbanks: u8, n_chrbanks: u8) -> Option<Box<dyn Mapper>> {
+    let mapper: Box<dyn Mapper> = match mapper_id {
+        0 => Box::new(Mapper0::new(n_prgbanks, n_chrbanks)),
+        5 => Box::new(Mapper5::new(n_prgbanks, n_chrbanks)),
+        9 => Box::new(Mapper9::new(n_prgbanks, n_chrbanks)),
+        10 => Box::new(Mapper10::new(n_prgbanks, n_chrbanks)),
+        21 | 22 | 23 | 25 => Box::new(Vrc4::new(mapper_id, submapper, n_prgbanks, n_chrbanks)),
+        24 | 26 => Box::new(Vrc6::new(mapper_id, n_prgbanks, n_chrbanks)),
+        19 => Box::new(Mapper19::new(n_prgbanks, n_chrbanks)),
+        69 => Box::new(Mapper69::new(n_prgbanks, n_chrbanks)),
+        85 => Box::new(Vrc7::new(submapper, n_prgbanks, n_chrbanks)),
+        _ => return None
+    };
+    return Some(mapper);
+}
@@ -0,0 +1,256 @@
+use crate::apu::fds_audio::FdsAudio;
+use crate::apu::mmc5_audio::Mmc5Audio;
+use crate::apu::n163_audio::N163Audio;
+use crate::apu::sunsoft5b_audio::Sunsoft5bAudio;
+use crate::apu::vrc6_audio::Vrc6Audio;
+use crate::apu::vrc7_audio::Vrc7Audio;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+use crate::nsf::{Nsf, CHIP_5B, CHIP_FDS, CHIP_MMC5, CHIP_N163, CHIP_VRC6, CHIP_VRC7};
+
+const BANK_SIZE: usize = 0x1000;
+
+/**
+ * The board an NSF player cartridge provides
+ *
+ * $5FF8-$5FFF  4 KB PRG banks at $8000-$FFFF
+ * $6000-$7FFF  PRG RAM
+ *
+ * Tunes that are not bank switched see their data at the load address. FDS tunes get
+ * RAM from $6000 to $FFFF instead, writing $5FF6-$5FFF copies a bank into it.
+ *
+ * Expansion chips flagged in the header sit at their usual addresses:
+ *
+ * $9000-$B002  VRC6
+ * $9010/$9030  VRC7
+ * $4040-$4092  FDS
+ * $5000-$5015  MMC5 audio, plus the multiplier at $5205/$5206 and ExRAM at $5C00
+ * $4800/$F800  N163
+ * $C000/$E000  Sunsoft 5B
+ */
+pub struct NsfMapper {
+    rom: Vec<u8>,
+    rom_banks: usize,
+    fds: bool,
+    bank_init: Vec<u8>,
+    banks: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chips: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+    exram: Vec<u8>,
+
+    vrc6: Vrc6Audio,
+    vrc7: Vrc7Audio,
+    fds_audio: FdsAudio,
+    mmc5: Mmc5Audio,
+    n163: N163Audio,
+    sunsoft5b: Sunsoft5bAudio,
+}
+
+impl NsfMapper {
+
+    pub fn new(nsf: &Nsf) -> NsfMapper {
+        let fds = nsf.chips & CHIP_FDS > 0;
+        // FDS tunes have ten banks starting at $6000, everything else eight at $8000
+        let (base, slots) = if fds { (0x6000, 10) } else { (0x8000, 8) };
+
+        let (padding, bank_init) = if nsf.is_bank_switched() {
+            let mut bank_init = nsf.bank_init.to_vec();
+            if fds {
+                bank_init.insert(0, nsf.bank_init[7]);
+                bank_init.insert(0, nsf.bank_init[6]);
+            }
+            ((nsf.load_addr & 0x0FFF) as usize, bank_init)
+        } else {
+            ((nsf.load_addr as usize).saturating_sub(base), (0..slots as u8).collect())
+        };
+
+        let mut rom = vec![0; padding];
+        rom.extend_from_slice(&nsf.data);
+        let rom_banks = rom.len().div_ceil(BANK_SIZE).max(1);
+        rom.resize(rom_banks * BANK_SIZE, 0);
+
+        let mut mapper = NsfMapper {
+            rom,
+            rom_banks,
+            fds,
+            banks: bank_init.clone(),
+            bank_init,
+            prg_ram: vec![0; if fds { 0xA000 } else { 0x2000 }],
+            chips: nsf.chips,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            exram: vec![0; 1024],
+            vrc6: Vrc6Audio::new(),
+            vrc7: Vrc7Audio::new(),
+            fds_audio: FdsAudio::new(),
+            mmc5: Mmc5Audio::new(),
+            n163: N163Audio::new(),
+            sunsoft5b: Sunsoft5bAudio::new()
+        };
+        mapper.reset();
+        mapper
+    }
+
+    fn has(&self, chip: u8) -> bool {
+        self.chips & chip > 0
+    }
+
+    fn switch_bank(&mut self, slot: usize, bank: u8) {
+        self.banks[slot] = bank;
+        if self.fds {
+            let source = (bank as usize % self.rom_banks) * BANK_SIZE;
+            let target = slot * BANK_SIZE;
+            self.prg_ram[target..target + BANK_SIZE].copy_from_slice(&self.rom[source..source + BANK_SIZE]);
+        }
+    }
+
+    fn read_rom(&self, addr: u16) -> u8 {
+        let bank = self.banks[((addr - 0x8000) as usize) / BANK_SIZE] as usize % self.rom_banks;
+        self.rom[bank * BANK_SIZE + (addr as usize & 0x0FFF)]
+    }
+
+    // Chip registers that sit under ROM, the writes never reach it
+    fn write_chip(&mut self, addr: u16, data: u8) {
+        if self.has(CHIP_N163) && addr >= 0xF800 {
+            self.n163.write_address(data);
+            return;
+        }
+        if self.has(CHIP_VRC7) && addr & 0xF030 == 0x9010 {
+            self.vrc7.write_select(data);
+            return;
+        }
+        if self.has(CHIP_VRC7) && addr & 0xF030 == 0x9030 {
+            self.vrc7.write_data(data);
+            return;
+        }
+        if self.has(CHIP_VRC6) {
+            match addr {
+                0x9000..=0x9003 => self.vrc6.write(0, addr & 0x03, data),
+                0xA000..=0xA002 => self.vrc6.write(1, addr & 0x03, data),
+                0xB000..=0xB002 => self.vrc6.write(2, addr & 0x03, data),
+                _ => {}
+            }
+        }
+        if self.has(CHIP_5B) {
+            match addr {
+                0xC000..=0xDFFF => self.sunsoft5b.write_select(data),
+                0xE000..=0xFFFF => self.sunsoft5b.write_data(data),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Mapper for NsfMapper {
+
+    fn cpu_map_read(&mut self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x4800 && addr <= 0x4FFF && self.has(CHIP_N163) {
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.n163.read_data();
+            return true;
+        }
+        return self.cpu_map_peek(addr, mapped_addr, data);
+    }
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        *mapped_addr = MAPPER_HANDLED;
+        match addr {
+            0x4040..=0x407F | 0x4090 | 0x4092 if self.has(CHIP_FDS) => {
+                match self.fds_audio.read(addr) {
+                    Some(value) => { *data = value; true }
+                    None => false
+                }
+            }
+            0x4800..=0x4FFF if self.has(CHIP_N163) => { *data = self.n163.peek_data(); true }
+            0x5015 if self.has(CHIP_MMC5) => { *data = self.mmc5.read_status(); true }
+            0x5205 if self.has(CHIP_MMC5) => {
+                *data = (self.multiplicand as u16 * self.multiplier as u16) as u8;
+                true
+            }
+            0x5206 if self.has(CHIP_MMC5) => {
+                *data = ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8;
+                true
+            }
+            0x5C00..=0x5FF5 if self.has(CHIP_MMC5) => { *data = self.exram[(addr - 0x5C00) as usize]; true }
+            0x6000..=0xFFFF if self.fds => { *data = self.prg_ram[(addr - 0x6000) as usize]; true }
+            0x6000..=0x7FFF => { *data = self.prg_ram[(addr - 0x6000) as usize]; true }
+            0x8000..=0xFFFF => { *data = self.read_rom(addr); true }
+            _ => false
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        *mapped_addr = MAPPER_HANDLED;
+        match addr {
+            0x4040..=0x408A if self.has(CHIP_FDS) => { self.fds_audio.write(addr, data); true }
+            0x4800..=0x4FFF if self.has(CHIP_N163) => { self.n163.write_data(data); true }
+            0x5000..=0x5015 if self.has(CHIP_MMC5) => { self.mmc5.write(addr, data); true }
+            0x5205 if self.has(CHIP_MMC5) => { self.multiplicand = data; true }
+            0x5206 if self.has(CHIP_MMC5) => { self.multiplier = data; true }
+            0x5C00..=0x5FF5 if self.has(CHIP_MMC5) => { self.exram[(addr - 0x5C00) as usize] = data; true }
+            0x5FF6..=0x5FF7 if self.fds => { self.switch_bank((addr - 0x5FF6) as usize, data); true }
+            0x5FF8..=0x5FFF => {
+                let slot = (addr - 0x5FF8) as usize + if self.fds { 2 } else { 0 };
+                self.switch_bank(slot, data);
+                true
+            }
+            0x6000..=0xFFFF if self.fds => {
+                self.write_chip(addr, data);
+                self.prg_ram[(addr - 0x6000) as usize] = data;
+                true
+            }
+            0x6000..=0x7FFF => { self.prg_ram[(addr - 0x6000) as usize] = data; true }
+            0x8000..=0xFFFF => { self.write_chip(addr, data); true }
+            _ => false
+        }
+    }
+
+    fn ppu_map_peek(&self, _addr: u16, _mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.has(CHIP_VRC6) { self.vrc6.clock(); }
+        if self.has(CHIP_VRC7) { self.vrc7.clock(); }
+        if self.has(CHIP_FDS) { self.fds_audio.clock(); }
+        if self.has(CHIP_MMC5) { self.mmc5.clock(); }
+        if self.has(CHIP_N163) { self.n163.clock(); }
+        if self.has(CHIP_5B) { self.sunsoft5b.clock(); }
+    }
+
+    fn audio_sample(&self) -> f32 {
+        let mut sample = 0.0;
+        if self.has(CHIP_VRC6) { sample += self.vrc6.output(); }
+        if self.has(CHIP_VRC7) { sample += self.vrc7.output(); }
+        if self.has(CHIP_FDS) { sample += self.fds_audio.output(); }
+        if self.has(CHIP_MMC5) { sample += self.mmc5.output(); }
+        if self.has(CHIP_N163) { sample += self.n163.output(); }
+        if self.has(CHIP_5B) { sample += self.sunsoft5b.output(); }
+        sample
+    }
+
+    // Back to the state the tune's INIT routine expects
+    fn reset(&mut self) {
+        for byte in self.prg_ram.iter_mut() {
+            *byte = 0;
+        }
+        for slot in 0..self.bank_init.len() {
+            self.switch_bank(slot, self.bank_init[slot]);
+        }
+        self.multiplicand = 0xFF;
+        self.multiplier = 0xFF;
+        self.exram = vec![0; 1024];
+        self.vrc6 = Vrc6Audio::new();
+        self.vrc7 = Vrc7Audio::new();
+        self.fds_audio = FdsAudio::new();
+        self.mmc5 = Mmc5Audio::new();
+        self.n163 = N163Audio::new();
+        self.sunsoft5b = Sunsoft5bAudio::new();
+    }
+}
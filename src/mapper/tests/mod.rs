@@ -0,0 +1,846 @@
+use crate::cartridge::{Cartridge, Mirror};
+use crate::apu::pulse_level;
+use crate::bus::{mem_peek, mem_read, mem_write};
+use crate::ppu::{ppu_peek, ppu_read, ppu_write};
+use crate::state::State;
+
+// Builds an iNES image where every 8 KB PRG bank and every 4 KB CHR bank is filled with its own index
+fn make_rom(mapper_id: u8, prg_chunks: u8, chr_chunks: u8) -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, prg_chunks, chr_chunks, (mapper_id & 0x0F) << 4, mapper_id & 0xF0, 0, 0, 0, 0, 0, 0, 0, 0];
+    for bank in 0..(prg_chunks as usize * 2) {
+        rom.extend(vec![bank as u8; 0x2000]);
+    }
+    for bank in 0..(chr_chunks as usize * 2) {
+        rom.extend(vec![bank as u8; 0x1000]);
+    }
+    rom
+}
+
+// Same image with an NES 2.0 header carrying a submapper
+fn make_rom_nes2(mapper_id: u8, submapper: u8, prg_chunks: u8, chr_chunks: u8) -> Vec<u8> {
+    let mut rom = make_rom(mapper_id, prg_chunks, chr_chunks);
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    rom
+}
+
+fn make_state(rom: Vec<u8>) -> State {
+    let mut state = State::new();
+    state.connect_cartridge(Some(Cartridge::from_bytes(&rom)));
+    state
+}
+
+#[test]
+fn test_nrom_prg_mirror_and_ram() {
+    let mut state = make_state(make_rom(0, 1, 1));
+
+    assert_eq!(mem_read(&mut state, 0xA000), 1);
+    assert_eq!(mem_read(&mut state, 0xE000), 1);
+
+    mem_write(&mut state, 0x6000, 0x80);
+    mem_write(&mut state, 0x7FFF, 0x42);
+    assert_eq!(mem_read(&mut state, 0x6000), 0x80);
+    assert_eq!(mem_read(&mut state, 0x7FFF), 0x42);
+}
+
+#[test]
+fn test_mmc2_prg_banks() {
+    let mut state = make_state(make_rom(9, 8, 16));
+
+    assert_eq!(mem_read(&mut state, 0x8000), 0);
+    assert_eq!(mem_read(&mut state, 0xA000), 13);
+    assert_eq!(mem_read(&mut state, 0xC000), 14);
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+
+    mem_write(&mut state, 0xA000, 5);
+    assert_eq!(mem_read(&mut state, 0x8000), 5);
+    assert_eq!(mem_read(&mut state, 0x9FFF), 5);
+    assert_eq!(mem_read(&mut state, 0xA000), 13);
+
+    // 16 KB of PRG mirrors into the fixed banks instead of running off the start
+    let mut state = make_state(make_rom(9, 1, 16));
+    assert_eq!(mem_read(&mut state, 0xA000), 1);
+    assert_eq!(mem_read(&mut state, 0xC000), 0);
+    assert_eq!(mem_read(&mut state, 0xE000), 1);
+}
+
+#[test]
+fn test_mmc2_chr_latch_switches_after_fetch() {
+    let mut state = make_state(make_rom(9, 8, 16));
+
+    mem_write(&mut state, 0xB000, 2);
+    mem_write(&mut state, 0xC000, 3);
+    mem_write(&mut state, 0xD000, 4);
+    mem_write(&mut state, 0xE000, 5);
+
+    // Latches power up holding $FE
+    assert_eq!(ppu_read(&mut state, 0x0000), 3);
+    assert_eq!(ppu_read(&mut state, 0x1000), 5);
+
+    // The triggering fetch still comes from the old bank
+    assert_eq!(ppu_read(&mut state, 0x0FD8), 3);
+    assert_eq!(ppu_read(&mut state, 0x0000), 2);
+
+    // MMC2 only reacts to exactly $0FD8 / $0FE8 on the left table
+    assert_eq!(ppu_read(&mut state, 0x0FE9), 2);
+    assert_eq!(ppu_read(&mut state, 0x0000), 2);
+    assert_eq!(ppu_read(&mut state, 0x0FE8), 2);
+    assert_eq!(ppu_read(&mut state, 0x0000), 3);
+
+    // The right table reacts to the whole tile row
+    assert_eq!(ppu_read(&mut state, 0x1FDB), 5);
+    assert_eq!(ppu_read(&mut state, 0x1000), 4);
+    assert_eq!(ppu_read(&mut state, 0x1FEF), 4);
+    assert_eq!(ppu_read(&mut state, 0x1000), 5);
+}
+
+// Points $2006 at addr and reads $2007 once, which fetches through the cartridge
+fn read_ppu_data(state: &mut State, addr: u16) -> u8 {
+    mem_write(state, 0x2006, (addr >> 8) as u8);
+    mem_write(state, 0x2006, addr as u8);
+    return mem_read(state, 0x2007);
+}
+
+#[test]
+fn test_mmc2_latch_through_ppu_data() {
+    let mut state = make_state(make_rom(9, 8, 16));
+    mem_write(&mut state, 0xB000, 2);
+    mem_write(&mut state, 0xC000, 3);
+
+    // Reads come out one late, the buffered byte is from the bank the latch picked before
+    read_ppu_data(&mut state, 0x0FD8);
+    assert_eq!(mem_read(&mut state, 0x2007), 3);
+    assert_eq!(read_ppu_data(&mut state, 0x0000), 2);
+    assert_eq!(mem_read(&mut state, 0x2007), 2);
+
+    read_ppu_data(&mut state, 0x0FE8);
+    read_ppu_data(&mut state, 0x0000);
+    assert_eq!(mem_read(&mut state, 0x2007), 3);
+
+    // Peeking the data port leaves the latch alone
+    mem_write(&mut state, 0x2006, 0x0F);
+    mem_write(&mut state, 0x2006, 0xD8);
+    mem_peek(&state, 0x2007);
+    assert_eq!(read_ppu_data(&mut state, 0x0000), 3);
+}
+
+#[test]
+fn test_mmc2_mirroring() {
+    let mut state = make_state(make_rom(9, 8, 16));
+
+    mem_write(&mut state, 0xF000, 0);
+    assert_eq!(state.get_cartridge().mirror(), Mirror::Vertical);
+    mem_write(&mut state, 0xF000, 1);
+    assert_eq!(state.get_cartridge().mirror(), Mirror::Horizontal);
+}
+
+#[test]
+fn test_mmc4_prg_banks_and_ram() {
+    let mut state = make_state(make_rom(10, 8, 16));
+
+    assert_eq!(mem_read(&mut state, 0x8000), 0);
+    assert_eq!(mem_read(&mut state, 0xC000), 14);
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+
+    mem_write(&mut state, 0xA000, 3);
+    assert_eq!(mem_read(&mut state, 0x8000), 6);
+    assert_eq!(mem_read(&mut state, 0xA000), 7);
+
+    mem_write(&mut state, 0x6123, 0x42);
+    assert_eq!(mem_read(&mut state, 0x6123), 0x42);
+}
+
+#[test]
+fn test_mmc4_left_latch_uses_tile_row() {
+    let mut state = make_state(make_rom(10, 8, 16));
+
+    mem_write(&mut state, 0xB000, 6);
+    mem_write(&mut state, 0xC000, 7);
+
+    assert_eq!(ppu_read(&mut state, 0x0FDD), 7);
+    assert_eq!(ppu_read(&mut state, 0x0000), 6);
+    assert_eq!(ppu_read(&mut state, 0x0FEA), 6);
+    assert_eq!(ppu_read(&mut state, 0x0000), 7);
+}
+
+// Replays the fetches the PPU makes for one rendered scanline, returning the background
+// and sprite pattern bytes it got back. Tiles 0 and 1 were prefetched by the previous line.
+fn fetch_line(state: &mut State, row: u16) -> (Vec<u8>, Vec<u8>) {
+    let mut background = vec![];
+    let mut sprites = vec![];
+    let nt = |col: u16, row: u16| 0x2000 + (row % 30) * 32 + (col % 32);
+
+    for col in 2..34 {
+        ppu_read(state, nt(col, row));
+        ppu_read(state, 0x23C0);
+        background.push(ppu_read(state, 0x0000));
+        ppu_read(state, 0x0008);
+    }
+    for _ in 0..8 {
+        ppu_read(state, nt(0, row));
+        ppu_read(state, nt(0, row));
+        sprites.push(ppu_read(state, 0x0000));
+        ppu_read(state, 0x0008);
+    }
+    for col in 0..2 {
+        ppu_read(state, nt(col, row + 1));
+        ppu_read(state, 0x23C0);
+        ppu_read(state, 0x0000);
+        ppu_read(state, 0x0008);
+    }
+    ppu_read(state, nt(2, row + 1));
+    ppu_read(state, nt(2, row + 1));
+    (background, sprites)
+}
+
+#[test]
+fn test_mmc5_prg_modes() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    // Power on is mode 3 with $5117 pointing at the last bank
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+
+    mem_write(&mut state, 0x5114, 0x80 | 4);
+    mem_write(&mut state, 0x5115, 0x80 | 5);
+    mem_write(&mut state, 0x5116, 0x80 | 6);
+    assert_eq!(mem_read(&mut state, 0x8000), 4);
+    assert_eq!(mem_read(&mut state, 0xA000), 5);
+    assert_eq!(mem_read(&mut state, 0xC000), 6);
+
+    mem_write(&mut state, 0x5100, 1);
+    mem_write(&mut state, 0x5115, 0x80 | 5);
+    assert_eq!(mem_read(&mut state, 0x8000), 4);
+    assert_eq!(mem_read(&mut state, 0xA000), 5);
+    assert_eq!(mem_read(&mut state, 0xC000), 14);
+
+    mem_write(&mut state, 0x5100, 0);
+    mem_write(&mut state, 0x5117, 0x07);
+    assert_eq!(mem_read(&mut state, 0x8000), 4);
+    assert_eq!(mem_read(&mut state, 0xE000), 7);
+}
+
+#[test]
+fn test_mmc5_prg_ram_protect() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x6000, 0x12);
+    assert_eq!(mem_read(&mut state, 0x6000), 0x00);
+
+    mem_write(&mut state, 0x5102, 0x02);
+    mem_write(&mut state, 0x5103, 0x01);
+    mem_write(&mut state, 0x6000, 0x12);
+    assert_eq!(mem_read(&mut state, 0x6000), 0x12);
+
+    // RAM banked into the $8000 window
+    mem_write(&mut state, 0x5114, 0x00);
+    assert_eq!(mem_read(&mut state, 0x8000), 0x12);
+}
+
+#[test]
+fn test_mmc5_multiplier() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x5205, 200);
+    mem_write(&mut state, 0x5206, 150);
+    let result = mem_read(&mut state, 0x5205) as u16 | ((mem_read(&mut state, 0x5206) as u16) << 8);
+    assert_eq!(result, 30000);
+
+    // Register writes must not reach PRG ROM
+    assert_eq!(state.get_cartridge().v_prg_memory[0], 0);
+}
+
+#[test]
+fn test_mmc5_exram_and_name_tables() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x5104, 2);
+    mem_write(&mut state, 0x5C05, 0x11);
+    assert_eq!(mem_read(&mut state, 0x5C05), 0x11);
+
+    // Quadrants 0 and 2 on CIRAM 0, 1 and 3 on CIRAM 1
+    mem_write(&mut state, 0x5105, 0b01_00_01_00);
+    ppu_write(&mut state, 0x2400, 7);
+    assert_eq!(ppu_read(&mut state, 0x2C00), 7);
+    assert_eq!(ppu_read(&mut state, 0x2000), 0);
+
+    // ExRAM as a name table, fill mode on the last quadrant
+    mem_write(&mut state, 0x5104, 0);
+    mem_write(&mut state, 0x5105, 0b11_00_10_00);
+    mem_write(&mut state, 0x5106, 0x42);
+    mem_write(&mut state, 0x5107, 0x02);
+    assert_eq!(ppu_read(&mut state, 0x2405), 0x11);
+    assert_eq!(ppu_read(&mut state, 0x2C10), 0x42);
+    assert_eq!(ppu_read(&mut state, 0x2FC0), 0xAA);
+}
+
+#[test]
+fn test_mmc5_scanline_irq() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x2001, 0x18);
+    mem_write(&mut state, 0x5203, 3);
+    mem_write(&mut state, 0x5204, 0x80);
+
+    // Priming fetches so the first line is detected
+    ppu_read(&mut state, 0x2002);
+    ppu_read(&mut state, 0x2002);
+
+    for row in 0..3 {
+        fetch_line(&mut state, row);
+        assert!(!state.get_cartridge().mapper.irq_state());
+    }
+    fetch_line(&mut state, 3);
+    assert!(state.get_cartridge().mapper.irq_state());
+
+    assert_eq!(mem_read(&mut state, 0x5204), 0xC0);
+    assert_eq!(mem_read(&mut state, 0x5204), 0x40);
+    assert!(!state.get_cartridge().mapper.irq_state());
+
+    // Fetching the NMI vector ends the frame
+    mem_read(&mut state, 0xFFFA);
+    assert_eq!(mem_read(&mut state, 0x5204), 0x00);
+}
+
+#[test]
+fn test_peeks_leave_mapper_state_alone() {
+    // MMC2 latch
+    let mut state = make_state(make_rom(9, 8, 16));
+    assert_eq!(ppu_peek(&state, 0x0FD8), 0);
+    assert_eq!(ppu_read(&mut state, 0x0FD8), 0);
+    assert_eq!(ppu_peek(&state, 0x0FE8), 0);
+    assert_eq!(ppu_read(&mut state, 0x0FD8), 0);
+
+    // MMC5 IRQ status
+    let mut state = make_state(make_rom(5, 8, 8));
+    mem_write(&mut state, 0x2001, 0x18);
+    mem_write(&mut state, 0x5203, 1);
+    mem_write(&mut state, 0x5204, 0x80);
+    ppu_read(&mut state, 0x2002);
+    ppu_read(&mut state, 0x2002);
+    fetch_line(&mut state, 0);
+    fetch_line(&mut state, 1);
+    assert_eq!(mem_peek(&state, 0x5204), 0xC0);
+    assert_eq!(mem_peek(&state, 0xFFFA), mem_read(&mut state, 0xFFFA));
+    assert_eq!(mem_peek(&state, 0x5204), 0x80);
+    assert!(state.get_cartridge().mapper.irq_state());
+    assert_eq!(mem_read(&mut state, 0x5204), 0x80);
+    assert!(!state.get_cartridge().mapper.irq_state());
+
+    // N163 sound RAM port with auto increment
+    let mut state = make_state(make_rom(19, 8, 4));
+    mem_write(&mut state, 0xF800, 0x80);
+    mem_write(&mut state, 0x4800, 0x11);
+    mem_write(&mut state, 0x4800, 0x22);
+    mem_write(&mut state, 0xF800, 0x80);
+    assert_eq!(mem_peek(&state, 0x4800), 0x11);
+    assert_eq!(mem_peek(&state, 0x4800), 0x11);
+    assert_eq!(mem_read(&mut state, 0x4800), 0x11);
+    assert_eq!(mem_read(&mut state, 0x4800), 0x22);
+}
+
+#[test]
+fn test_mmc5_8x16_sprite_chr_sets() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x2001, 0x18);
+    mem_write(&mut state, 0x5101, 3);
+    mem_write(&mut state, 0x5120, 4);
+    mem_write(&mut state, 0x5128, 12);
+
+    // 8x8 sprites use whichever set was written last
+    assert_eq!(ppu_read(&mut state, 0x0000), 3);
+
+    mem_write(&mut state, 0x2000, 0x20);
+    ppu_read(&mut state, 0x2002);
+    ppu_read(&mut state, 0x2002);
+    fetch_line(&mut state, 0);
+    let (background, sprites) = fetch_line(&mut state, 1);
+    assert!(background.iter().all(|b| *b == 3));
+    assert!(sprites.iter().all(|b| *b == 1));
+}
+
+#[test]
+fn test_mmc5_extended_attributes() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x5104, 2);
+    mem_write(&mut state, 0x5C00 + 32 + 2, 0xC5);
+    mem_write(&mut state, 0x5104, 1);
+    mem_write(&mut state, 0x2001, 0x18);
+
+    ppu_read(&mut state, 0x2002);
+    ppu_read(&mut state, 0x2002);
+    fetch_line(&mut state, 0);
+
+    // First tile of row 1 is column 2, which picked up the ExRAM byte
+    assert_eq!(ppu_read(&mut state, 0x2000 + 32 + 2), 0x00);
+    assert_eq!(ppu_read(&mut state, 0x23C0), 0xFF);
+    assert_eq!(ppu_read(&mut state, 0x0000), 5);
+}
+
+#[test]
+fn test_mmc5_vertical_split() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x5104, 2);
+    mem_write(&mut state, 0x5C02, 0x77);
+    mem_write(&mut state, 0x5104, 0);
+    mem_write(&mut state, 0x5200, 0x80 | 4);
+    mem_write(&mut state, 0x5202, 6);
+    mem_write(&mut state, 0x2001, 0x18);
+
+    ppu_read(&mut state, 0x2002);
+    ppu_read(&mut state, 0x2002);
+    // Completes the match, line 0 column 2 is inside the split
+    assert_eq!(ppu_read(&mut state, 0x2002), 0x77);
+    ppu_read(&mut state, 0x23C0);
+    assert_eq!(ppu_read(&mut state, 0x0000), 6);
+    ppu_read(&mut state, 0x0008);
+
+    for col in 3..5 {
+        ppu_read(&mut state, 0x2000 + col);
+        ppu_read(&mut state, 0x23C0);
+        ppu_read(&mut state, 0x0000);
+        ppu_read(&mut state, 0x0008);
+    }
+
+    // Column 5 is past the threshold and comes from CIRAM again
+    assert_eq!(ppu_read(&mut state, 0x2005), 0x00);
+    ppu_read(&mut state, 0x23C0);
+    assert_eq!(ppu_read(&mut state, 0x0000), 0);
+}
+
+fn clock_mapper(state: &mut State, cycles: u32) {
+    let cart = state.get_cartridge_mut();
+    for _ in 0..cycles {
+        cart.mapper.cpu_clock();
+    }
+}
+
+#[test]
+fn test_vrc4_submapper_address_lines() {
+    // VRC4b: register select lines are A1 then A0
+    let mut state = make_state(make_rom_nes2(25, 1, 8, 4));
+
+    mem_write(&mut state, 0x8000, 3);
+    mem_write(&mut state, 0xA000, 4);
+    assert_eq!(mem_read(&mut state, 0x8000), 3);
+    assert_eq!(mem_read(&mut state, 0xA000), 4);
+    assert_eq!(mem_read(&mut state, 0xC000), 14);
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+
+    // $9002 sits on A0 for this board and swaps $8000 with $C000
+    mem_write(&mut state, 0x9001, 0x02);
+    assert_eq!(mem_read(&mut state, 0x8000), 14);
+    assert_eq!(mem_read(&mut state, 0xC000), 3);
+
+    // CHR bank 0 low nibble at $B000, high nibble at $B002
+    mem_write(&mut state, 0xB000, 0x02);
+    mem_write(&mut state, 0xB002, 0x01);
+    assert_eq!(ppu_read(&mut state, 0x0000), 18 / 4);
+
+    mem_write(&mut state, 0x9000, 0x03);
+    assert_eq!(state.get_cartridge().mirror(), Mirror::OneScreenHi);
+}
+
+#[test]
+fn test_vrc4_ines_listens_on_both_lines() {
+    let mut state = make_state(make_rom(21, 8, 4));
+
+    // VRC4a ($B002) and VRC4c ($B040) wiring both reach the high nibble of CHR bank 0
+    mem_write(&mut state, 0xB000, 0x00);
+    mem_write(&mut state, 0xB002, 0x01);
+    assert_eq!(ppu_read(&mut state, 0x0000), 16 / 4);
+    mem_write(&mut state, 0xB040, 0x00);
+    assert_eq!(ppu_read(&mut state, 0x0000), 0);
+}
+
+#[test]
+fn test_vrc2_chr_ignores_low_bit() {
+    let mut state = make_state(make_rom(22, 8, 4));
+
+    // VRC2a drops bit 0 of the bank number
+    mem_write(&mut state, 0xB000, 0x09);
+    assert_eq!(ppu_read(&mut state, 0x0000), 4 / 4);
+    mem_write(&mut state, 0xC000, 0x0F);
+    assert_eq!(ppu_read(&mut state, 0x0800), 7 / 4);
+}
+
+#[test]
+fn test_vrc_irq_cycle_mode() {
+    let mut state = make_state(make_rom_nes2(25, 1, 8, 4));
+
+    // Latch $FD, enabled in cycle mode: $FD, $FE, $FF, overflow
+    mem_write(&mut state, 0xF000, 0x0D);
+    mem_write(&mut state, 0xF002, 0x0F);
+    mem_write(&mut state, 0xF001, 0x06);
+
+    clock_mapper(&mut state, 2);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 1);
+    assert!(state.get_cartridge().mapper.irq_state());
+
+    // Acknowledge copies the A bit, which was clear, so the counter stops
+    mem_write(&mut state, 0xF003, 0x00);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 1000);
+    assert!(!state.get_cartridge().mapper.irq_state());
+}
+
+#[test]
+fn test_vrc_irq_scanline_mode() {
+    let mut state = make_state(make_rom(24, 8, 4));
+
+    // Latch $FF overflows on the first scanline, roughly 113.67 CPU cycles
+    mem_write(&mut state, 0xF000, 0xFF);
+    mem_write(&mut state, 0xF001, 0x02);
+
+    clock_mapper(&mut state, 113);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 1);
+    assert!(state.get_cartridge().mapper.irq_state());
+}
+
+#[test]
+fn test_vrc6_prg_layout() {
+    let mut state = make_state(make_rom(24, 8, 4));
+
+    mem_write(&mut state, 0x8000, 2);
+    mem_write(&mut state, 0xC000, 9);
+    assert_eq!(mem_read(&mut state, 0x8000), 4);
+    assert_eq!(mem_read(&mut state, 0xA000), 5);
+    assert_eq!(mem_read(&mut state, 0xC000), 9);
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+}
+
+#[test]
+fn test_vrc6_swapped_lines_and_chr_modes() {
+    // Mapper 26 swaps A0 and A1, so the banking register $B003 is still $B003
+    // but CHR register R1 moves from $D001 to $D002
+    let mut state = make_state(make_rom(26, 8, 4));
+
+    mem_write(&mut state, 0xD000, 4);
+    mem_write(&mut state, 0xD002, 9);
+    mem_write(&mut state, 0xB003, 0x80);
+    assert_eq!(ppu_read(&mut state, 0x0000), 4 / 4);
+    assert_eq!(ppu_read(&mut state, 0x0400), 9 / 4);
+
+    // PRG RAM is only there with bit 7 of $B003 set
+    mem_write(&mut state, 0x6000, 0x42);
+    assert_eq!(mem_read(&mut state, 0x6000), 0x42);
+
+    // Mode 1 uses R0-R3 as 2 KB banks, A10 picks the half
+    mem_write(&mut state, 0xB003, 0x81);
+    mem_write(&mut state, 0xD002, 13);
+    assert_eq!(ppu_read(&mut state, 0x0800), 12 / 4);
+    assert_eq!(ppu_read(&mut state, 0x0C00), 13 / 4);
+}
+
+#[test]
+fn test_vrc7_registers() {
+    // VRC7a takes the second register of each pair from A4
+    let mut state = make_state(make_rom_nes2(85, 2, 8, 4));
+
+    mem_write(&mut state, 0x8000, 3);
+    mem_write(&mut state, 0x8010, 5);
+    mem_write(&mut state, 0x9000, 7);
+    assert_eq!(mem_read(&mut state, 0x8000), 3);
+    assert_eq!(mem_read(&mut state, 0xA000), 5);
+    assert_eq!(mem_read(&mut state, 0xC000), 7);
+    assert_eq!(mem_read(&mut state, 0xE000), 15);
+
+    mem_write(&mut state, 0xD010, 20);
+    assert_eq!(ppu_read(&mut state, 0x1C00), 20 / 4);
+
+    mem_write(&mut state, 0xE000, 0x01);
+    assert_eq!(state.get_cartridge().mirror(), Mirror::Horizontal);
+}
+
+// Loudest expansion audio sample over a number of CPU cycles
+fn audio_peak(state: &mut State, cycles: u32) -> f32 {
+    let cart = state.get_cartridge_mut();
+    let mut peak: f32 = 0.0;
+    for _ in 0..cycles {
+        cart.mapper.cpu_clock();
+        peak = peak.max(cart.mapper.audio_sample().abs());
+    }
+    peak
+}
+
+#[test]
+fn test_vrc6_audio() {
+    let mut state = make_state(make_rom(24, 8, 4));
+    assert_eq!(audio_peak(&mut state, 1000), 0.0);
+
+    // Pulse 1 at volume 15, 50% duty
+    mem_write(&mut state, 0x9000, 0x7F);
+    mem_write(&mut state, 0x9001, 0x40);
+    mem_write(&mut state, 0x9002, 0x80);
+    let pulse = audio_peak(&mut state, 2000);
+    assert!((pulse - pulse_level(15.0)).abs() < 0.0001);
+
+    // Halting through the frequency control freezes the channel
+    mem_write(&mut state, 0x9003, 0x01);
+    mem_write(&mut state, 0x9002, 0x00);
+    assert_eq!(audio_peak(&mut state, 1000), 0.0);
+
+    // The sawtooth reaches 6 x 42 >> 3 = 31
+    mem_write(&mut state, 0x9003, 0x00);
+    mem_write(&mut state, 0xB000, 42);
+    mem_write(&mut state, 0xB001, 0x10);
+    mem_write(&mut state, 0xB002, 0x80);
+    let saw = audio_peak(&mut state, 20000);
+    assert!((saw - 31.0 * pulse_level(15.0) / 15.0).abs() < 0.0001);
+}
+
+#[test]
+fn test_sunsoft5b_audio() {
+    let mut state = make_state(make_rom(69, 8, 4));
+
+    // Tone A at full volume, everything else off in the mixer
+    for (register, value) in [(0, 0x20), (1, 0x00), (7, 0x3E), (8, 0x0F)].iter() {
+        mem_write(&mut state, 0xC000, *register);
+        mem_write(&mut state, 0xE000, *value);
+    }
+    let full = audio_peak(&mut state, 5000);
+    assert!((full - pulse_level(15.0)).abs() < 0.0001);
+
+    // Each volume step is 3 dB
+    mem_write(&mut state, 0xC000, 8);
+    mem_write(&mut state, 0xE000, 0x0E);
+    let quieter = audio_peak(&mut state, 5000);
+    assert!((20.0 * (full / quieter).log10() - 3.0).abs() < 0.01);
+}
+
+#[test]
+fn test_fme7_irq_counter() {
+    let mut state = make_state(make_rom(69, 8, 4));
+
+    mem_write(&mut state, 0x8000, 0x0E);
+    mem_write(&mut state, 0xA000, 0x02);
+    mem_write(&mut state, 0x8000, 0x0F);
+    mem_write(&mut state, 0xA000, 0x00);
+    mem_write(&mut state, 0x8000, 0x0D);
+    mem_write(&mut state, 0xA000, 0x81);
+
+    clock_mapper(&mut state, 2);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 1);
+    assert!(state.get_cartridge().mapper.irq_state());
+}
+
+#[test]
+fn test_n163_wavetable() {
+    let mut state = make_state(make_rom(19, 8, 4));
+
+    // Sound RAM through the auto incrementing port, a square wave in the first 4 bytes
+    mem_write(&mut state, 0xF800, 0x80);
+    for value in [0xFF, 0xFF, 0x00, 0x00].iter() {
+        mem_write(&mut state, 0x4800, *value);
+    }
+    mem_write(&mut state, 0xF800, 0x00);
+    assert_eq!(mem_read(&mut state, 0x4800), 0xFF);
+
+    // Channel 7, the only one enabled: wave length 8, frequency $4000, volume 15
+    mem_write(&mut state, 0xF800, 0xF8);
+    for value in [0x00, 0x00, 0x40, 0x00, 0xF8, 0x00, 0x00, 0x0F].iter() {
+        mem_write(&mut state, 0x4800, *value);
+    }
+    let peak = audio_peak(&mut state, 1000);
+    assert!((peak - 8.0 * 15.0 / 120.0 * pulse_level(15.0)).abs() < 0.0001);
+
+    // Sound disable in $E000
+    mem_write(&mut state, 0xE000, 0x40);
+    assert_eq!(audio_peak(&mut state, 1000), 0.0);
+}
+
+#[test]
+fn test_n163_name_tables_from_chr() {
+    let mut state = make_state(make_rom(19, 8, 4));
+
+    // $E0 and up use CIRAM, anything lower pages CHR ROM in
+    ppu_write(&mut state, 0x2000, 0x55);
+    assert_eq!(ppu_read(&mut state, 0x2000), 0x55);
+    mem_write(&mut state, 0xC000, 9);
+    assert_eq!(ppu_read(&mut state, 0x2000), 9 / 4);
+    ppu_write(&mut state, 0x2000, 0x77);
+    assert_eq!(ppu_read(&mut state, 0x2000), 9 / 4);
+}
+
+#[test]
+fn test_mmc5_audio() {
+    let mut state = make_state(make_rom(5, 8, 8));
+
+    mem_write(&mut state, 0x5015, 0x01);
+    mem_write(&mut state, 0x5000, 0xBF);
+    mem_write(&mut state, 0x5002, 0x40);
+    mem_write(&mut state, 0x5003, 0x00);
+    assert_eq!(mem_read(&mut state, 0x5015), 0x01);
+    assert!((audio_peak(&mut state, 2000) - pulse_level(15.0)).abs() < 0.0001);
+
+    // Raw PCM, zero writes are ignored
+    mem_write(&mut state, 0x5015, 0x00);
+    mem_write(&mut state, 0x5011, 0x80);
+    mem_write(&mut state, 0x5011, 0x00);
+    assert!(audio_peak(&mut state, 10) > 0.0);
+}
+
+#[test]
+fn test_vrc7_fm_key_on() {
+    let mut state = make_state(make_rom_nes2(85, 2, 8, 4));
+
+    // Channel 0, flute at full volume, A4
+    let writes = [(0x30, 0x40), (0x10, 0x20), (0x20, 0x19)];
+    for (register, value) in writes.iter() {
+        mem_write(&mut state, 0x9010, *register);
+        mem_write(&mut state, 0x9030, *value);
+    }
+    assert!(audio_peak(&mut state, 36 * 500) > 0.01);
+
+    // $E000 bit 6 silences the chip
+    mem_write(&mut state, 0xE000, 0x40);
+    assert_eq!(audio_peak(&mut state, 36 * 10), 0.0);
+}
+
+// One .fds side holding the disk info block, a file count of one and a four byte file
+fn make_fds_side(marker: u8) -> Vec<u8> {
+    let mut side = vec![0x01];
+    side.extend_from_slice(b"*NINTENDO-HVC*");
+    side.resize(56, marker);
+    side.extend_from_slice(&[0x02, 0x01]);
+    side.extend_from_slice(&[0x03, 0x00, 0x00]);
+    side.extend_from_slice(b"FILE0000");
+    side.extend_from_slice(&[0x00, 0x60, 0x04, 0x00, 0x00]);
+    side.extend_from_slice(&[0x04, 0x11, 0x22, 0x33, 0x44]);
+    side.resize(65500, 0);
+    side
+}
+
+fn make_fds_state() -> State {
+    let mut bios = vec![0xEA; 8192];
+    bios[0x1FFC] = 0x00;
+    bios[0x1FFD] = 0xE0;
+    let mut disk = b"FDS\x1A\x02".to_vec();
+    disk.resize(16, 0);
+    disk.extend(make_fds_side(0xAA));
+    disk.extend(make_fds_side(0xBB));
+
+    let mut state = State::new();
+    state.connect_cartridge(Some(Cartridge::from_fds(&bios, &disk)));
+    state
+}
+
+// Runs the drive until it hands over the next byte, the lead in alone is about 530000 cycles
+fn fds_next_byte(state: &mut State) -> u8 {
+    for _ in 0..1_000_000 {
+        clock_mapper(state, 1);
+        if mem_read(state, 0x4030) & 0x02 > 0 {
+            return mem_read(state, 0x4031);
+        }
+    }
+    panic!("The drive never transferred a byte");
+}
+
+#[test]
+fn test_fds_memory_map() {
+    let mut state = make_fds_state();
+
+    assert_eq!(mem_read(&mut state, 0xE000), 0xEA);
+    assert_eq!(mem_read(&mut state, 0xFFFD), 0xE0);
+    mem_write(&mut state, 0x6000, 0x12);
+    mem_write(&mut state, 0xDFFF, 0x34);
+    assert_eq!(mem_read(&mut state, 0x6000), 0x12);
+    assert_eq!(mem_read(&mut state, 0xDFFF), 0x34);
+
+    ppu_write(&mut state, 0x1234, 0x56);
+    assert_eq!(ppu_read(&mut state, 0x1234), 0x56);
+
+    mem_write(&mut state, 0x4023, 0x01);
+    mem_write(&mut state, 0x4025, 0x2E);
+    assert_eq!(state.get_cartridge().mirror(), Mirror::Horizontal);
+}
+
+#[test]
+fn test_fds_timer_irq() {
+    let mut state = make_fds_state();
+
+    mem_write(&mut state, 0x4023, 0x01);
+    mem_write(&mut state, 0x4020, 0x03);
+    mem_write(&mut state, 0x4021, 0x00);
+    mem_write(&mut state, 0x4022, 0x02);
+
+    clock_mapper(&mut state, 3);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 1);
+    assert!(state.get_cartridge().mapper.irq_state());
+
+    // $4030 reports and acknowledges it, without repeat the timer stops
+    assert_eq!(mem_read(&mut state, 0x4030) & 0x01, 0x01);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    clock_mapper(&mut state, 100);
+    assert!(!state.get_cartridge().mapper.irq_state());
+}
+
+#[test]
+fn test_fds_drive_reads_blocks() {
+    let mut state = make_fds_state();
+
+    mem_write(&mut state, 0x4023, 0x01);
+    // Motor on with transfer reset, then release it and start reading
+    mem_write(&mut state, 0x4025, 0x27);
+    clock_mapper(&mut state, 10);
+    assert_eq!(mem_read(&mut state, 0x4032) & 0x03, 0x02);
+    mem_write(&mut state, 0x4025, 0x65);
+
+    assert_eq!(fds_next_byte(&mut state), 0x01);
+    assert_eq!(fds_next_byte(&mut state), b'*');
+    assert_eq!(mem_read(&mut state, 0x4032) & 0x03, 0x00);
+}
+
+#[test]
+fn test_fds_disk_flip() {
+    let mut state = make_fds_state();
+    assert_eq!(state.get_cartridge().disk_sides(), 2);
+
+    state.get_cartridge_mut().eject_disk();
+    mem_write(&mut state, 0x4023, 0x01);
+    assert_eq!(mem_read(&mut state, 0x4032) & 0x01, 0x01);
+
+    assert!(!state.get_cartridge_mut().insert_disk_side(2));
+    assert!(state.get_cartridge_mut().insert_disk_side(1));
+    assert_eq!(mem_read(&mut state, 0x4032) & 0x01, 0x00);
+
+    // Side B has its own disk info padding
+    mem_write(&mut state, 0x4025, 0x65);
+    for _ in 0..15 {
+        fds_next_byte(&mut state);
+    }
+    assert_eq!(fds_next_byte(&mut state), 0xBB);
+}
+
+#[test]
+fn test_fds_audio() {
+    let mut state = make_fds_state();
+    mem_write(&mut state, 0x4023, 0x03);
+
+    // Square wave in the table, written while $4089 bit 7 is set
+    mem_write(&mut state, 0x4089, 0x80);
+    for i in 0..64 {
+        mem_write(&mut state, 0x4040 + i, if i < 32 { 63 } else { 0 });
+    }
+    mem_write(&mut state, 0x4089, 0x00);
+    assert_eq!(mem_read(&mut state, 0x4040), 63);
+
+    // Envelope off with gain 32, then start the wave
+    mem_write(&mut state, 0x4080, 0xA0);
+    assert_eq!(mem_read(&mut state, 0x4090), 32);
+    mem_write(&mut state, 0x4082, 0x00);
+    mem_write(&mut state, 0x4083, 0x04);
+    let peak = audio_peak(&mut state, 2000);
+    assert!((peak - 2.4 * pulse_level(15.0)).abs() < 0.0001);
+
+    // Master volume 2/5
+    mem_write(&mut state, 0x4089, 0x03);
+    let quieter = audio_peak(&mut state, 2000);
+    assert!((quieter - peak * 0.4).abs() < 0.0001);
+}
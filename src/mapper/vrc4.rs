@@ -0,0 +1,185 @@
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+use crate::mapper::vrc_irq::VrcIrq;
+
+// CPU address bit masks for A0 .. A7
+const A0: u16 = 0x01;
+const A1: u16 = 0x02;
+const A2: u16 = 0x04;
+const A3: u16 = 0x08;
+const A6: u16 = 0x40;
+const A7: u16 = 0x80;
+
+/**
+ * Konami VRC2 and VRC4 (mappers 21, 22, 23 and 25)
+ *
+ * Every board variant wires the chip's two register select pins to different CPU address
+ * lines, so after decoding each register is at $x000-$x003:
+ *
+ * $8000        PRG bank at $8000 (or $C000 when swapped)
+ * $9000        mirroring
+ * $9002        VRC4 PRG swap mode
+ * $A000        PRG bank at $A000
+ * $B000-$E003  eight 1 KB CHR banks, written as low / high nibble pairs
+ * $F000-$F003  VRC4 IRQ latch low, latch high, control and acknowledge
+ *
+ * Submapper 0 (plain iNES) listens on both candidate lines at once, which works for every
+ * known game because each one leaves the other board's lines low. The bundled ROM database
+ * has no VRC entries, the submapper only comes from an NES 2.0 header or a NES20DB file.
+ */
+pub struct Vrc4 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+    is_vrc2: bool,
+    // VRC2a ignores the low bit of every CHR bank
+    chr_shift: u8,
+    select_lines: [u16; 2],
+    prg_regs: [u8; 2],
+    prg_swap: bool,
+    chr_regs: [u16; 8],
+    mirror: Mirror,
+    prg_ram: Vec<u8>,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+
+    pub fn new(mapper_id: u16, submapper: u8, n_prgbanks: u8, n_chrbanks: u8) -> Vrc4 {
+        let (is_vrc2, chr_shift, select_lines) = match (mapper_id, submapper) {
+            (21, 1) => (false, 0, [A1, A2]),
+            (21, 2) => (false, 0, [A6, A7]),
+            (21, _) => (false, 0, [A1 | A6, A2 | A7]),
+            (22, _) => (true, 1, [A1, A0]),
+            (23, 1) => (false, 0, [A0, A1]),
+            (23, 2) => (false, 0, [A2, A3]),
+            (23, 3) => (true, 0, [A0, A1]),
+            (23, _) => (false, 0, [A0 | A2, A1 | A3]),
+            (25, 1) => (false, 0, [A1, A0]),
+            (25, 2) => (false, 0, [A3, A2]),
+            (25, 3) => (true, 0, [A1, A0]),
+            (_, _) => (false, 0, [A1 | A3, A0 | A2])
+        };
+
+        Vrc4 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(2),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            is_vrc2,
+            chr_shift,
+            select_lines,
+            prg_regs: [0, 0],
+            prg_swap: false,
+            chr_regs: [0; 8],
+            mirror: Mirror::Vertical,
+            prg_ram: vec![0; 8192],
+            irq: VrcIrq::new()
+        }
+    }
+
+    fn decode(&self, addr: u16) -> u16 {
+        let mut register = addr & 0xF000;
+        if addr & self.select_lines[0] > 0 {
+            register |= 0x01;
+        }
+        if addr & self.select_lines[1] > 0 {
+            register |= 0x02;
+        }
+        register
+    }
+}
+
+impl Mapper for Vrc4 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            return true;
+        } else if addr >= 0x8000 {
+            let second_last = self.prg_banks_8k - 2;
+            let bank = match addr {
+                0x8000..=0x9FFF => if self.prg_swap { second_last } else { self.prg_regs[0] as u32 },
+                0xA000..=0xBFFF => self.prg_regs[1] as u32,
+                0xC000..=0xDFFF => if self.prg_swap { self.prg_regs[0] as u32 } else { second_last },
+                _ => self.prg_banks_8k - 1
+            };
+            *mapped_addr = (bank % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            *mapped_addr = MAPPER_HANDLED;
+            self.prg_ram[(addr & 0x1FFF) as usize] = data;
+            return true;
+        } else if addr < 0x8000 {
+            return false;
+        }
+
+        let register = self.decode(addr);
+        match register {
+            0x8000..=0x8003 => self.prg_regs[0] = data & 0x1F,
+            0x9000..=0x9001 if self.is_vrc2 => {
+                self.mirror = if data & 0x01 > 0 { Mirror::Horizontal } else { Mirror::Vertical };
+            }
+            0x9000..=0x9001 => {
+                self.mirror = match data & 0x03 {
+                    0 => Mirror::Vertical,
+                    1 => Mirror::Horizontal,
+                    2 => Mirror::OneScreenLo,
+                    _ => Mirror::OneScreenHi
+                };
+            }
+            0x9002..=0x9003 if !self.is_vrc2 => self.prg_swap = data & 0x02 > 0,
+            0xA000..=0xA003 => self.prg_regs[1] = data & 0x1F,
+            0xB000..=0xEFFF => {
+                let select = register & 0x03;
+                let index = (((register - 0xB000) >> 12) * 2 + (select >> 1)) as usize;
+                if select & 0x01 == 0 {
+                    self.chr_regs[index] = (self.chr_regs[index] & 0x1F0) | (data & 0x0F) as u16;
+                } else {
+                    let high = if self.is_vrc2 { data & 0x0F } else { data & 0x1F };
+                    self.chr_regs[index] = (self.chr_regs[index] & 0x0F) | ((high as u16) << 4);
+                }
+            }
+            0xF000 if !self.is_vrc2 => self.irq.write_latch_low(data),
+            0xF001 if !self.is_vrc2 => self.irq.write_latch_high(data),
+            0xF002 if !self.is_vrc2 => self.irq.write_control(data),
+            0xF003 if !self.is_vrc2 => self.irq.acknowledge(),
+            _ => {}
+        }
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            let bank = (self.chr_regs[(addr / 0x0400) as usize] >> self.chr_shift) as u32;
+            *mapped_addr = (bank % self.chr_banks_1k) * 0x0400 + (addr & 0x03FF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn reset(&mut self) {
+        self.prg_regs = [0, 0];
+        self.prg_swap = false;
+        self.irq = VrcIrq::new();
+    }
+}
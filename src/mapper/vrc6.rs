@@ -0,0 +1,168 @@
+use crate::apu::vrc6_audio::Vrc6Audio;
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+use crate::mapper::vrc_irq::VrcIrq;
+
+/**
+ * Konami VRC6 (mappers 24 and 26)
+ *
+ * Mapper 26 swaps the A0 and A1 register select lines, after decoding:
+ *
+ * $8000-$8003  16 KB PRG bank at $8000
+ * $9000-$B002  expansion audio, see Vrc6Audio
+ * $B003        PPU banking mode, mirroring and PRG RAM enable
+ * $C000-$C003  8 KB PRG bank at $C000
+ * $D000-$E003  CHR registers R0-R7
+ * $F000-$F002  IRQ latch, control and acknowledge
+ *
+ * $E000-$FFFF is fixed to the last 8 KB bank.
+ */
+pub struct Vrc6 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+    swap_lines: bool,
+    prg_16k: u8,
+    prg_8k: u8,
+    banking: u8,
+    chr_regs: [u8; 8],
+    prg_ram: Vec<u8>,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6 {
+
+    pub fn new(mapper_id: u16, n_prgbanks: u8, n_chrbanks: u8) -> Vrc6 {
+        Vrc6 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(2),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            swap_lines: mapper_id == 26,
+            prg_16k: 0,
+            prg_8k: 0,
+            banking: 0,
+            chr_regs: [0; 8],
+            prg_ram: vec![0; 8192],
+            irq: VrcIrq::new(),
+            audio: Vrc6Audio::new()
+        }
+    }
+
+    fn decode(&self, addr: u16) -> u16 {
+        let select = if self.swap_lines {
+            ((addr & 0x01) << 1) | ((addr & 0x02) >> 1)
+        } else {
+            addr & 0x03
+        };
+        (addr & 0xF000) | select
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking & 0x80 > 0
+    }
+
+    // 1 KB CHR bank for a pattern table address, 2 KB banks take A10 from the PPU
+    fn chr_bank(&self, addr: u16) -> u32 {
+        let slot = (addr / 0x0400) as usize;
+        let a10 = ((addr >> 10) & 0x01) as u8;
+        let bank = match self.banking & 0x03 {
+            0 => self.chr_regs[slot],
+            1 => (self.chr_regs[slot / 2] & 0xFE) | a10,
+            _ => if slot < 4 {
+                self.chr_regs[slot]
+            } else {
+                (self.chr_regs[4 + (slot - 4) / 2] & 0xFE) | a10
+            }
+        };
+        bank as u32
+    }
+}
+
+impl Mapper for Vrc6 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            if !self.prg_ram_enabled() {
+                return false;
+            }
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            return true;
+        } else if addr >= 0x8000 {
+            let bank = match addr {
+                0x8000..=0xBFFF => (self.prg_16k as u32) * 2 + ((addr as u32 >> 13) & 0x01),
+                0xC000..=0xDFFF => self.prg_8k as u32,
+                _ => self.prg_banks_8k - 1
+            };
+            *mapped_addr = (bank % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            if !self.prg_ram_enabled() {
+                return false;
+            }
+            *mapped_addr = MAPPER_HANDLED;
+            self.prg_ram[(addr & 0x1FFF) as usize] = data;
+            return true;
+        } else if addr < 0x8000 {
+            return false;
+        }
+
+        match self.decode(addr) {
+            0x8000..=0x8003 => self.prg_16k = data & 0x0F,
+            register @ 0x9000..=0x9003 => self.audio.write(0, register & 0x03, data),
+            register @ 0xA000..=0xA002 => self.audio.write(1, register & 0x03, data),
+            register @ 0xB000..=0xB002 => self.audio.write(2, register & 0x03, data),
+            0xB003 => self.banking = data,
+            0xC000..=0xC003 => self.prg_8k = data & 0x1F,
+            register @ 0xD000..=0xD003 => self.chr_regs[(register & 0x03) as usize] = data,
+            register @ 0xE000..=0xE003 => self.chr_regs[4 + (register & 0x03) as usize] = data,
+            0xF000 => self.irq.latch = data,
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            *mapped_addr = (self.chr_bank(addr) % self.chr_banks_1k) * 0x0400 + (addr & 0x03FF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, _addr: u16, _mapped_addr: &mut u32, _data: u8) -> bool {
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        match (self.banking >> 2) & 0x03 {
+            0 => Mirror::Vertical,
+            1 => Mirror::Horizontal,
+            2 => Mirror::OneScreenLo,
+            _ => Mirror::OneScreenHi
+        }
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn audio_sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn reset(&mut self) {
+        self.irq = VrcIrq::new();
+    }
+}
@@ -0,0 +1,172 @@
+use crate::apu::vrc7_audio::Vrc7Audio;
+use crate::cartridge::Mirror;
+use crate::mapper::{Mapper, MAPPER_HANDLED};
+use crate::mapper::vrc_irq::VrcIrq;
+
+/**
+ * Konami VRC7 (mapper 85)
+ *
+ * VRC7a (submapper 2) selects the second register of each pair with A4, VRC7b
+ * (submapper 1) with A3. After decoding:
+ *
+ * $8000 / $8001  PRG banks at $8000 and $A000
+ * $9000          PRG bank at $C000
+ * $9010 / $9030  FM audio register select and data
+ * $A000-$D001    eight 1 KB CHR banks
+ * $E000          mirroring, audio silence and PRG RAM enable
+ * $E001          IRQ latch
+ * $F000 / $F001  IRQ control and acknowledge
+ */
+pub struct Vrc7 {
+    prg_banks_8k: u32,
+    chr_banks_1k: u32,
+    select_line: u16,
+    prg_regs: [u8; 3],
+    chr_regs: [u8; 8],
+    control: u8,
+    prg_ram: Vec<u8>,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl Vrc7 {
+
+    pub fn new(submapper: u8, n_prgbanks: u8, n_chrbanks: u8) -> Vrc7 {
+        Vrc7 {
+            prg_banks_8k: (n_prgbanks as u32 * 2).max(1),
+            chr_banks_1k: (n_chrbanks as u32 * 8).max(8),
+            select_line: match submapper {
+                1 => 0x08,
+                2 => 0x10,
+                _ => 0x18
+            },
+            prg_regs: [0; 3],
+            chr_regs: [0; 8],
+            control: 0,
+            prg_ram: vec![0; 8192],
+            irq: VrcIrq::new(),
+            audio: Vrc7Audio::new()
+        }
+    }
+
+    fn decode(&self, addr: u16) -> u16 {
+        (addr & 0xF000) | if addr & self.select_line > 0 { 0x01 } else { 0x00 }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & 0x80 > 0
+    }
+}
+
+impl Mapper for Vrc7 {
+
+    fn cpu_map_peek(&self, addr: u16, mapped_addr: &mut u32, data: &mut u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            if !self.prg_ram_enabled() {
+                return false;
+            }
+            *mapped_addr = MAPPER_HANDLED;
+            *data = self.prg_ram[(addr & 0x1FFF) as usize];
+            return true;
+        } else if addr >= 0x8000 {
+            let bank = match addr {
+                0x8000..=0x9FFF => self.prg_regs[0] as u32,
+                0xA000..=0xBFFF => self.prg_regs[1] as u32,
+                0xC000..=0xDFFF => self.prg_regs[2] as u32,
+                _ => self.prg_banks_8k - 1
+            };
+            *mapped_addr = (bank % self.prg_banks_8k) * 0x2000 + (addr & 0x1FFF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, data: u8) -> bool {
+        if addr >= 0x6000 && addr <= 0x7FFF {
+            if !self.prg_ram_enabled() {
+                return false;
+            }
+            *mapped_addr = MAPPER_HANDLED;
+            self.prg_ram[(addr & 0x1FFF) as usize] = data;
+            return true;
+        } else if addr < 0x8000 {
+            return false;
+        }
+
+        // The audio ports are decoded on their own and ignore the board's select line
+        if addr & 0xF030 == 0x9010 {
+            self.audio.write_select(data);
+            return false;
+        } else if addr & 0xF030 == 0x9030 {
+            if self.control & 0x40 == 0 {
+                self.audio.write_data(data);
+            }
+            return false;
+        }
+
+        match self.decode(addr) {
+            0x8000 => self.prg_regs[0] = data & 0x3F,
+            0x8001 => self.prg_regs[1] = data & 0x3F,
+            0x9000 => self.prg_regs[2] = data & 0x3F,
+            register @ 0xA000..=0xD001 => {
+                let index = (((register - 0xA000) >> 12) * 2 + (register & 0x01)) as usize;
+                self.chr_regs[index] = data;
+            }
+            0xE000 => {
+                self.control = data;
+                if data & 0x40 > 0 {
+                    self.audio.silence();
+                }
+            }
+            0xE001 => self.irq.latch = data,
+            0xF000 => self.irq.write_control(data),
+            0xF001 => self.irq.acknowledge(),
+            _ => {}
+        }
+        return false;
+    }
+
+    fn ppu_map_peek(&self, addr: u16, mapped_addr: &mut u32, _data: &mut u8) -> bool {
+        if addr <= 0x1FFF {
+            let bank = self.chr_regs[(addr / 0x0400) as usize] as u32;
+            *mapped_addr = (bank % self.chr_banks_1k) * 0x0400 + (addr & 0x03FF) as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn ppu_map_write(&mut self, addr: u16, mapped_addr: &mut u32, _data: u8) -> bool {
+        // Lagrange Point uses 8 KB of CHR RAM
+        if addr <= 0x1FFF && self.chr_banks_1k == 8 {
+            *mapped_addr = addr as u32;
+            return true;
+        }
+        return false;
+    }
+
+    fn mirror(&self) -> Mirror {
+        match self.control & 0x03 {
+            0 => Mirror::Vertical,
+            1 => Mirror::Horizontal,
+            2 => Mirror::OneScreenLo,
+            _ => Mirror::OneScreenHi
+        }
+    }
+
+    fn irq_state(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn audio_sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn reset(&mut self) {
+        self.irq = VrcIrq::new();
+    }
+}
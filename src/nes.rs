@@ -0,0 +1,145 @@
+use crate::bus;
+use crate::cartridge::Cartridge;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::StopReason;
+use crate::ppu;
+use crate::ppu::{Ppu, Screen};
+use crate::state::State;
+
+/**
+ * The whole machine in one owned value. The CPU owns the bus State (RAM, APU and the
+ * cartridge) and lends it to the PPU for every dot, so nothing is shared and the machine
+ * can be moved to another thread.
+ */
+pub struct Nes {
+    pub cpu: Cpu,
+    pub ppu: Ppu,
+    frames: u64,
+    audio: Vec<f32>,
+}
+
+/**
+ * What one call to run_frame produced. The picture and the samples are borrowed from the
+ * machine and stay valid until it runs again.
+ */
+pub struct Frame<'a> {
+    // One palette index per dot, 256 x 240
+    pub indices: &'a [u8],
+    // Four bytes per dot, 256 x 240
+    pub rgba: &'a [u8],
+    // Mono samples at 44.1 kHz, about 734 for a whole frame
+    pub audio: &'a [f32],
+    pub stats: FrameStats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStats {
+    // Frames completed since the machine was created
+    pub frame: u64,
+    pub cpu_cycles: u32,
+    // FrameComplete, or whatever stopped the CPU part way through the frame
+    pub stop: StopReason,
+}
+
+impl Default for Nes {
+
+    fn default() -> Nes {
+        Nes::new()
+    }
+}
+
+impl Nes {
+
+    pub fn new() -> Nes {
+        Nes {
+            cpu: Cpu::new(),
+            ppu: Ppu::new(),
+            frames: 0,
+            audio: vec![]
+        }
+    }
+
+    // Plugs in a cartridge and presses reset
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cpu.get_state_mut().connect_cartridge(Some(cartridge));
+        self.reset();
+    }
+
+    // Loads a program into RAM without a cartridge and points the reset vector at it
+    pub fn load(&mut self, code: Vec<u8>, offset: u16) {
+        self.cpu.get_state_mut().load(code, offset);
+        self.reset();
+    }
+
+    pub fn state(&self) -> &State {
+        self.cpu.get_state()
+    }
+
+    pub fn state_mut(&mut self) -> &mut State {
+        self.cpu.get_state_mut()
+    }
+
+    pub fn reset(&mut self) {
+        bus::reset(&mut self.ppu, &mut self.cpu);
+    }
+
+    // One PPU dot, with a CPU cycle on every third
+    pub fn clock(&mut self) -> Result<(), StopReason> {
+        bus::clock(&mut self.ppu, &mut self.cpu)
+    }
+
+    // Runs until the current instruction is done
+    pub fn step(&mut self) -> StopReason {
+        bus::advance(&mut self.ppu, &mut self.cpu)
+    }
+
+    // Runs until the PPU finishes a frame
+    pub fn frame(&mut self) -> StopReason {
+        bus::system_clock(&mut self.ppu, &mut self.cpu)
+    }
+
+    /**
+     * Runs until the PPU finishes a frame and hands back the picture, the audio produced on
+     * the way and some counters. A breakpoint or a jam ends the frame early, the next call
+     * carries on from there.
+     */
+    pub fn run_frame(&mut self) -> Frame<'_> {
+        let start = self.cpu.clock_count();
+        let stop = self.frame();
+        if stop == StopReason::FrameComplete {
+            self.frames += 1;
+        }
+        self.audio = self.cpu.get_state_mut().apu.take_samples();
+
+        Frame {
+            indices: self.ppu.screen.indices(),
+            rgba: self.ppu.screen.rgba(),
+            audio: &self.audio,
+            stats: FrameStats {
+                frame: self.frames,
+                cpu_cycles: self.cpu.clock_count().wrapping_sub(start),
+                stop
+            }
+        }
+    }
+
+    // What the CPU would read at addr, registers and mappers are left as they are
+    pub fn peek(&self, addr: u16) -> u8 {
+        bus::mem_peek(self.state(), addr)
+    }
+
+    // The same for the PPU's address space: pattern tables, name tables and palettes
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        ppu::ppu_peek(self.state(), addr)
+    }
+
+    // The last picture the PPU drew
+    pub fn screen(&self) -> &Screen {
+        &self.ppu.screen
+    }
+
+    // Runs for at most the given number of CPU cycles
+    pub fn run(&mut self, cpu_cycles: usize) -> StopReason {
+        bus::run(&mut self.ppu, &mut self.cpu, cpu_cycles)
+    }
+}
@@ -0,0 +1,210 @@
+pub mod player;
+#[cfg(test)]
+mod tests;
+
+use std::fs::File;
+use std::io::Read;
+
+// Play rates in microseconds per call, used when an NSFE file has no RATE chunk
+const DEFAULT_SPEED_NTSC: u16 = 16639;
+const DEFAULT_SPEED_PAL: u16 = 19997;
+
+// Expansion chip flags, header byte $7B
+pub const CHIP_VRC6: u8 = 0x01;
+pub const CHIP_VRC7: u8 = 0x02;
+pub const CHIP_FDS: u8 = 0x04;
+pub const CHIP_MMC5: u8 = 0x08;
+pub const CHIP_N163: u8 = 0x10;
+pub const CHIP_5B: u8 = 0x20;
+
+/**
+ * A ripped NES tune, from either an .nsf or an .nsfe file
+ *
+ * .nsf header:
+ *
+ * $00  "NESM" $1A
+ * $06  song count
+ * $07  starting song, counted from 1
+ * $08  load, init and play addresses
+ * $0E  title, artist and copyright, 32 bytes each
+ * $6E  NTSC play speed in microseconds
+ * $70  bank switch init values, all zero when the tune is not bank switched
+ * $78  PAL play speed
+ * $7A  region: bit 0 PAL, bit 1 both
+ * $7B  expansion chips
+ * $80  program data
+ *
+ * .nsfe files hold the same fields in "INFO", "DATA", "BANK", "RATE", "auth" and "tlbl"
+ * chunks, each prefixed with its length and ending with "NEND".
+ */
+pub struct Nsf {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub song_count: u8,
+    // Counted from 0
+    pub starting_song: u8,
+    pub load_addr: u16,
+    pub init_addr: u16,
+    pub play_addr: u16,
+    pub play_speed_ntsc: u16,
+    pub play_speed_pal: u16,
+    pub bank_init: [u8; 8],
+    pub region: u8,
+    pub chips: u8,
+    pub data: Vec<u8>,
+    pub track_labels: Vec<String>,
+}
+
+impl Nsf {
+
+    pub fn new(file_path: &str) -> Nsf {
+        let mut file = File::open(file_path).expect("File not found");
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).expect("Cannot read from NSF file");
+        Nsf::from_bytes(&buffer)
+    }
+
+    pub fn from_bytes(buffer: &[u8]) -> Nsf {
+        if buffer.len() >= 4 && &buffer[0..4] == b"NSFE" {
+            return Nsf::from_nsfe(buffer);
+        }
+
+        assert!(buffer.len() >= 0x80, "NSF file is too short");
+        assert!(&buffer[0..5] == b"NESM\x1A", "Not an NSF file");
+
+        let mut bank_init = [0; 8];
+        bank_init.copy_from_slice(&buffer[0x70..0x78]);
+
+        Nsf {
+            title: read_string(&buffer[0x0E..0x2E]),
+            artist: read_string(&buffer[0x2E..0x4E]),
+            copyright: read_string(&buffer[0x4E..0x6E]),
+            song_count: buffer[0x06],
+            starting_song: buffer[0x07].max(1) - 1,
+            load_addr: read_word(buffer, 0x08),
+            init_addr: read_word(buffer, 0x0A),
+            play_addr: read_word(buffer, 0x0C),
+            // Some rippers leave the rates at 0, play those at the usual frame rate
+            play_speed_ntsc: nonzero_or(read_word(buffer, 0x6E), DEFAULT_SPEED_NTSC),
+            play_speed_pal: nonzero_or(read_word(buffer, 0x78), DEFAULT_SPEED_PAL),
+            bank_init,
+            region: buffer[0x7A],
+            chips: buffer[0x7B],
+            data: buffer[0x80..].to_vec(),
+            track_labels: vec![]
+        }
+    }
+
+    fn from_nsfe(buffer: &[u8]) -> Nsf {
+        let mut nsf = Nsf {
+            title: String::new(),
+            artist: String::new(),
+            copyright: String::new(),
+            song_count: 1,
+            starting_song: 0,
+            load_addr: 0,
+            init_addr: 0,
+            play_addr: 0,
+            play_speed_ntsc: DEFAULT_SPEED_NTSC,
+            play_speed_pal: DEFAULT_SPEED_PAL,
+            bank_init: [0; 8],
+            region: 0,
+            chips: 0,
+            data: vec![],
+            track_labels: vec![]
+        };
+
+        let mut found_info = false;
+        let mut offset = 4;
+        while offset + 8 <= buffer.len() {
+            let length = u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]]) as usize;
+            let id = &buffer[offset + 4..offset + 8];
+            offset += 8;
+            let chunk = &buffer[offset.min(buffer.len())..(offset + length).min(buffer.len())];
+            offset += length;
+
+            match id {
+                b"INFO" => {
+                    assert!(chunk.len() >= 8, "NSFE INFO chunk is too short");
+                    nsf.load_addr = read_word(chunk, 0);
+                    nsf.init_addr = read_word(chunk, 2);
+                    nsf.play_addr = read_word(chunk, 4);
+                    nsf.region = chunk[6];
+                    nsf.chips = chunk[7];
+                    if chunk.len() > 8 {
+                        nsf.song_count = chunk[8];
+                    }
+                    if chunk.len() > 9 {
+                        nsf.starting_song = chunk[9];
+                    }
+                    found_info = true;
+                }
+                b"DATA" => nsf.data = chunk.to_vec(),
+                b"BANK" => {
+                    let count = chunk.len().min(8);
+                    nsf.bank_init[..count].copy_from_slice(&chunk[..count]);
+                }
+                b"RATE" => {
+                    if chunk.len() >= 2 {
+                        nsf.play_speed_ntsc = nonzero_or(read_word(chunk, 0), DEFAULT_SPEED_NTSC);
+                    }
+                    if chunk.len() >= 4 {
+                        nsf.play_speed_pal = nonzero_or(read_word(chunk, 2), DEFAULT_SPEED_PAL);
+                    }
+                }
+                b"auth" => {
+                    let mut strings = chunk.split(|&byte| byte == 0).map(read_string);
+                    nsf.title = strings.next().unwrap_or_default();
+                    nsf.artist = strings.next().unwrap_or_default();
+                    nsf.copyright = strings.next().unwrap_or_default();
+                }
+                b"tlbl" => {
+                    nsf.track_labels = chunk.split(|&byte| byte == 0).map(read_string).collect();
+                    nsf.track_labels.truncate(nsf.song_count as usize);
+                }
+                b"NEND" => break,
+                _ => {}
+            }
+        }
+
+        assert!(found_info, "NSFE file has no INFO chunk");
+        nsf
+    }
+
+    pub fn is_bank_switched(&self) -> bool {
+        self.bank_init.iter().any(|&bank| bank != 0)
+    }
+
+    // PAL only tunes are played at their PAL rate, everything else at the NTSC rate
+    pub fn is_pal(&self) -> bool {
+        self.region & 0x03 == 0x01
+    }
+
+    // Microseconds between PLAY calls, never 0
+    pub fn play_speed(&self) -> u16 {
+        if self.is_pal() { self.play_speed_pal } else { self.play_speed_ntsc }
+    }
+
+    // The NSFE label for a track, or a generic name
+    pub fn track_label(&self, track: u8) -> String {
+        match self.track_labels.get(track as usize) {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => format!("Track {}", track + 1)
+        }
+    }
+}
+
+fn nonzero_or(value: u16, default: u16) -> u16 {
+    if value == 0 { default } else { value }
+}
+
+fn read_word(buffer: &[u8], offset: usize) -> u16 {
+    (buffer[offset] as u16) | ((buffer[offset + 1] as u16) << 8)
+}
+
+// Strings are zero terminated, or fill their whole field
+fn read_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
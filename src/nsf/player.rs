@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::apu::{CPU_CLOCK_RATE, SAMPLE_RATE};
+use crate::bus::{clock_peripherals, mem_write};
+use crate::cartridge::Cartridge;
+use crate::cpu::cpu_6502::Cpu;
+use crate::cpu::Flags::{I, U};
+use crate::nsf::{Nsf, CHIP_FDS};
+
+// INIT and PLAY return here, the CPU stops before fetching from it. Nothing is mapped
+// at $4100 so a tune never jumps there by itself
+const RETURN_TRAP: u16 = 0x4100;
+
+// How long INIT may run before the player gives up waiting and starts the tune anyway
+const INIT_TIMEOUT: u64 = CPU_CLOCK_RATE as u64;
+
+// Cycles the CPU runs between collecting samples from the APU
+const RENDER_CHUNK: u32 = 1000;
+
+/**
+ * Plays an NSF tune without a PPU. INIT runs once per track, then PLAY is called at the
+ * rate from the header. Between calls the CPU sits idle while the APU and the expansion
+ * chips keep running.
+ */
+pub struct NsfPlayer {
+    nsf: Nsf,
+    pub(crate) cpu: Cpu,
+    track: u8,
+    busy: bool,
+    play_period: f64,
+    play_clock: f64,
+    play_pending: bool,
+    leftover: Vec<f32>,
+}
+
+impl NsfPlayer {
+
+    pub fn new(nsf: Nsf) -> NsfPlayer {
+        let mut cpu = Cpu::new();
+        cpu.get_state_mut().connect_cartridge(Some(Cartridge::from_nsf(&nsf)));
+
+        let mut player = NsfPlayer {
+            play_period: nsf.play_speed() as f64 * CPU_CLOCK_RATE / 1_000_000.0,
+            track: nsf.starting_song,
+            nsf,
+            cpu,
+            busy: false,
+            play_clock: 0.0,
+            play_pending: false,
+            leftover: vec![]
+        };
+        player.start_track(player.track);
+        player
+    }
+
+    pub fn nsf(&self) -> &Nsf {
+        &self.nsf
+    }
+
+    // The current track, counted from 0
+    pub fn track(&self) -> u8 {
+        self.track
+    }
+
+    pub fn next_track(&mut self) {
+        let track = if self.track + 1 >= self.nsf.song_count { 0 } else { self.track + 1 };
+        self.start_track(track);
+    }
+
+    pub fn previous_track(&mut self) {
+        let track = if self.track == 0 { self.nsf.song_count.max(1) - 1 } else { self.track - 1 };
+        self.start_track(track);
+    }
+
+    // Resets the sound hardware and runs the tune's INIT routine for a track
+    pub fn start_track(&mut self, track: u8) {
+        self.track = track;
+        {
+            let state = self.cpu.get_state_mut();
+            for byte in state.cpu_ram[0..0x0800].iter_mut() {
+                *byte = 0;
+            }
+            if let Some(cart) = state.cartridge.as_mut() {
+                cart.reset();
+            }
+            state.apu.reset();
+
+            for addr in 0x4000..=0x4013 {
+                mem_write(state, addr, 0x00);
+            }
+            mem_write(state, 0x4015, 0x00);
+            mem_write(state, 0x4015, 0x0F);
+            mem_write(state, 0x4017, 0x40);
+            if self.nsf.chips & CHIP_FDS > 0 {
+                mem_write(state, 0x4080, 0x80);
+                mem_write(state, 0x408A, 0xE8);
+            }
+        }
+
+        {
+            let cpu = &mut self.cpu;
+            cpu.a = track;
+            cpu.x = if self.nsf.is_pal() { 0x01 } else { 0x00 };
+            cpu.y = 0;
+            cpu.stkp = 0xFD;
+            cpu.status = 0x00;
+            cpu.set_flag(U, true);
+            cpu.set_flag(I, true);
+            cpu.cycles = 0;
+        }
+
+        self.busy = false;
+        self.call(self.nsf.init_addr);
+        let mut cycles = 0;
+        while self.busy && cycles < INIT_TIMEOUT {
+            self.step();
+            cycles += 1;
+        }
+
+        // Whatever INIT produced is not part of the track
+        self.cpu.get_state_mut().apu.take_samples();
+        self.leftover.clear();
+        self.play_clock = 0.0;
+        self.play_pending = false;
+    }
+
+    // Runs the tune for a number of seconds and returns the mixed output at SAMPLE_RATE
+    pub fn render(&mut self, seconds: f64) -> Vec<f32> {
+        let count = (seconds * SAMPLE_RATE) as usize;
+        let mut samples = std::mem::take(&mut self.leftover);
+        while samples.len() < count {
+            for _ in 0..RENDER_CHUNK {
+                self.step();
+            }
+            samples.extend(self.cpu.get_state_mut().apu.take_samples());
+        }
+        self.leftover = samples.split_off(count);
+        samples
+    }
+
+    // Jumps to a routine with the return address pointing at RETURN_TRAP
+    fn call(&mut self, addr: u16) {
+        let cpu = &mut self.cpu;
+        let ret = RETURN_TRAP - 1;
+        let stkp = cpu.stkp;
+        cpu.write(0x0100 + stkp as u16, (ret >> 8) as u8);
+        cpu.write(0x0100 + stkp.wrapping_sub(1) as u16, (ret & 0x00FF) as u8);
+        cpu.stkp = stkp.wrapping_sub(2);
+        cpu.pc = addr;
+        self.busy = true;
+    }
+
+    // One CPU cycle
+    fn step(&mut self) {
+        {
+            let cpu = &mut self.cpu;
+            clock_peripherals(cpu);
+            if self.busy {
+                let _ = cpu.clock();
+                if cpu.cycles == 0 && cpu.pc == RETURN_TRAP {
+                    self.busy = false;
+                }
+            }
+        }
+
+        // A PLAY call that comes due while the last one is still running waits for it
+        self.play_clock += 1.0;
+        if self.play_clock >= self.play_period {
+            self.play_clock -= self.play_period;
+            self.play_pending = true;
+        }
+        if self.play_pending && !self.busy {
+            self.play_pending = false;
+            self.call(self.nsf.play_addr);
+        }
+    }
+}
+
+/**
+ * Writes samples from the player as a 16 bit mono WAV file. The APU output never goes
+ * below zero, so the DC offset is filtered out first.
+ */
+pub fn write_wav(path: &str, samples: &[f32], sample_rate: u32) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let data_size = samples.len() as u32 * 2;
+
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_size).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    // PCM, one channel
+    file.write_all(&1u16.to_le_bytes())?;
+    file.write_all(&1u16.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * 2).to_le_bytes())?;
+    file.write_all(&2u16.to_le_bytes())?;
+    file.write_all(&16u16.to_le_bytes())?;
+    file.write_all(b"data")?;
+    file.write_all(&data_size.to_le_bytes())?;
+
+    let mut last_in = samples.first().cloned().unwrap_or(0.0);
+    let mut last_out = 0.0;
+    for &sample in samples {
+        last_out = sample - last_in + 0.995 * last_out;
+        last_in = sample;
+        let value = (last_out * 32767.0).max(-32768.0).min(32767.0) as i16;
+        file.write_all(&value.to_le_bytes())?;
+    }
+    file.flush()
+}
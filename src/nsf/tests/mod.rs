@@ -0,0 +1,195 @@
+use crate::cartridge::Cartridge;
+use crate::nsf::player::{write_wav, NsfPlayer};
+use crate::nsf::{Nsf, CHIP_VRC6};
+
+// INIT stores the track number at $00, PLAY counts its calls at $01
+const COUNTER_PROGRAM: [u8; 6] = [0x85, 0x00, 0x60, 0xE6, 0x01, 0x60];
+
+fn make_nsf(songs: u8, start: u8, load: u16, bank_init: [u8; 8], data: &[u8]) -> Vec<u8> {
+    let mut nsf = vec![0; 0x80];
+    nsf[0..5].copy_from_slice(b"NESM\x1A");
+    nsf[0x05] = 1;
+    nsf[0x06] = songs;
+    nsf[0x07] = start;
+    nsf[0x08..0x0A].copy_from_slice(&load.to_le_bytes());
+    nsf[0x0A..0x0C].copy_from_slice(&load.to_le_bytes());
+    nsf[0x0C..0x0E].copy_from_slice(&(load + 3).to_le_bytes());
+    nsf[0x0E..0x13].copy_from_slice(b"Title");
+    nsf[0x2E..0x34].copy_from_slice(b"Artist");
+    nsf[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+    nsf[0x70..0x78].copy_from_slice(&bank_init);
+    nsf[0x78..0x7A].copy_from_slice(&19997u16.to_le_bytes());
+    nsf.extend_from_slice(data);
+    nsf
+}
+
+fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+    chunk.extend_from_slice(id);
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+#[test]
+fn test_parse_nsf_header() {
+    let mut image = make_nsf(5, 2, 0x8000, [0; 8], &COUNTER_PROGRAM);
+    image[0x7A] = 0x02;
+    image[0x7B] = CHIP_VRC6;
+    let nsf = Nsf::from_bytes(&image);
+
+    assert_eq!(nsf.title, "Title");
+    assert_eq!(nsf.artist, "Artist");
+    assert_eq!(nsf.copyright, "");
+    assert_eq!(nsf.song_count, 5);
+    assert_eq!(nsf.starting_song, 1);
+    assert_eq!((nsf.load_addr, nsf.init_addr, nsf.play_addr), (0x8000, 0x8000, 0x8003));
+    assert_eq!(nsf.play_speed(), 16639);
+    assert_eq!(nsf.chips, CHIP_VRC6);
+    assert!(!nsf.is_pal());
+    assert!(!nsf.is_bank_switched());
+    assert_eq!(nsf.data, COUNTER_PROGRAM.to_vec());
+    assert_eq!(nsf.track_label(0), "Track 1");
+}
+
+#[test]
+fn test_parse_nsfe_chunks() {
+    let mut info = vec![];
+    info.extend_from_slice(&0x8000u16.to_le_bytes());
+    info.extend_from_slice(&0x8000u16.to_le_bytes());
+    info.extend_from_slice(&0x8003u16.to_le_bytes());
+    info.extend_from_slice(&[0x01, 0x00, 3, 2]);
+
+    let mut image = b"NSFE".to_vec();
+    image.extend(chunk(b"INFO", &info));
+    image.extend(chunk(b"BANK", &[0, 1]));
+    image.extend(chunk(b"RATE", &[0x00, 0x10, 0x20, 0x4E]));
+    image.extend(chunk(b"auth", b"Song\0Composer\0Company\0Ripper\0"));
+    image.extend(chunk(b"tlbl", b"Intro\0Stage\0\0"));
+    image.extend(chunk(b"DATA", &COUNTER_PROGRAM));
+    image.extend(chunk(b"NEND", &[]));
+    let nsf = Nsf::from_bytes(&image);
+
+    assert_eq!(nsf.song_count, 3);
+    assert_eq!(nsf.starting_song, 2);
+    assert!(nsf.is_pal());
+    assert_eq!(nsf.play_speed(), 20000);
+    assert_eq!(nsf.bank_init, [0, 1, 0, 0, 0, 0, 0, 0]);
+    assert!(nsf.is_bank_switched());
+    assert_eq!((nsf.title.as_str(), nsf.artist.as_str(), nsf.copyright.as_str()), ("Song", "Composer", "Company"));
+    assert_eq!(nsf.track_label(1), "Stage");
+    assert_eq!(nsf.track_label(2), "Track 3");
+    assert_eq!(nsf.data, COUNTER_PROGRAM.to_vec());
+}
+
+#[test]
+fn test_nsf_bank_switching() {
+    let mut data = vec![0; 3 * 4096];
+    data[0x0000] = 0x10;
+    data[0x1000] = 0x11;
+    data[0x2000] = 0x12;
+    let nsf = Nsf::from_bytes(&make_nsf(1, 1, 0x8000, [2, 0, 0, 0, 0, 0, 0, 0], &data));
+    let mut cart = Cartridge::from_nsf(&nsf);
+
+    let mut value = 0;
+    assert!(cart.cpu_read(0x8000, &mut value));
+    assert_eq!(value, 0x12);
+    cart.cpu_write(0x5FF9, 0x01);
+    cart.cpu_read(0x9000, &mut value);
+    assert_eq!(value, 0x11);
+
+    // Writes to ROM are dropped
+    cart.cpu_write(0x9000, 0x55);
+    cart.cpu_read(0x9000, &mut value);
+    assert_eq!(value, 0x11);
+
+    // Reset goes back to the header's banks
+    cart.reset();
+    cart.cpu_read(0x9000, &mut value);
+    assert_eq!(value, 0x10);
+}
+
+#[test]
+fn test_nsf_without_banks_loads_at_load_address() {
+    let nsf = Nsf::from_bytes(&make_nsf(1, 1, 0x8100, [0; 8], &COUNTER_PROGRAM));
+    let mut cart = Cartridge::from_nsf(&nsf);
+
+    let mut value = 0xFF;
+    cart.cpu_read(0x80FF, &mut value);
+    assert_eq!(value, 0x00);
+    cart.cpu_read(0x8103, &mut value);
+    assert_eq!(value, 0xE6);
+}
+
+#[test]
+fn test_play_called_at_header_rate() {
+    let nsf = Nsf::from_bytes(&make_nsf(3, 2, 0x8000, [0; 8], &COUNTER_PROGRAM));
+    let mut player = NsfPlayer::new(nsf);
+    assert_eq!(player.track(), 1);
+    assert_eq!(player.cpu.get_state().cpu_ram[0x00], 1);
+
+    let samples = player.render(1.0);
+    assert_eq!(samples.len(), 44100);
+    let calls = player.cpu.get_state().cpu_ram[0x01];
+    assert!(calls >= 59 && calls <= 61, "{} PLAY calls", calls);
+}
+
+#[test]
+fn test_play_speed_of_zero_uses_the_frame_rate() {
+    let mut image = make_nsf(1, 1, 0x8000, [0; 8], &COUNTER_PROGRAM);
+    image[0x6E..0x70].copy_from_slice(&[0, 0]);
+    let nsf = Nsf::from_bytes(&image);
+    assert_eq!(nsf.play_speed(), 16639);
+
+    let mut player = NsfPlayer::new(nsf);
+    player.render(1.0);
+    let calls = player.cpu.get_state().cpu_ram[0x01];
+    assert!(calls >= 59 && calls <= 61, "{} PLAY calls", calls);
+}
+
+#[test]
+fn test_track_next_and_previous_wrap() {
+    let nsf = Nsf::from_bytes(&make_nsf(3, 1, 0x8000, [0; 8], &COUNTER_PROGRAM));
+    let mut player = NsfPlayer::new(nsf);
+    assert_eq!(player.track(), 0);
+
+    player.previous_track();
+    assert_eq!(player.track(), 2);
+    assert_eq!(player.cpu.get_state().cpu_ram[0x00], 2);
+
+    player.next_track();
+    assert_eq!(player.track(), 0);
+    player.next_track();
+    assert_eq!(player.track(), 1);
+    assert_eq!(player.cpu.get_state().cpu_ram[0x00], 1);
+}
+
+#[test]
+fn test_render_and_write_wav() {
+    // INIT starts a constant volume square wave on pulse 1
+    let program = [
+        0xA9, 0xBF, 0x8D, 0x00, 0x40,
+        0xA9, 0xFD, 0x8D, 0x02, 0x40,
+        0xA9, 0x00, 0x8D, 0x03, 0x40,
+        0x60,
+        0x60
+    ];
+    let mut image = make_nsf(1, 1, 0x8000, [0; 8], &program);
+    image[0x0C..0x0E].copy_from_slice(&0x8010u16.to_le_bytes());
+    let mut player = NsfPlayer::new(Nsf::from_bytes(&image));
+
+    let samples = player.render(0.1);
+    let peak = samples.iter().cloned().fold(0.0, f32::max);
+    let low = samples.iter().cloned().fold(1.0, f32::min);
+    assert!(peak - low > 0.1);
+
+    let path = std::env::temp_dir().join("nsf_player_test.wav");
+    let path = path.to_str().unwrap();
+    write_wav(path, &samples, 44100).unwrap();
+    let wav = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 44100);
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+}
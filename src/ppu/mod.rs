@@ -1,91 +1,211 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::bus::Bus;
 use crate::state::State;
-use graphics::{Image};
 use image::Rgba;
-use rand::Rng;
-use crate::display::{EMU_HEIGHT, EMU_WIDTH};
+
+#[cfg(test)]
+mod tests;
+
+pub(crate) const EMU_WIDTH: u32 = 256;
+pub(crate) const EMU_HEIGHT: u32 = 240;
+
+/**
+ * The picture as the PPU draws it, row by row from the top left. Every dot keeps both its
+ * palette index and the RGBA colour the index maps to, frontends use whichever suits them.
+ */
+pub struct Screen {
+    indices: Vec<u8>,
+    rgba: Vec<u8>,
+}
+
+impl Screen {
+
+    // Starts out black, $0F in the palette
+    pub(crate) fn new() -> Screen {
+        Screen {
+            indices: vec![0x0F; (EMU_WIDTH * EMU_HEIGHT) as usize],
+            rgba: [0, 0, 0, 255].repeat((EMU_WIDTH * EMU_HEIGHT) as usize)
+        }
+    }
+
+    pub(crate) fn set_pixel(&mut self, x: usize, y: usize, index: u8, colour: Rgba<u8>) {
+        let dot = y * EMU_WIDTH as usize + x;
+        self.indices[dot] = index;
+        self.rgba[dot * 4..dot * 4 + 4].copy_from_slice(&colour.0);
+    }
+
+    // One palette index per dot, 256 x 240
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    // Four bytes per dot, 256 x 240
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        let dot = (y * EMU_WIDTH + x) as usize * 4;
+        Rgba([self.rgba[dot], self.rgba[dot + 1], self.rgba[dot + 2], self.rgba[dot + 3]])
+    }
+}
+
+// NTSC frame: 341 dots a line, 240 visible lines, post render, 20 lines of vertical blank
+// and the pre-render line
+pub(crate) const DOTS_PER_LINE: u32 = 341;
+pub(crate) const LINES_PER_FRAME: u32 = 262;
+pub(crate) const VBLANK_LINE: u32 = 241;
+pub(crate) const PRE_RENDER_LINE: u32 = 261;
+
+// PPUSTATUS bit set during vertical blank, PPUCTRL bit that turns it into an NMI
+const STATUS_VBLANK: u8 = 0x80;
+const CTRL_NMI: u8 = 0x80;
+// PPUMASK background and sprite enables
+const MASK_RENDERING: u8 = 0x18;
+// PPUCTRL VRAM increment, across (1) or down (32)
+const CTRL_INCREMENT: u8 = 0x04;
+// PPUCTRL pattern tables for sprites and the background, and 8 x 16 sprites
+const CTRL_SPRITE_TABLE: u8 = 0x08;
+const CTRL_BACKGROUND_TABLE: u8 = 0x10;
+const CTRL_TALL_SPRITES: u8 = 0x20;
 
 pub struct Ppu {
-    pub(crate) state: Option<Rc<RefCell<State>>>,
     pub pallet: Vec<Rgba<u8>>,
-    pub screen: Vec<Vec<Rgba<u8>>>,
+    pub screen: Screen,
     pub cycle: u32,
     pub scan_line: u32,
     pub frame_complete: bool,
+    odd_frame: bool,
+    // Tile number from the last name table fetch, its pattern bytes come next
+    tile: u8,
 }
 
 impl Ppu {
 
     pub fn new() -> Ppu {
-        let mut screen: Vec<Vec<Rgba<u8>>> = vec![];
-
-        for y in 0..EMU_HEIGHT {
-            let mut row = vec![];
-            for x in 0..EMU_WIDTH {
-                row.push(image::Rgba([0, 0, 0, 255]));
-            }
-            screen.push(row);
-        }
-
         Ppu {
             frame_complete: false,
             cycle: 0,
             scan_line: 0,
-            screen,
+            screen: Screen::new(),
             pallet: create_pallet(),
-            state: None
+            odd_frame: false,
+            tile: 0
         }
     }
 
-    pub fn clock(&mut self) {
-        let mut rng = rand::thread_rng();
+    // One dot, drawn into the screen while it is in the visible 256 x 240
+    pub fn clock(&mut self, state: &mut State) {
+        self.fetch(state);
 
-        let rnd: usize = rng.gen();
-        let pixel = if rnd % 2 == 0 {
-            0x3F
-        } else {
-            0x30
-        };
+        if self.scan_line < EMU_HEIGHT && self.cycle >= 1 && self.cycle <= EMU_WIDTH {
+            // Backgrounds and sprites are not drawn yet, every dot shows the backdrop colour
+            let pixel = ppu_peek(state, 0x3F00) & 0x3F;
+            self.screen.set_pixel((self.cycle - 1) as usize, self.scan_line as usize, pixel, self.pallet[pixel as usize]);
+        }
+
+        if self.cycle == 1 {
+            if self.scan_line == VBLANK_LINE {
+                state.ppu_ram[2] |= STATUS_VBLANK;
+            } else if self.scan_line == PRE_RENDER_LINE {
+                state.ppu_ram[2] &= !STATUS_VBLANK;
+            }
+        }
 
-        let x = (self.cycle) as usize;
-        let y = self.scan_line as usize;
-        self.state
-            .as_ref()
-            .expect("Missing State")
-            .as_ref()
-            .borrow_mut()
-            .screen[y][x] = self.pallet[pixel];
         self.cycle += 1;
-        if self.cycle == EMU_WIDTH - 1 {
+        // With rendering on, odd frames drop the last dot of the pre-render line
+        let skip = self.odd_frame && self.scan_line == PRE_RENDER_LINE && self.cycle == DOTS_PER_LINE - 1
+            && state.ppu_ram[1] & MASK_RENDERING > 0;
+        if self.cycle == DOTS_PER_LINE || skip {
             self.cycle = 0;
             self.scan_line += 1;
-            if self.scan_line == EMU_HEIGHT - 1 {
+            if self.scan_line == LINES_PER_FRAME {
                 self.scan_line = 0;
+                self.odd_frame = !self.odd_frame;
                 self.frame_complete = true;
             }
         }
-        // Fake some noise for now
-        // sprScreen.SetPixel(cycle - 1, scanline, palScreen[(rand() % 2) ? 0x3F : 0x30]);
-        //
-        // // Advance renderer - it never stops, it's relentless
-        // cycle++;
-        // if (cycle >= 341)
-        // {
-        //     cycle = 0;
-        //     scanline++;
-        //     if (scanline >= 261)
-        //     {
-        //         scanline = -1;
-        //         frame_complete = true;
-        //     }
-        // }
+        state.ppu_scan_line = self.scan_line;
+        state.ppu_cycle = self.cycle;
+    }
+
+    /**
+     * The memory fetches rendering makes, through ppu_read so mappers that count them (MMC5)
+     * or watch the pattern addresses (MMC2 / MMC4) see the same sequence as on the console.
+     * Every tile takes 8 dots, name table, attribute, pattern low, pattern high, one read
+     * every other dot. Dots 1-256 fetch columns 2-33 of this line, 257-320 the eight sprite
+     * slots, 321-336 the first two columns of the next line and 337 / 339 re-read its
+     * third name table byte. Nothing is drawn from them yet.
+     */
+    fn fetch(&mut self, state: &mut State) {
+        let rendering_line = self.scan_line < EMU_HEIGHT || self.scan_line == PRE_RENDER_LINE;
+        if state.ppu_ram[1] & MASK_RENDERING == 0 || !rendering_line || self.cycle.is_multiple_of(2) {
+            return;
+        }
+
+        let y = self.scan_line % EMU_HEIGHT;
+        let next_y = (self.scan_line + 1) % LINES_PER_FRAME % EMU_HEIGHT;
+        let step = (self.cycle - 1) % 8;
+        let (column, y) = match self.cycle {
+            1..=256 => ((self.cycle - 1) / 8 + 2, y),
+            257..=320 => {
+                // Sprite slots, two name table reads that are thrown away then tile $FF
+                if step < 4 {
+                    ppu_read(state, name_table_address(state, 0, next_y));
+                } else {
+                    let addr = sprite_pattern_address(state, 0xFF) + if step == 6 { 8 } else { 0 };
+                    ppu_read(state, addr);
+                }
+                return;
+            }
+            321..=336 => ((self.cycle - 321) / 8, next_y),
+            337 | 339 => {
+                ppu_read(state, name_table_address(state, 2, next_y));
+                return;
+            }
+            _ => return
+        };
+
+        match step {
+            0 => self.tile = ppu_read(state, name_table_address(state, column, y)),
+            2 => {
+                ppu_read(state, attribute_address(state, column, y));
+            }
+            _ => {
+                let table = if state.ppu_ram[0] & CTRL_BACKGROUND_TABLE > 0 { 0x1000 } else { 0x0000 };
+                let plane = if step == 6 { 8 } else { 0 };
+                ppu_read(state, table | (self.tile as u16) << 4 | plane | (y & 0x07) as u16);
+            }
+        }
     }
 }
 
-pub(crate) fn cpu_read(state: &mut State, addr: u16, read_only: bool) -> u8 {
-    let data = 0x00;
+// Name table byte for a tile column, columns past 31 run on into the next table across
+fn name_table_address(state: &State, column: u32, y: u32) -> u16 {
+    let table = (state.ppu_ram[0] & 0x03) as u16 ^ if column >= 32 { 1 } else { 0 };
+    return 0x2000 | table << 10 | ((y / 8) << 5) as u16 | (column % 32) as u16;
+}
+
+fn attribute_address(state: &State, column: u32, y: u32) -> u16 {
+    let table = name_table_address(state, column, y) & 0x2C00;
+    return table | 0x03C0 | ((y / 32) << 3) as u16 | ((column % 32) / 4) as u16;
+}
+
+// First row of a sprite tile, 8 x 16 sprites take even tiles from the table bit 0 picks
+fn sprite_pattern_address(state: &State, tile: u8) -> u16 {
+    if state.ppu_ram[0] & CTRL_TALL_SPRITES > 0 {
+        return (tile as u16 & 0x01) << 12 | ((tile & 0xFE) as u16) << 4;
+    }
+    let table = if state.ppu_ram[0] & CTRL_SPRITE_TABLE > 0 { 0x1000 } else { 0x0000 };
+    return table | (tile as u16) << 4;
+}
+
+// The PPU holds NMI low for as long as vertical blank and the NMI enable are both set
+pub(crate) fn nmi_output(state: &State) -> bool {
+    return state.ppu_ram[2] & STATUS_VBLANK > 0 && state.ppu_ram[0] & CTRL_NMI > 0;
+}
+
+// Register reads, until the registers are emulated a read gives back what was last written
+pub(crate) fn cpu_read(state: &mut State, addr: u16) -> u8 {
+    let mut data = state.ppu_ram[addr as usize];
 
     match addr {
         // Control
@@ -96,9 +216,10 @@ pub(crate) fn cpu_read(state: &mut State, addr: u16, read_only: bool) -> u8 {
         0x0001 => {
 
         }
-        // Status
+        // Status, reading it ends vertical blank and resets the address latch
         0x0002 => {
-
+            state.ppu_ram[2] &= !STATUS_VBLANK;
+            state.ppu_address_latch = false;
         }
         // OAM Address
         0x0003 => {
@@ -116,9 +237,13 @@ pub(crate) fn cpu_read(state: &mut State, addr: u16, read_only: bool) -> u8 {
         0x0006 => {
 
         }
-        // PPU Data
+        // PPU Data, goes through the cartridge like a rendering fetch so mappers see it
         0x0007 => {
-
+            data = read_data(state);
+            // Under the palette the buffer picks up the name table byte it covers
+            let addr = if state.ppu_address >= 0x3F00 { state.ppu_address - 0x1000 } else { state.ppu_address };
+            state.ppu_data_buffer = ppu_read(state, addr);
+            increment_address(state);
         }
         _ => {}
     }
@@ -126,6 +251,33 @@ pub(crate) fn cpu_read(state: &mut State, addr: u16, read_only: bool) -> u8 {
     data
 }
 
+/**
+ * What cpu_read would return. Reading status clears vertical blank and the address latch,
+ * reading data moves the PPU address on, a peek does neither.
+ */
+pub(crate) fn cpu_peek(state: &State, addr: u16) -> u8 {
+    if addr == 0x0007 {
+        return read_data(state);
+    }
+    return state.ppu_ram[addr as usize];
+}
+
+/**
+ * $2007 hands back what the previous read left in the buffer, the byte at the address only
+ * arrives on the next read. Palette reads skip the wait.
+ */
+fn read_data(state: &State) -> u8 {
+    if state.ppu_address >= 0x3F00 {
+        return ppu_peek(state, state.ppu_address);
+    }
+    return state.ppu_data_buffer;
+}
+
+fn increment_address(state: &mut State) {
+    let increment = if state.ppu_ram[0] & CTRL_INCREMENT > 0 { 32 } else { 1 };
+    state.ppu_address = state.ppu_address.wrapping_add(increment) & 0x3FFF;
+}
+
 pub(crate) fn cpu_write(state: &mut State, addr: u16, data: u8) {
     match addr {
         // Control
@@ -152,18 +304,105 @@ pub(crate) fn cpu_write(state: &mut State, addr: u16, data: u8) {
         0x0005 => {
 
         }
-        // PPU Address
+        // PPU Address, high byte first
         0x0006 => {
-
+            state.ppu_address = if state.ppu_address_latch {
+                (state.ppu_address & 0xFF00) | data as u16
+            } else {
+                ((data as u16) << 8 | (state.ppu_address & 0x00FF)) & 0x3FFF
+            };
+            state.ppu_address_latch = !state.ppu_address_latch;
         }
         // PPU Data
         0x0007 => {
-
+            ppu_write(state, state.ppu_address, data);
+            increment_address(state);
         }
         _ => {}
     }
 }
 
+// Reads from the PPU's own address space, pattern tables are fetched through the
+// cartridge so mappers can watch (and react to) every tile fetch
+pub(crate) fn ppu_read(state: &mut State, addr: u16) -> u8 {
+    let addr = addr & 0x3FFF;
+    let mut data = 0x00;
+
+    if let Some(cart) = state.cartridge.as_mut() {
+        if cart.ppu_read(addr, &mut data) {
+            return data;
+        }
+    }
+
+    return internal_read(state, addr);
+}
+
+// ppu_read without tripping the mapper, for pattern and name table viewers
+pub(crate) fn ppu_peek(state: &State, addr: u16) -> u8 {
+    let addr = addr & 0x3FFF;
+    let mut data = 0x00;
+
+    if let Some(cart) = state.cartridge.as_ref() {
+        if cart.ppu_peek(addr, &mut data) {
+            return data;
+        }
+    }
+
+    return internal_read(state, addr);
+}
+
+pub(crate) fn ppu_write(state: &mut State, addr: u16, data: u8) {
+    let addr = addr & 0x3FFF;
+
+    if let Some(cart) = state.cartridge.as_mut() {
+        if cart.ppu_write(addr, data) {
+            return;
+        }
+    }
+
+    if addr <= 0x1FFF {
+        // Pattern memory is read only unless the cartridge claimed it
+    } else if addr <= 0x3EFF {
+        let (table, offset) = name_table_index(state, addr);
+        state.ppu_name_tables[table][offset] = data;
+    } else {
+        state.ppu_palette_table[palette_index(addr)] = data;
+    }
+}
+
+// Name tables and palettes when the cartridge leaves them to the console
+fn internal_read(state: &State, addr: u16) -> u8 {
+    if addr <= 0x1FFF {
+        // No cartridge connected, nothing drives the pattern bus
+        return 0x00;
+    } else if addr <= 0x3EFF {
+        let (table, offset) = name_table_index(state, addr);
+        return state.ppu_name_tables[table][offset];
+    }
+    return state.ppu_palette_table[palette_index(addr)];
+}
+
+fn name_table_index(state: &State, addr: u16) -> (usize, usize) {
+    let addr = addr & 0x0FFF;
+    let offset = (addr & 0x03FF) as usize;
+    let quadrant = (addr / 0x0400) as usize;
+
+    let table = match state.cartridge.as_ref() {
+        Some(cart) => cart.ciram_page(quadrant),
+        None => quadrant & 0x01
+    };
+    (table, offset)
+}
+
+fn palette_index(addr: u16) -> usize {
+    let mut addr = addr & 0x001F;
+    // $3F10/$3F14/$3F18/$3F1C mirror the background entries
+    if addr == 0x0010 || addr == 0x0014 || addr == 0x0018 || addr == 0x001C {
+        addr -= 0x0010;
+    }
+    addr as usize
+}
+
 fn create_pallet() -> Vec<Rgba<u8>> {
     vec![
         image::Rgba([84, 84, 84, 255]),
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::cartridge::Cartridge;
+use crate::Nes;
+use crate::ppu::{EMU_HEIGHT, EMU_WIDTH};
+
+// ROMs that only report on screen, run for a number of frames and compared with assets/goldens/<name>.png
+const GOLDEN_ROMS: &[(&str, usize)] = &[
+    // Backdrop colour $21 once the NMI has stepped it that far
+    ("backdrop.nes", 36),
+];
+
+const ROMS_DIR_VAR: &str = "SCREEN_ROMS";
+const ROMS_DIR: &str = "assets/screen_roms";
+const GOLDENS_DIR: &str = "assets/goldens";
+// Set to write the current frame as the new golden instead of comparing
+const UPDATE_VAR: &str = "UPDATE_GOLDENS";
+
+fn frame_hash(rgba: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(rgba);
+    return hasher.finalize();
+}
+
+fn to_image(rgba: &[u8]) -> RgbaImage {
+    return RgbaImage::from_raw(EMU_WIDTH, EMU_HEIGHT, rgba.to_vec()).expect("Frame is not 256 x 240");
+}
+
+/**
+ * Compares a frame with the golden PNG, or writes it as the golden when update is set.
+ * A mismatch saves the frame next to the golden as <name>.actual.png and names both hashes.
+ */
+fn check_golden(name: &str, rgba: &[u8], goldens: &Path, update: bool) -> Result<(), String> {
+    let golden_path = goldens.join(format!("{}.png", name));
+    if update {
+        std::fs::create_dir_all(goldens).map_err(|e| e.to_string())?;
+        return to_image(rgba).save(&golden_path).map_err(|e| e.to_string());
+    }
+
+    let golden = match image::open(&golden_path) {
+        Ok(golden) => golden.to_rgba8().into_raw(),
+        Err(_) => return Err(format!("{} has no golden, run with {}=1 to create it", name, UPDATE_VAR))
+    };
+    let (expected, actual) = (frame_hash(&golden), frame_hash(rgba));
+    if expected == actual {
+        return Ok(());
+    }
+
+    let actual_path = goldens.join(format!("{}.actual.png", name));
+    let _ = to_image(rgba).save(&actual_path);
+    return Err(format!("{} frame {:08X} differs from golden {:08X}, see {}", name, actual, expected, actual_path.display()));
+}
+
+// Runs the ROM headless and hands back the picture after the last frame
+fn run_frames(rom: &[u8], frames: usize) -> Vec<u8> {
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cartridge::from_bytes(rom));
+    for _ in 1..frames {
+        nes.run_frame();
+    }
+    return nes.run_frame().rgba.to_vec();
+}
+
+#[test]
+fn test_screen_roms_match_goldens() {
+    let dir = std::env::var(ROMS_DIR_VAR).unwrap_or_else(|_| ROMS_DIR.to_string());
+    let update = std::env::var(UPDATE_VAR).is_ok();
+
+    let mut failures = vec![];
+    for (file, frames) in GOLDEN_ROMS {
+        let rom = std::fs::read(Path::new(&dir).join(file))
+            .unwrap_or_else(|err| panic!("Could not read {} from {}: {}, set {} to the screen ROMs", file, dir, err, ROMS_DIR_VAR));
+        let name = Path::new(file).file_stem().unwrap().to_string_lossy().to_string();
+        if let Err(message) = check_golden(&name, &run_frames(&rom, *frames), Path::new(GOLDENS_DIR), update) {
+            failures.push(message);
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_backdrop_rom_draws_one_colour() {
+    let rom = std::fs::read(Path::new(ROMS_DIR).join("backdrop.nes")).unwrap();
+    let colour = crate::ppu::Ppu::new().pallet[0x21].0;
+    let rgba = run_frames(&rom, 36);
+    assert!(rgba.chunks(4).all(|dot| dot == colour));
+}
+
+#[test]
+fn test_golden_round_trip_and_mismatch() {
+    let goldens = std::env::temp_dir().join(format!("nes_goldens_{}", std::process::id()));
+    let mut screen = [0, 0, 0, 255].repeat((EMU_WIDTH * EMU_HEIGHT) as usize);
+    let dot = (10 * EMU_WIDTH + 20) as usize * 4;
+    screen[dot..dot + 4].copy_from_slice(&[236, 88, 180, 255]);
+
+    assert!(check_golden("checker", &screen, &goldens, false).unwrap_err().contains("has no golden"));
+    assert_eq!(check_golden("checker", &screen, &goldens, true), Ok(()));
+    assert_eq!(check_golden("checker", &screen, &goldens, false), Ok(()));
+
+    let last = screen.len() - 4;
+    screen[last..].copy_from_slice(&[255, 255, 255, 255]);
+    let error = check_golden("checker", &screen, &goldens, false).unwrap_err();
+    assert!(error.starts_with("checker frame"), "{}", error);
+
+    let _ = std::fs::remove_dir_all(&goldens);
+}
+
@@ -0,0 +1,123 @@
+mod golden;
+
+use crate::bus::{mem_read, mem_write};
+use crate::cartridge::Cartridge;
+use crate::ppu::{cpu_peek, cpu_read, cpu_write, nmi_output, ppu_peek, ppu_write, Ppu, PRE_RENDER_LINE, VBLANK_LINE};
+use crate::state::State;
+
+fn clock_to(ppu: &mut Ppu, state: &mut State, line: u32, dot: u32) {
+    while ppu.scan_line != line || ppu.cycle != dot {
+        ppu.clock(state);
+    }
+}
+
+#[test]
+fn test_vertical_blank_and_nmi() {
+    let mut ppu = Ppu::new();
+    let mut state = State::new();
+
+    clock_to(&mut ppu, &mut state, VBLANK_LINE, 1);
+    assert_eq!(state.ppu_ram[2] & 0x80, 0);
+    ppu.clock(&mut state);
+    assert_eq!(state.ppu_ram[2] & 0x80, 0x80);
+    assert!(!nmi_output(&state));
+    state.ppu_ram[0] = 0x80;
+    assert!(nmi_output(&state));
+
+    // Reading the status ends it early
+    assert_eq!(cpu_read(&mut state, 2) & 0x80, 0x80);
+    assert_eq!(state.ppu_ram[2] & 0x80, 0);
+    assert!(!nmi_output(&state));
+
+    // Otherwise it lasts until the pre-render line
+    clock_to(&mut ppu, &mut state, 0, 0);
+    clock_to(&mut ppu, &mut state, VBLANK_LINE, 2);
+    clock_to(&mut ppu, &mut state, PRE_RENDER_LINE, 1);
+    assert_eq!(state.ppu_ram[2] & 0x80, 0x80);
+    ppu.clock(&mut state);
+    assert_eq!(state.ppu_ram[2] & 0x80, 0);
+}
+
+#[test]
+fn test_ppu_data_reads_are_buffered() {
+    let mut state = State::new();
+    ppu_write(&mut state, 0x2005, 0x11);
+    ppu_write(&mut state, 0x2006, 0x22);
+    ppu_write(&mut state, 0x2F00, 0x33);
+    ppu_write(&mut state, 0x3F00, 0x0F);
+
+    cpu_write(&mut state, 6, 0x20);
+    cpu_write(&mut state, 6, 0x05);
+    cpu_read(&mut state, 7);
+    assert_eq!(cpu_peek(&state, 7), 0x11);
+    assert_eq!(cpu_read(&mut state, 7), 0x11);
+    assert_eq!(cpu_read(&mut state, 7), 0x22);
+
+    // Palette bytes come straight back and leave the name table underneath in the buffer
+    cpu_write(&mut state, 6, 0x3F);
+    cpu_write(&mut state, 6, 0x00);
+    assert_eq!(cpu_read(&mut state, 7), 0x0F);
+    cpu_write(&mut state, 6, 0x20);
+    cpu_write(&mut state, 6, 0x00);
+    assert_eq!(cpu_read(&mut state, 7), 0x33);
+}
+
+// An iNES image with every 8 KB PRG bank and 4 KB CHR bank filled with its index
+fn cartridge_state(mapper_id: u8, prg_chunks: u8, chr_chunks: u8) -> State {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, prg_chunks, chr_chunks, (mapper_id & 0x0F) << 4, mapper_id & 0xF0, 0, 0, 0, 0, 0, 0, 0, 0];
+    for bank in 0..(prg_chunks as usize * 2) {
+        rom.extend(vec![bank as u8; 0x2000]);
+    }
+    for bank in 0..(chr_chunks as usize * 2) {
+        rom.extend(vec![bank as u8; 0x1000]);
+    }
+    let mut state = State::new();
+    state.connect_cartridge(Some(Cartridge::from_bytes(&rom)));
+    state
+}
+
+#[test]
+fn test_background_fetch_trips_mmc2_latch() {
+    let mut ppu = Ppu::new();
+    let mut state = cartridge_state(9, 8, 16);
+    mem_write(&mut state, 0xB000, 2);
+    mem_write(&mut state, 0xC000, 3);
+    // Tile $FD in column 5 of the first two rows, the top row of its high plane is
+    // fetched from $0FD8 at dot 31 of lines 0 and 8
+    ppu_write(&mut state, 0x2005, 0xFD);
+    ppu_write(&mut state, 0x2025, 0xFD);
+
+    clock_to(&mut ppu, &mut state, 0, 31);
+    assert_eq!(ppu_peek(&state, 0x0000), 3);
+
+    // Nothing is fetched with rendering off
+    ppu.clock(&mut state);
+    assert_eq!(ppu_peek(&state, 0x0000), 3);
+
+    mem_write(&mut state, 0x2001, 0x08);
+    clock_to(&mut ppu, &mut state, 8, 31);
+    assert_eq!(ppu_peek(&state, 0x0000), 3);
+    ppu.clock(&mut state);
+    assert_eq!(ppu_peek(&state, 0x0000), 2);
+}
+
+#[test]
+fn test_rendering_fetches_clock_mmc5_scanline_irq() {
+    let mut ppu = Ppu::new();
+    let mut state = cartridge_state(5, 8, 8);
+    mem_write(&mut state, 0x2001, 0x18);
+    mem_write(&mut state, 0x5203, 3);
+    mem_write(&mut state, 0x5204, 0x80);
+
+    // Start from a clean frame, as the CPU would after the NMI vector fetch
+    clock_to(&mut ppu, &mut state, PRE_RENDER_LINE, 0);
+    mem_read(&mut state, 0x5204);
+    mem_read(&mut state, 0xFFFA);
+
+    clock_to(&mut ppu, &mut state, 3, 1);
+    assert!(!state.get_cartridge().mapper.irq_state());
+    // The first name table fetch of line 3 completes the match
+    ppu.clock(&mut state);
+    assert!(state.get_cartridge().mapper.irq_state());
+    assert_eq!(mem_read(&mut state, 0x5204) & 0x40, 0x40);
+}
@@ -1,13 +1,6 @@
-use std::cell::{Ref, RefCell};
-use std::rc::Rc;
-
+use crate::apu::Apu;
 use crate::bus::mem_write;
 use crate::cartridge::Cartridge;
-use crate::mapper::Mapper;
-use crate::mapper::mapper0::Mapper0;
-use crate::display::{EMU_HEIGHT, EMU_WIDTH};
-use image::{ImageBuffer, Rgba};
-use opengl_graphics::{Texture, TextureSettings};
 
 pub struct State {
     pub(crate) cpu_ram: Vec<u8>,
@@ -15,63 +8,72 @@ pub struct State {
     pub(crate) code_end: usize,
     pub(crate) ppu_name_tables: Vec<Vec<u8>>,
     pub(crate) ppu_palette_table: Vec<u8>,
+    // VRAM address set through $2006, and whether the next write is its low byte
+    pub(crate) ppu_address: u16,
+    pub(crate) ppu_address_latch: bool,
+    // $2007 reads come out one read late through this
+    pub(crate) ppu_data_buffer: u8,
+    // Where the PPU is, kept up to date by Ppu::clock for the CPU trace
+    pub(crate) ppu_scan_line: u32,
+    pub(crate) ppu_cycle: u32,
     pub(crate) n_system_clock_counter: usize,
-    pub cartridge: Option<Rc<RefCell<Cartridge>>>,
+    pub cartridge: Option<Cartridge>,
+    pub apu: Apu,
     pub mapper: usize,
-    pub screen: Vec<Vec<Rgba<u8>>>,
 }
 
 impl State {
 
     pub fn new() -> State {
-        let mut screen: Vec<Vec<Rgba<u8>>> = vec![];
-
-        for _ in 0..EMU_HEIGHT {
-            let mut row = vec![];
-            for _ in 0..EMU_WIDTH {
-                row.push(image::Rgba([0, 0, 0, 255]));
-            }
-            screen.push(row);
-        }
-
         State {
-            screen,
             cpu_ram: vec![0; 64 * 1024],
             ppu_ram: vec![0; 2048],
             code_end: 0,
             ppu_name_tables: vec![vec![0; 1024], vec![0; 1024]],
             ppu_palette_table: vec![0; 32],
+            ppu_address: 0,
+            ppu_address_latch: false,
+            ppu_data_buffer: 0,
+            ppu_scan_line: 0,
+            ppu_cycle: 0,
             n_system_clock_counter: 0,
             cartridge: None,
+            apu: Apu::new(),
             mapper: 0
         }
     }
 
-    pub fn get_cartridge(&self) -> Ref<'_, Cartridge> {
-        self.cartridge.as_ref().expect("Missing cart").as_ref().borrow()
+    pub fn get_cartridge(&self) -> &Cartridge {
+        self.cartridge.as_ref().expect("Missing cart")
     }
 
-    pub fn connect_cartridge(&mut self, cartridge: Option<Rc<RefCell<Cartridge>>>) {
-        self.cartridge = cartridge;
+    pub fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        self.cartridge.as_mut().expect("Missing cart")
     }
 
-    pub fn get_mapper(&self) -> impl Mapper {
-        let cart = self.cartridge.as_ref().expect("Missing cart").as_ref().borrow();
-        if cart.n_mapper_id == 0 {
-            Mapper0 {}
-        } else {
-            Mapper0 {}
-        }
+    /**
+     * Where code loaded without a cartridge ends, the machine stops with ProgramEnd once PC
+     * gets there and will not run at all while it is 0. A cartridge runs until it is
+     * switched off.
+     */
+    pub(crate) fn program_end(&self) -> Option<usize> {
+        return match self.cartridge {
+            Some(_) => None,
+            None => Some(self.code_end)
+        };
+    }
+
+    pub fn connect_cartridge(&mut self, cartridge: Option<Cartridge>) {
+        self.cartridge = cartridge;
     }
 
     pub fn load(&mut self, code: Vec<u8>, offset: u16) {
         let end = code.len().clone();
         self.code_end = end + offset as usize;
 
-        let mut i = 0;
-        for item in code {
-            mem_write(self, i + offset, item);
-            i += 1;
+        // A full 64K image wraps back round to $0000
+        for (i, item) in code.into_iter().enumerate() {
+            mem_write(self, offset.wrapping_add(i as u16), item);
         }
 
         let offset_bytes = offset.to_be_bytes();